@@ -35,6 +35,8 @@ impl<const R: u8> BitAnd<u8> for Register<R> {
 
 /// USB Controller
 pub const USBCON: Register<0xD8> = Register();
+/// USB Status Register
+pub const USBSTA: Register<0xD9> = Register();
 /// External Interupt Mask Register
 pub const EIMSK: Register<0x3D> = Register();
 /// External Interupt Control Register A
@@ -50,6 +52,10 @@ pub const TCCR1A: Register<0x80> = Register();
 pub const TIMSK0: Register<0x6E> = Register();
 /// SREG
 pub const SREG: Register<0x5F> = Register();
+/// Timer 0 Counter Register
+pub const TCNT0: Register<0x46> = Register();
+/// Timer 0 Interrupt Flag Register
+pub const TIFR0: Register<0x35> = Register();
 /// Output Compare Register
 pub const OCR0A: Register<0x47> = Register();
 /// Timer 0 Counter Control Register
@@ -62,6 +68,35 @@ pub const WDTCSR: Register<0x60> = Register();
 
 pub const MCUSR: Register<0x54> = Register();
 
+/// Sleep Mode Control Register
+pub const SMCR: Register<0x53> = Register();
+/// Pin Change Interrupt Control Register
+pub const PCICR: Register<0x68> = Register();
+/// Pin Change Interrupt Flag Register
+pub const PCIFR: Register<0x3B> = Register();
+/// Pin Change Mask Register 0, one bit per pin of port B
+pub const PCMSK0: Register<0x6B> = Register();
+/// SPI Control Register
+pub const SPCR: Register<0x4C> = Register();
+/// SPI Status Register
+pub const SPSR: Register<0x4D> = Register();
+/// SPI Data Register
+pub const SPDR: Register<0x4E> = Register();
+/// ADC Multiplexer Selection Register
+pub const ADMUX: Register<0x7C> = Register();
+/// ADC Control and Status Register A
+pub const ADCSRA: Register<0x7A> = Register();
+/// ADC Control and Status Register B
+pub const ADCSRB: Register<0x7B> = Register();
+/// ADC Data Register Low byte, to read first
+pub const ADCL: Register<0x78> = Register();
+/// ADC Data Register High byte
+pub const ADCH: Register<0x79> = Register();
+/// Digital Input Disable Register 0, one bit per ADC channel 0 to 7
+pub const DIDR0: Register<0x7E> = Register();
+/// Digital Input Disable Register 2, one bit per ADC channel 8 to 13
+pub const DIDR2: Register<0x7D> = Register();
+
 
 // # Registers values
 
@@ -69,6 +104,8 @@ pub const MCUSR: Register<0x54> = Register();
 pub const WGM01: u8 = 1 << 1;
 /// Timer/Counter0 Output Compare Match A Interrupt Enable
 pub const OCIE0A: u8 = 1 << 1;
+/// Timer/Counter0 Output Compare Match A Flag
+pub const OCF0A: u8 = 1 << 1;
 
 /// Clock Select
 pub const CS00: u8 = 1 << 0;
@@ -82,6 +119,43 @@ pub const SREG_I: u8 = 1 << 7;
 
 /// USB Enable
 pub const USBE: u8 = 1 << 7;
+/// VBUS Pad Enable
+pub const OTGPADE: u8 = 1 << 4;
+/// VBUS line state
+pub const VBUS: u8 = 1 << 0;
+
+/// Sleep Enable, the sleep mode bits being left to 0 for the idle mode
+pub const SE: u8 = 1 << 0;
+/// Pin Change Interrupt Enable 0
+pub const PCIE0: u8 = 1 << 0;
+/// Pin Change Interrupt Flag 0
+pub const PCIF0: u8 = 1 << 0;
+
+/// SPI Enable
+pub const SPE: u8 = 1 << 6;
+/// SPI Data Order, LSB first when set
+pub const DORD: u8 = 1 << 5;
+/// SPI Master Select
+pub const MSTR: u8 = 1 << 4;
+/// SPI Clock Polarity
+pub const CPOL: u8 = 1 << 3;
+/// SPI Clock Phase
+pub const CPHA: u8 = 1 << 2;
+/// SPI Interrupt Flag, set once a transfer is complete
+pub const SPIF: u8 = 1 << 7;
+/// SPI Double Speed
+pub const SPI2X: u8 = 1 << 0;
+
+/// ADC Reference Selection, AVcc being the reference voltage
+pub const REFS0: u8 = 1 << 6;
+/// ADC Enable
+pub const ADEN: u8 = 1 << 7;
+/// ADC Start Conversion, cleared once the conversion is done
+pub const ADSC: u8 = 1 << 6;
+/// ADC Prescaler Select, all three bits dividing the clock by 128
+pub const ADPS: u8 = 0b111;
+/// ADC Multiplexer bit 5, selecting the channels 8 to 13
+pub const MUX5: u8 = 1 << 5;
 
 /// Watch Dog system reset Enable
 pub const WDE: u8 = 1 << 3;
@@ -15,10 +15,8 @@
 use avr_base::pins::{B1, B2, B3, B4, B5, B6, C6, D2, D5, D7, E6, F4, F5, F6, F7, Pin};
 use keyboard_macros::progmem;
 use keyboard_macros::{entry, image_dimension, include_font_plate};
-use omk::keymap::{CustomKey, Key, Keymap};
-use omk::keys::{VOLUME_DOWN, VOLUME_UP};
+use omk::keymap::{EncoderMap, Keymap};
 use omk::progmem::ProgmemRef;
-use omk::usb::hid_task;
 use omk::{Keyboard, OmkKeyboard, progmem};
 
 type Kb = OmkKeyboard<UserKeyboard>;
@@ -41,6 +39,7 @@ static USER_FONTPLATE: [u8; const { UserKeyboard::FONT_DIM.2 }] =
 impl omk::PrivateConfig for UserKeyboard {
     type const ROWS_PER_HAND: usize = const { Self::MATRIX_ROWS / 2 };
     type const MATRIX_KEYS_COUNT: usize = const { Self::MATRIX_ROWS * Self::MATRIX_COLUMNS };
+    type const ENCODER_COUNT: usize = const { Self::ENCODERS_PER_HAND * 2 };
     type const FONT_SIZE: usize = const { Self::FONT_DIM.2 };
     type const FONT_WIDTH: u8 = const { Self::FONT_DIM.0 };
     type const FONT_HEIGHT: u8 = const { Self::FONT_DIM.1 };
@@ -56,10 +55,9 @@ impl Keyboard for UserKeyboard {
     const COL_PINS: [Pin; Self::MATRIX_COLUMNS] = [F6, F7, B1, B3, B2, B6];
     const RED_LED_PIN: Pin = D5;
     const SOFT_SERIAL_PIN: Pin = D2;
-    const LEFT_ENCODER_PIN1: Pin = F5;
-    const LEFT_ENCODER_PIN2: Pin = F4;
-    const RIGHT_ENCODER_PIN1: Pin = F4;
-    const RIGHT_ENCODER_PIN2: Pin = F5;
+    type const ENCODERS_PER_HAND: usize = 1;
+    const LEFT_ENCODERS: [(Pin, Pin); Self::ENCODERS_PER_HAND] = [(F5, F4)];
+    const RIGHT_ENCODERS: [(Pin, Pin); Self::ENCODERS_PER_HAND] = [(F4, F5)];
     const ROTARY_ENCODER_RESOLUTION: i8 = 1;
 
     const FONT_DIM: (u8, u8, usize) = image_dimension!("examples/images/fontplate.png");
@@ -70,26 +68,7 @@ impl Keyboard for UserKeyboard {
 
     const KEYMAP: progmem::ProgmemRef<Keymap<Self>> = KEYMAP;
 
-    fn rotary_encoder_handler(keyboard: &mut OmkKeyboard<Self>, arg: (i8, i8)) {
-        let mut repeat_press = |key: &Key, repeat: u8| {
-            for _ in 0..repeat {
-                key.on_pressed(keyboard);
-                hid_task();
-                // delay_ms::<1>();
-                key.on_released(keyboard);
-                hid_task();
-                // delay_ms::<1>();
-            }
-        };
-        if omk::is_left() {
-            let (rotary, _) = arg;
-            if rotary > 0 {
-                repeat_press(VOLUME_UP, rotary as u8);
-            } else {
-                repeat_press(VOLUME_DOWN, (-rotary) as u8);
-            }
-        }
-    }
+    const ENCODER_MAP: Option<progmem::ProgmemRef<EncoderMap<Self>>> = Some(ENCODER_MAP);
 
     type MatrixRowType = u8;
 }
@@ -119,3 +98,12 @@ static KEYMAP: Keymap<UserKeyboard> =
     L_SHFT, KC_Z,   VOL_DO, MUTE,   VOL_UP, NO_OP,   KC_N,  KP_1,   KP_2,   KP_3,   SLASH,  L_GUI,
     L_GUI,  L_CTRL, NO_OP,  SPACE,  L_GUI,  KC_A ,   KC_A,  ENTER,R_SHFT,  L_ALT,  DELETE,  R_CTRL,
 ]]};
+
+#[rustfmt::skip]
+#[progmem]
+static ENCODER_MAP: EncoderMap<UserKeyboard> =
+{ use omk::keys::*;
+[
+    [[VOL_DO, VOL_UP], [NO_OP, NO_OP]],
+    [[VOL_DO, VOL_UP], [NO_OP, NO_OP]],
+]};
@@ -18,7 +18,6 @@ use keyboard_macros::progmem;
 use keyboard_macros::{entry, image_dimension, include_font_plate};
 use omk::keymap::Keymap;
 use omk::progmem::ProgmemRef;
-use omk::usb::set_vertical_wheel_delta;
 use omk::{Keyboard, OmkKeyboard, is_left, progmem};
 
 #[cfg(surv_private)]
@@ -53,6 +52,7 @@ static USER_FONTPLATE: [u8; const { UserKeyboard::FONT_DIM.2 }] =
 impl omk::PrivateConfig for UserKeyboard {
     type const ROWS_PER_HAND: usize = const { Self::MATRIX_ROWS / 2 };
     type const MATRIX_KEYS_COUNT: usize = const { Self::MATRIX_ROWS * Self::MATRIX_COLUMNS };
+    type const ENCODER_COUNT: usize = const { Self::ENCODERS_PER_HAND * 2 };
     type const FONT_SIZE: usize = const { Self::FONT_DIM.2 };
     type const FONT_WIDTH: u8 = const { Self::FONT_DIM.0 };
     type const FONT_HEIGHT: u8 = const { Self::FONT_DIM.1 };
@@ -69,10 +69,9 @@ impl Keyboard for UserKeyboard {
     const COL_PINS: [Pin; Self::MATRIX_COLUMNS] = [F6, F7, B1, B3, B2, B6];
     const RED_LED_PIN: Pin = D5;
     const SOFT_SERIAL_PIN: Pin = D2;
-    const LEFT_ENCODER_PIN1: Pin = F5;
-    const LEFT_ENCODER_PIN2: Pin = F4;
-    const RIGHT_ENCODER_PIN1: Pin = F4;
-    const RIGHT_ENCODER_PIN2: Pin = F5;
+    type const ENCODERS_PER_HAND: usize = 1;
+    const LEFT_ENCODERS: [(Pin, Pin); Self::ENCODERS_PER_HAND] = [(F5, F4)];
+    const RIGHT_ENCODERS: [(Pin, Pin); Self::ENCODERS_PER_HAND] = [(F4, F5)];
     const ROTARY_ENCODER_RESOLUTION: i8 = 4;
 
     const FONT_DIM: (u8, u8, usize) = image_dimension!("examples/images/fontplate.png");
@@ -83,17 +82,22 @@ impl Keyboard for UserKeyboard {
 
     const KEYMAP: progmem::ProgmemRef<Keymap<Self>> = KEYMAP;
 
-    fn rotary_encoder_handler(keyboard: &mut OmkKeyboard<Self>, rotary: (i8, i8)) {
+    fn rotary_encoder_handler(keyboard: &mut OmkKeyboard<Self>, rotary: [i8; 2]) {
+        // Encoder of the other half
         if is_left() {
-            keyboard.user.rotary_state += rotary.1;
+            keyboard.user.rotary_state += rotary[1];
+        } else {
+            keyboard.user.rotary_state += rotary[0];
+        }
+        OmkKeyboard::<Self>::draw_u8(keyboard.user.rotary_state as u8, 0, 100);
+    }
 
+    fn rotary_encoder_pulses_handler(keyboard: &mut OmkKeyboard<Self>, pulses: [i8; 2]) {
+        if is_left() {
             // scroll faster in navigation layer
             let multiplier = if keyboard.layer == 1 { 2 } else { 1 };
-            set_vertical_wheel_delta(-rotary.1 * multiplier);
-        } else {
-            keyboard.user.rotary_state += rotary.0;
+            keyboard.scroll_wheel(-pulses[1] * multiplier, Self::ROTARY_ENCODER_RESOLUTION);
         }
-        OmkKeyboard::<Self>::draw_u8(keyboard.user.rotary_state as u8, 0, 100);
     }
 
     type MatrixRowType = u8;
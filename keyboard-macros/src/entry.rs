@@ -11,8 +11,8 @@ use syn::{Ident, ItemFn, parse_macro_input, parse_quote};
 /// Implements the `#[entry]` macro.
 ///
 /// This macro sets up the entry point for the firmware. It defines the panic handler,
-/// interrupt handlers, and initializes the keyboard environment before calling the
-/// user-defined entry function.
+/// interrupt handlers, the USB identity descriptors, and initializes the keyboard environment
+/// before calling the user-defined entry function.
 ///
 /// # Arguments
 /// - `args`: The type of the keyboard (e.g., `MyKeyboard`) passed as an argument to the macro.
@@ -56,6 +56,69 @@ pub fn entry_impl(args: TokenStream, item: TokenStream) -> TokenStream {
             unsafe {<#userkbtype as omk::interrupts::InterruptsHandler<#userkbtype>>::timer_interrupt();}
         }
 
+        #[unsafe(no_mangle)]
+        extern "avr-interrupt" fn __vector_9() {
+            unsafe {<#userkbtype as omk::interrupts::InterruptsHandler<#userkbtype>>::pin_change_interrupt();}
+        }
+
+        #[unsafe(no_mangle)]
+        extern "avr-interrupt" fn __vector_36() {
+            unsafe {<#userkbtype as omk::interrupts::InterruptsHandler<#userkbtype>>::i2c_interrupt();}
+        }
+
+        // USB identity, from the consts of the keyboard
+        #[unsafe(link_section = ".progmem.data")]
+        static _USB_DEVICE_DESCRIPTOR: omk::usb::UsbDescriptorDevice =
+            omk::usb::device_descriptor::<#userkbtype>();
+
+        #[unsafe(link_section = ".progmem.data")]
+        static _USB_MANUFACTURER_STRING: omk::usb::UsbStringDescriptor<
+            { omk::usb::utf16_len(<#userkbtype as omk::Keyboard>::USB_MANUFACTURER) },
+        > = omk::usb_string_descriptor!(<#userkbtype as omk::Keyboard>::USB_MANUFACTURER);
+
+        #[unsafe(link_section = ".progmem.data")]
+        static _USB_PRODUCT_STRING: omk::usb::UsbStringDescriptor<
+            { omk::usb::utf16_len(<#userkbtype as omk::Keyboard>::USB_PRODUCT) },
+        > = omk::usb_string_descriptor!(<#userkbtype as omk::Keyboard>::USB_PRODUCT);
+
+        // USB configuration, with only the interfaces and reports enabled by the consts
+        type _UsbConsoleLayout =
+            omk::usb::ConsoleLayout<{ <#userkbtype as omk::Keyboard>::CONSOLE as u8 }>;
+
+        #[unsafe(link_section = ".progmem.data")]
+        static _USB_CONFIGURATION_DESCRIPTOR: omk::usb::ConfigurationDescriptor<_UsbConsoleLayout> =
+            omk::usb::configuration_descriptor::<#userkbtype, _UsbConsoleLayout>();
+
+        #[unsafe(link_section = ".progmem.data")]
+        static _USB_MOUSE_REPORT_DESCRIPTOR: [u8; omk::usb::mouse_descriptor_size::<#userkbtype>()] =
+            omk::usb::mouse_descriptor::<#userkbtype, { omk::usb::mouse_descriptor_size::<#userkbtype>() }>();
+
+        const _USB_DESCRIPTORS: omk::usb::UsbDescriptors = omk::usb::UsbDescriptors {
+            device: omk::usb::DescriptorRef::new(&raw const _USB_DEVICE_DESCRIPTOR),
+            configuration: omk::usb::DescriptorRef::new(&raw const _USB_CONFIGURATION_DESCRIPTOR),
+            manufacturer: omk::usb::DescriptorRef::new(&raw const _USB_MANUFACTURER_STRING),
+            product: omk::usb::DescriptorRef::new(&raw const _USB_PRODUCT_STRING),
+            keyboard_hid: omk::usb::DescriptorRef::new(
+                &raw const _USB_CONFIGURATION_DESCRIPTOR.hid_keyboard_hid,
+            ),
+            mouse_hid: omk::usb::DescriptorRef::new(
+                &raw const _USB_CONFIGURATION_DESCRIPTOR.hid_mouse_hid,
+            ),
+            raw_hid_hid: omk::usb::DescriptorRef::new(
+                &raw const _USB_CONFIGURATION_DESCRIPTOR.hid_raw_hid,
+            ),
+            mouse_report: omk::usb::DescriptorRef::new(&raw const _USB_MOUSE_REPORT_DESCRIPTOR),
+        };
+
+        #[unsafe(no_mangle)]
+        unsafe extern "C" fn CALLBACK_USB_GetDescriptor(
+            w_value: u16,
+            w_index: u16,
+            descriptor_address: *mut *const ::core::ffi::c_void,
+        ) -> u16 {
+            unsafe { omk::usb::get_descriptor(&_USB_DESCRIPTORS, w_value, w_index, descriptor_address) }
+        }
+
         #[unsafe(no_mangle)]
         // Necessary to boot since avr-libc >= 2.3.0
         #[unsafe(link_section = ".init9")]
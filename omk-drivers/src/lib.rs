@@ -0,0 +1,11 @@
+//! Device drivers of the keyboard, independent of the microcontroller: they talk to the bus
+//! through a trait, so that they build and are tested on the host against a mock bus.
+//!
+//! The workspace builds for the keyboard by default, so the tests need a host target:
+//! `cargo test -p omk-drivers --target x86_64-unknown-linux-gnu`.
+
+#![cfg_attr(not(test), no_std)]
+#![feature(const_trait_impl, const_default, derive_const)]
+
+pub mod pointing;
+pub mod spi;
@@ -0,0 +1,71 @@
+//! This module defines the pointing devices, trackball sensors or trackpads, read by the keyboard.
+//!
+//! Drivers are provided for sensors on the SPI bus: [Pmw3360], [Pmw3389] and [Adns5050].
+//! They are generic over the [SpiBus](crate::spi::SpiBus).
+
+mod adns5050;
+mod pmw33xx;
+
+pub use adns5050::Adns5050;
+pub use pmw33xx::{NoFirmware, Pmw33xx, Pmw33xxFirmware, Pmw3360, Pmw3389};
+
+use crate::spi::SpiError;
+
+/// Represents an error of a pointing device, which is then left unused.
+#[derive(Debug)]
+pub struct PointingError();
+
+impl From<SpiError> for PointingError {
+    fn from(_: SpiError) -> Self {
+        Self()
+    }
+}
+
+/// Motion and buttons read from a pointing device.
+#[derive_const(Default)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PointingReport {
+    /// Motion since the last read, in counts, positive to the right
+    pub x: i16,
+    /// Motion since the last read, in counts, positive downward
+    pub y: i16,
+    /// Pressed buttons, a bit per button, the left one first
+    pub buttons: u8,
+    /// Vertical wheel motion, in [PointingDevice::WHEEL_PULSES_PER_DETENT] of detent, positive upward
+    pub wheel: i8,
+}
+
+/// A pointing device, read by the master half.
+pub trait PointingDevice {
+    /// `false` for [NoPointingDevice], leaving the pointing code out
+    const PRESENT: bool = true;
+    /// Wheel pulses of [PointingReport::wheel] making a detent
+    const WHEEL_PULSES_PER_DETENT: i8 = 1;
+    /// `true` for the devices on the I2C bus, which the slave half cannot drive over an I2C split link
+    const I2C_DEVICE: bool = false;
+
+    /// Powers the device up and configures it, at boot.
+    fn init(&mut self, cpi: u16) -> Result<(), PointingError>;
+    /// Reads the motion since the last read, and the pressed buttons.
+    fn read(&mut self) -> Result<PointingReport, PointingError>;
+    /// Sets the resolution, in counts per inch, rounded to one supported by the device.
+    fn set_cpi(&mut self, cpi: u16) -> Result<(), PointingError>;
+}
+
+/// No pointing device, the default.
+#[derive_const(Default)]
+pub struct NoPointingDevice;
+
+impl PointingDevice for NoPointingDevice {
+    const PRESENT: bool = false;
+
+    fn init(&mut self, _cpi: u16) -> Result<(), PointingError> {
+        Ok(())
+    }
+    fn read(&mut self) -> Result<PointingReport, PointingError> {
+        Ok(PointingReport::default())
+    }
+    fn set_cpi(&mut self, _cpi: u16) -> Result<(), PointingError> {
+        Ok(())
+    }
+}
@@ -0,0 +1,144 @@
+//! This module drives the ADNS-5050 optical sensor, from 125 to 1625 CPI.
+//!
+//! The sensor has a single data line, SDIO, to wire to MISO, and to MOSI through a 1 kΩ resistor,
+//! so that the sensor can drive it while MOSI is idle.
+
+use crate::{
+    pointing::{PointingDevice, PointingError, PointingReport},
+    spi::{SpiBus, SpiConfig},
+};
+
+/// Product ID of the ADNS-5050.
+const ADNS5050_ID: u8 = 0x12;
+
+const REG_PRODUCT_ID: u8 = 0x00;
+const REG_MOUSE_CONTROL2: u8 = 0x19;
+const REG_CHIP_RESET: u8 = 0x3A;
+const REG_MOTION_BURST: u8 = 0x63;
+
+/// Written to Chip_Reset to reset the sensor.
+const CHIP_RESET: u8 = 0x5A;
+/// Bit of Mouse_Control2 enabling the resolution set by its low bits
+const RES_EN: u8 = 1 << 4;
+
+/// Delays of the datasheet, in microseconds
+const T_SRAD: u16 = 4;
+const T_SWW: u16 = 30;
+const T_SWR: u16 = 20;
+const T_SRR: u16 = 1;
+
+/// The sensor samples on the rising edge, with the clock high when idle, up to 1 MHz.
+const SPI_CONFIG: SpiConfig = SpiConfig {
+    divisor: 16,
+    mode: 3,
+    lsb_first: false,
+};
+
+/// An ADNS-5050 sensor on `Bus`.
+pub struct Adns5050<Bus> {
+    bus: Bus,
+}
+
+impl<Bus: const Default> const Default for Adns5050<Bus> {
+    fn default() -> Self {
+        Self {
+            bus: Bus::default(),
+        }
+    }
+}
+
+impl<Bus: SpiBus> Adns5050<Bus> {
+    fn write_register(&mut self, register: u8, value: u8) -> Result<(), PointingError> {
+        self.bus.transaction(SPI_CONFIG, |bus| {
+            bus.transfer(register | 0x80)?;
+            bus.transfer(value)?;
+            Ok(())
+        })?;
+        self.bus.delay_us(T_SWW.max(T_SWR));
+        Ok(())
+    }
+
+    fn read_register(&mut self, register: u8) -> Result<u8, PointingError> {
+        let value = self.bus.transaction(SPI_CONFIG, |bus| {
+            bus.transfer(register & 0x7F)?;
+            bus.delay_us(T_SRAD);
+            bus.transfer(0)
+        })?;
+        self.bus.delay_us(T_SRR);
+        Ok(value)
+    }
+}
+
+impl<Bus: SpiBus> PointingDevice for Adns5050<Bus> {
+    fn init(&mut self, cpi: u16) -> Result<(), PointingError> {
+        self.bus.init();
+        // Deselecting the sensor resets its serial port
+        self.bus.transaction(SPI_CONFIG, |_| Ok(()))?;
+        self.write_register(REG_CHIP_RESET, CHIP_RESET)?;
+        self.bus.delay_us(55_000);
+        if self.read_register(REG_PRODUCT_ID)? != ADNS5050_ID {
+            return Err(PointingError());
+        }
+        self.set_cpi(cpi)
+    }
+
+    fn read(&mut self) -> Result<PointingReport, PointingError> {
+        let (x, y) = self.bus.transaction(SPI_CONFIG, |bus| {
+            bus.transfer(REG_MOTION_BURST)?;
+            bus.delay_us(T_SRAD);
+            let x = bus.transfer(0)? as i8;
+            let y = bus.transfer(0)? as i8;
+            Ok((x, y))
+        })?;
+        self.bus.delay_us(T_SRR);
+        Ok(PointingReport {
+            x: x as i16,
+            y: y as i16,
+            buttons: 0,
+            wheel: 0,
+        })
+    }
+
+    fn set_cpi(&mut self, cpi: u16) -> Result<(), PointingError> {
+        let resolution = (cpi / 125).clamp(1, 0x0D) as u8;
+        self.write_register(REG_MOUSE_CONTROL2, RES_EN | resolution)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::spi::mock::MockSpi;
+
+    #[test]
+    fn init() {
+        let mut bus = MockSpi::default();
+        bus.set_register(REG_PRODUCT_ID, &[ADNS5050_ID]);
+        let mut sensor = Adns5050 { bus };
+        sensor.init(1000).unwrap();
+        assert_eq!(
+            sensor.bus.writes(),
+            [
+                (REG_CHIP_RESET, CHIP_RESET),
+                (REG_MOUSE_CONTROL2, RES_EN | 8)
+            ]
+        );
+    }
+
+    #[test]
+    fn product_id_mismatch() {
+        let mut sensor = Adns5050 {
+            bus: MockSpi::default(),
+        };
+        assert!(sensor.init(1000).is_err());
+    }
+
+    #[test]
+    fn motion() {
+        let mut bus = MockSpi::default();
+        bus.set_register(REG_MOTION_BURST, &[5, -4i8 as u8]);
+        let mut sensor = Adns5050 { bus };
+        let report = sensor.read().unwrap();
+        assert_eq!((report.x, report.y), (5, -4));
+    }
+}
@@ -0,0 +1,317 @@
+//! This module drives the PMW3360 and PMW3389 optical sensors, common in trackballs.
+//! Both share their registers, but for the resolution.
+//!
+//! The sensors run a firmware uploaded at power up, the SROM, given by a [Pmw33xxFirmware].
+//! Without it, they run on the firmware of their ROM, whose tracking may be worse.
+
+use core::marker::PhantomData;
+
+use crate::{
+    pointing::{PointingDevice, PointingError, PointingReport},
+    spi::{SpiBus, SpiConfig},
+};
+
+/// Product ID of the PMW3360.
+const PMW3360_ID: u8 = 0x42;
+/// Product ID of the PMW3389.
+const PMW3389_ID: u8 = 0x47;
+
+const REG_PRODUCT_ID: u8 = 0x00;
+const REG_MOTION: u8 = 0x02;
+const REG_DELTA_Y_H: u8 = 0x06;
+/// Resolution of the PMW3360, and high byte of the one of the PMW3389
+const REG_CONFIG1: u8 = 0x0F;
+const REG_RESOLUTION_L: u8 = 0x0E;
+const REG_RESOLUTION_H: u8 = 0x0F;
+const REG_CONFIG2: u8 = 0x10;
+const REG_SROM_ENABLE: u8 = 0x13;
+const REG_SROM_ID: u8 = 0x2A;
+const REG_POWER_UP_RESET: u8 = 0x3A;
+const REG_MOTION_BURST: u8 = 0x50;
+const REG_SROM_LOAD_BURST: u8 = 0x62;
+
+/// Written to Power_Up_Reset to reset the sensor.
+const POWER_UP_RESET: u8 = 0x5A;
+/// Bit of the Motion register set when the sensor moved
+const MOTION_MOT: u8 = 1 << 7;
+/// Bit of the Motion register set when the sensor is lifted from the surface
+const MOTION_LIFT: u8 = 1 << 3;
+
+/// Delays of the datasheet, in microseconds
+const T_SRAD: u16 = 160;
+const T_SRAD_MOTBR: u16 = 35;
+const T_SCLK_NCS_WRITE: u16 = 35;
+const T_SWW: u16 = 180;
+const T_SWR: u16 = 180;
+const T_SRW: u16 = 20;
+const T_SROM_BYTE: u16 = 15;
+
+/// Both sensors sample on the rising edge, with the clock high when idle, up to 2 MHz.
+const SPI_CONFIG: SpiConfig = SpiConfig {
+    divisor: 8,
+    mode: 3,
+    lsb_first: false,
+};
+
+/// The SROM firmware of a sensor, given by the manufacturer.
+pub trait Pmw33xxFirmware {
+    /// Size of the firmware, in bytes, 0 to upload none
+    const LENGTH: u16;
+
+    /// Returns the byte of the firmware at `index`, e.g. read from progmem.
+    fn byte(index: u16) -> u8;
+}
+
+/// No firmware, the sensor running on its ROM.
+pub struct NoFirmware;
+
+impl Pmw33xxFirmware for NoFirmware {
+    const LENGTH: u16 = 0;
+
+    fn byte(_index: u16) -> u8 {
+        0
+    }
+}
+
+/// A PMW3360 or PMW3389 sensor on `Bus`, identified by its `PRODUCT_ID`.
+pub struct Pmw33xx<Bus, const PRODUCT_ID: u8, Firmware: Pmw33xxFirmware = NoFirmware> {
+    bus: Bus,
+    /// Set while the sensor is in burst mode, which any other register access leaves
+    in_burst: bool,
+    _firmware: PhantomData<Firmware>,
+}
+
+/// A PMW3360 sensor, from 100 to 12000 CPI.
+pub type Pmw3360<Bus, Firmware = NoFirmware> = Pmw33xx<Bus, PMW3360_ID, Firmware>;
+/// A PMW3389 sensor, from 50 to 16000 CPI.
+pub type Pmw3389<Bus, Firmware = NoFirmware> = Pmw33xx<Bus, PMW3389_ID, Firmware>;
+
+impl<Bus: const Default, const PRODUCT_ID: u8, Firmware: Pmw33xxFirmware> const Default
+    for Pmw33xx<Bus, PRODUCT_ID, Firmware>
+{
+    fn default() -> Self {
+        Self {
+            bus: Bus::default(),
+            in_burst: false,
+            _firmware: PhantomData,
+        }
+    }
+}
+
+impl<Bus: SpiBus, const PRODUCT_ID: u8, Firmware: Pmw33xxFirmware>
+    Pmw33xx<Bus, PRODUCT_ID, Firmware>
+{
+    fn write_register(&mut self, register: u8, value: u8) -> Result<(), PointingError> {
+        self.in_burst = false;
+        self.bus.transaction(SPI_CONFIG, |bus| {
+            bus.transfer(register | 0x80)?;
+            bus.transfer(value)?;
+            bus.delay_us(T_SCLK_NCS_WRITE);
+            Ok(())
+        })?;
+        self.bus.delay_us(T_SWW.max(T_SWR));
+        Ok(())
+    }
+
+    fn read_register(&mut self, register: u8) -> Result<u8, PointingError> {
+        self.in_burst = false;
+        let value = self.bus.transaction(SPI_CONFIG, |bus| {
+            bus.transfer(register & 0x7F)?;
+            bus.delay_us(T_SRAD);
+            bus.transfer(0)
+        })?;
+        self.bus.delay_us(T_SRW);
+        Ok(value)
+    }
+
+    /// Uploads the SROM firmware, and checks that the sensor runs it.
+    fn upload_firmware(&mut self) -> Result<(), PointingError> {
+        // Rest mode must be off during the upload
+        self.write_register(REG_CONFIG2, 0x00)?;
+        self.write_register(REG_SROM_ENABLE, 0x1D)?;
+        self.bus.delay_us(10_000);
+        self.write_register(REG_SROM_ENABLE, 0x18)?;
+        self.bus.transaction(SPI_CONFIG, |bus| {
+            bus.transfer(REG_SROM_LOAD_BURST | 0x80)?;
+            bus.delay_us(T_SROM_BYTE);
+            for index in 0..Firmware::LENGTH {
+                bus.transfer(Firmware::byte(index))?;
+                bus.delay_us(T_SROM_BYTE);
+            }
+            Ok(())
+        })?;
+        self.bus.delay_us(200);
+        if self.read_register(REG_SROM_ID)? == 0 {
+            return Err(PointingError());
+        }
+        Ok(())
+    }
+}
+
+impl<Bus: SpiBus, const PRODUCT_ID: u8, Firmware: Pmw33xxFirmware> PointingDevice
+    for Pmw33xx<Bus, PRODUCT_ID, Firmware>
+{
+    fn init(&mut self, cpi: u16) -> Result<(), PointingError> {
+        self.bus.init();
+        // Deselecting the sensor resets its serial port
+        self.bus.transaction(SPI_CONFIG, |_| Ok(()))?;
+        self.write_register(REG_POWER_UP_RESET, POWER_UP_RESET)?;
+        self.bus.delay_us(50_000);
+        // The motion registers must be read once after the reset
+        for register in REG_MOTION..=REG_DELTA_Y_H {
+            self.read_register(register)?;
+        }
+        if Firmware::LENGTH > 0 {
+            self.upload_firmware()?;
+        }
+        self.write_register(REG_CONFIG2, 0x00)?;
+        self.set_cpi(cpi)?;
+        if self.read_register(REG_PRODUCT_ID)? != PRODUCT_ID {
+            return Err(PointingError());
+        }
+        Ok(())
+    }
+
+    fn read(&mut self) -> Result<PointingReport, PointingError> {
+        if !self.in_burst {
+            self.write_register(REG_MOTION_BURST, 0x00)?;
+            self.in_burst = true;
+        }
+        // Motion, Observation, Delta_X_L, Delta_X_H, Delta_Y_L, Delta_Y_H
+        let mut burst = [0u8; 6];
+        self.bus.transaction(SPI_CONFIG, |bus| {
+            bus.transfer(REG_MOTION_BURST)?;
+            bus.delay_us(T_SRAD_MOTBR);
+            for byte in burst.iter_mut() {
+                *byte = bus.transfer(0)?;
+            }
+            Ok(())
+        })?;
+        let motion = burst[0];
+        // The operation mode bits are never set in burst mode, start it again
+        if motion & 0b111 != 0 {
+            self.in_burst = false;
+        }
+        if motion & MOTION_MOT == 0 || motion & MOTION_LIFT != 0 {
+            return Ok(PointingReport::default());
+        }
+        Ok(PointingReport {
+            x: i16::from_le_bytes([burst[2], burst[3]]),
+            y: i16::from_le_bytes([burst[4], burst[5]]),
+            buttons: 0,
+            wheel: 0,
+        })
+    }
+
+    fn set_cpi(&mut self, cpi: u16) -> Result<(), PointingError> {
+        if PRODUCT_ID == PMW3389_ID {
+            let [low, high] = (cpi.clamp(50, 16000) / 50).to_le_bytes();
+            self.write_register(REG_RESOLUTION_L, low)?;
+            self.write_register(REG_RESOLUTION_H, high)
+        } else {
+            self.write_register(REG_CONFIG1, (cpi.clamp(100, 12000) / 100 - 1) as u8)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::spi::mock::MockSpi;
+
+    fn sensor<const PRODUCT_ID: u8>(bus: MockSpi) -> Pmw33xx<MockSpi, PRODUCT_ID> {
+        Pmw33xx {
+            bus,
+            in_burst: false,
+            _firmware: PhantomData,
+        }
+    }
+
+    /// Returns the bus answering a motion burst with `motion`, and a motion of (3, -2).
+    fn burst_bus(motion: u8) -> MockSpi {
+        let mut bus = MockSpi::default();
+        let [x_low, x_high] = 3i16.to_le_bytes();
+        let [y_low, y_high] = (-2i16).to_le_bytes();
+        bus.set_register(REG_MOTION_BURST, &[motion, 0, x_low, x_high, y_low, y_high]);
+        bus
+    }
+
+    #[test]
+    fn pmw3360_cpi() {
+        let mut sensor = sensor::<PMW3360_ID>(MockSpi::default());
+        sensor.set_cpi(800).unwrap();
+        assert_eq!(sensor.bus.writes(), [(REG_CONFIG1, 7)]);
+    }
+
+    #[test]
+    fn pmw3389_cpi() {
+        let mut sensor = sensor::<PMW3389_ID>(MockSpi::default());
+        sensor.set_cpi(16000).unwrap();
+        assert_eq!(
+            sensor.bus.writes(),
+            [(REG_RESOLUTION_L, 0x40), (REG_RESOLUTION_H, 0x01)]
+        );
+    }
+
+    #[test]
+    fn motion() {
+        let mut sensor = sensor::<PMW3360_ID>(burst_bus(MOTION_MOT));
+        let report = sensor.read().unwrap();
+        assert_eq!((report.x, report.y), (3, -2));
+    }
+
+    #[test]
+    fn burst_kept() {
+        let mut sensor = sensor::<PMW3360_ID>(burst_bus(MOTION_MOT));
+        sensor.read().unwrap();
+        sensor.read().unwrap();
+        assert_eq!(sensor.bus.writes(), [(REG_MOTION_BURST, 0)]);
+    }
+
+    #[test]
+    fn burst_rearmed() {
+        let mut sensor = sensor::<PMW3360_ID>(burst_bus(MOTION_MOT | 0b001));
+        sensor.read().unwrap();
+        sensor.read().unwrap();
+        assert_eq!(
+            sensor.bus.writes(),
+            [(REG_MOTION_BURST, 0), (REG_MOTION_BURST, 0)]
+        );
+    }
+
+    #[test]
+    fn lifted() {
+        let mut sensor = sensor::<PMW3360_ID>(burst_bus(MOTION_MOT | MOTION_LIFT));
+        assert_eq!(sensor.read().unwrap(), PointingReport::default());
+    }
+
+    #[test]
+    fn no_motion() {
+        let mut sensor = sensor::<PMW3360_ID>(burst_bus(0));
+        assert_eq!(sensor.read().unwrap(), PointingReport::default());
+    }
+
+    #[test]
+    fn init() {
+        let mut bus = MockSpi::default();
+        bus.set_register(REG_PRODUCT_ID, &[PMW3360_ID]);
+        let mut sensor = sensor::<PMW3360_ID>(bus);
+        sensor.init(800).unwrap();
+        assert_eq!(
+            sensor.bus.writes(),
+            [
+                (REG_POWER_UP_RESET, POWER_UP_RESET),
+                (REG_CONFIG2, 0),
+                (REG_CONFIG1, 7)
+            ]
+        );
+    }
+
+    #[test]
+    fn product_id_mismatch() {
+        let mut bus = MockSpi::default();
+        bus.set_register(REG_PRODUCT_ID, &[PMW3389_ID]);
+        let mut sensor = sensor::<PMW3360_ID>(bus);
+        assert!(sensor.init(800).is_err());
+    }
+}
@@ -0,0 +1,46 @@
+//! This module defines the SPI bus the device drivers talk to, the microcontroller being the controller.
+//! Each device has its own chip select, low during its transactions.
+
+#[cfg(test)]
+pub(crate) mod mock;
+
+/// Represents an error in SPI communication.
+#[derive(Debug)]
+pub struct SpiError();
+
+/// Settings of the SPI for a device.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SpiConfig {
+    /// Divisor of the CPU clock, a power of two from 2 to 128
+    pub divisor: u8,
+    /// SPI mode, from 0 to 3: the clock polarity, then the clock phase
+    pub mode: u8,
+    /// Send the least significant bit first
+    pub lsb_first: bool,
+}
+
+/// A device on an SPI bus, selected with its own chip select.
+pub trait SpiBus {
+    /// Configures the pins of the bus and the chip select, at boot.
+    fn init(&mut self) {}
+    /// Selects the device, starting a transaction with its settings.
+    fn start(&mut self, config: SpiConfig) -> Result<(), SpiError>;
+    /// Deselects the device, ending the transaction.
+    fn stop(&mut self);
+    /// Sends a byte, and returns the byte received meanwhile.
+    fn transfer(&mut self, data: u8) -> Result<u8, SpiError>;
+    /// Waits at least `us` microseconds, for the timings of the device.
+    fn delay_us(&mut self, us: u16);
+
+    /// Runs `f` in a transaction, the device being deselected even if `f` fails.
+    fn transaction<R>(
+        &mut self,
+        config: SpiConfig,
+        f: impl FnOnce(&mut Self) -> Result<R, SpiError>,
+    ) -> Result<R, SpiError> {
+        self.start(config)?;
+        let result = f(self);
+        self.stop();
+        result
+    }
+}
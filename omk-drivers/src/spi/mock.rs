@@ -0,0 +1,67 @@
+//! This module provides a mock SPI bus for the tests of the drivers. It records the bytes sent,
+//! and answers the register reads with the bytes set for the register.
+
+use std::collections::BTreeMap;
+
+use crate::spi::{SpiBus, SpiConfig, SpiError};
+
+/// A mock SPI bus, the first byte of a transaction being the register address, its top bit set for writes.
+#[derive(Debug, Default)]
+pub struct MockSpi {
+    /// Bytes sent in each transaction, in order
+    pub transactions: Vec<Vec<u8>>,
+    /// Bytes returned after the address by the reads of a register, 0 past them
+    registers: BTreeMap<u8, Vec<u8>>,
+    /// Settings of the transaction in progress
+    selected: Option<SpiConfig>,
+}
+
+impl MockSpi {
+    /// Sets the bytes returned by the reads of `register`.
+    pub fn set_register(&mut self, register: u8, bytes: &[u8]) {
+        self.registers.insert(register, bytes.to_vec());
+    }
+
+    /// Returns the register writes, address and value, in order.
+    pub fn writes(&self) -> Vec<(u8, u8)> {
+        self.transactions
+            .iter()
+            .filter_map(|transaction| match transaction[..] {
+                [address, value] if address & 0x80 != 0 => Some((address & 0x7F, value)),
+                _ => None,
+            })
+            .collect()
+    }
+}
+
+impl SpiBus for MockSpi {
+    fn start(&mut self, config: SpiConfig) -> Result<(), SpiError> {
+        assert!(self.selected.is_none(), "transaction started twice");
+        self.selected = Some(config);
+        self.transactions.push(Vec::new());
+        Ok(())
+    }
+
+    fn stop(&mut self) {
+        assert!(self.selected.take().is_some(), "transaction stopped twice");
+    }
+
+    fn transfer(&mut self, data: u8) -> Result<u8, SpiError> {
+        assert!(self.selected.is_some(), "transfer out of a transaction");
+        let transaction = self.transactions.last_mut().unwrap();
+        transaction.push(data);
+        let address = transaction[0];
+        if transaction.len() == 1 || address & 0x80 != 0 {
+            return Ok(0);
+        }
+        let index = transaction.len() - 2;
+        Ok(self
+            .registers
+            .get(&address)
+            .and_then(|bytes| bytes.get(index))
+            .copied()
+            .unwrap_or(0))
+    }
+
+    fn delay_us(&mut self, _us: u16) {}
+}
@@ -0,0 +1,127 @@
+//! This module talks to the keyboard through the Linux `hidraw` driver.
+//! The user needs read and write access to the `/dev/hidrawN` node, usually given by an udev rule.
+
+use std::{
+    fs::{self, File, OpenOptions},
+    io::{ErrorKind, Read, Write},
+    path::{Path, PathBuf},
+    sync::mpsc::{Receiver, RecvTimeoutError, channel},
+    thread,
+    time::Duration,
+};
+
+use crate::{Error, REPORT_SIZE, Report, Transport, USAGE, USAGE_PAGE};
+
+/// A Raw HID interface found on the system.
+#[derive(Debug, Clone)]
+pub struct DeviceInfo {
+    /// Device node, e.g. `/dev/hidraw3`.
+    pub path: PathBuf,
+    pub vendor_id: u16,
+    pub product_id: u16,
+}
+
+/// Lists the HID interfaces declaring the Raw HID usage page and usage.
+pub fn list_devices() -> Result<Vec<DeviceInfo>, Error> {
+    let mut devices = Vec::new();
+    for entry in fs::read_dir("/sys/class/hidraw")? {
+        let entry = entry?;
+        let device = entry.path().join("device");
+        let Ok(descriptor) = fs::read(device.join("report_descriptor")) else {
+            continue;
+        };
+        if !is_raw_hid_descriptor(&descriptor) {
+            continue;
+        }
+        let Some((vendor_id, product_id)) = read_ids(&device.join("uevent")) else {
+            continue;
+        };
+        devices.push(DeviceInfo {
+            path: Path::new("/dev").join(entry.file_name()),
+            vendor_id,
+            product_id,
+        });
+    }
+    devices.sort_by(|a, b| a.path.cmp(&b.path));
+    Ok(devices)
+}
+
+/// Returns true if a report descriptor starts with the Raw HID usage page and usage.
+fn is_raw_hid_descriptor(descriptor: &[u8]) -> bool {
+    let [page_low, page_high] = USAGE_PAGE.to_le_bytes();
+    descriptor.starts_with(&[0x06, page_low, page_high, 0x09, USAGE])
+}
+
+/// Reads the vendor and product IDs from the `HID_ID=bus:vendor:product` line of an uevent file.
+fn read_ids(uevent: &Path) -> Option<(u16, u16)> {
+    let uevent = fs::read_to_string(uevent).ok()?;
+    let ids = uevent
+        .lines()
+        .find_map(|line| line.strip_prefix("HID_ID="))?;
+    let mut fields = ids.split(':').skip(1);
+    let vendor_id = u32::from_str_radix(fields.next()?, 16).ok()?;
+    let product_id = u32::from_str_radix(fields.next()?, 16).ok()?;
+    Some((vendor_id as u16, product_id as u16))
+}
+
+/// A transport over a `/dev/hidrawN` node.
+///
+/// Reports are read by a background thread, so that reads can time out.
+pub struct HidrawTransport {
+    file: File,
+    reports: Receiver<Result<Report, Error>>,
+}
+
+impl HidrawTransport {
+    /// Opens a device node.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, Error> {
+        let file = OpenOptions::new().read(true).write(true).open(path)?;
+        let mut reader = file.try_clone()?;
+        let (sender, reports) = channel();
+        thread::spawn(move || {
+            let mut report = [0; REPORT_SIZE];
+            loop {
+                let result = match reader.read(&mut report) {
+                    Ok(REPORT_SIZE) => Ok(report),
+                    // Short reads are malformed reports, skip them
+                    Ok(len) if len > 0 => continue,
+                    Ok(_) => Err(Error::Disconnected),
+                    Err(err) if err.kind() == ErrorKind::Interrupted => continue,
+                    Err(err) => Err(Error::Io(err)),
+                };
+                let failed = result.is_err();
+                if sender.send(result).is_err() || failed {
+                    break;
+                }
+            }
+        });
+        Ok(Self { file, reports })
+    }
+
+    /// Opens the first keyboard found, optionally filtered on its vendor and product IDs.
+    pub fn open_first(ids: Option<(u16, u16)>) -> Result<Self, Error> {
+        let device = list_devices()?
+            .into_iter()
+            .find(|device| ids.is_none_or(|ids| ids == (device.vendor_id, device.product_id)))
+            .ok_or(Error::NotFound)?;
+        Self::open(device.path)
+    }
+}
+
+impl Transport for HidrawTransport {
+    fn write_report(&mut self, report: &Report) -> Result<(), Error> {
+        // The interface has no report IDs, which hidraw expects as a leading 0
+        let mut buffer = [0; REPORT_SIZE + 1];
+        buffer[1..].copy_from_slice(report);
+        self.file.write_all(&buffer)?;
+        Ok(())
+    }
+
+    fn read_report(&mut self, timeout: Duration) -> Result<Report, Error> {
+        match self.reports.recv_timeout(timeout) {
+            Ok(result) => result,
+            Err(RecvTimeoutError::Timeout) => Err(Error::Timeout),
+            Err(RecvTimeoutError::Disconnected) => Err(Error::Disconnected),
+        }
+    }
+}
@@ -0,0 +1,213 @@
+//! Host side of the Raw HID interface of the keyboard.
+//! It exchanges fixed size reports with the firmware, through a [Transport]:
+//! [hidraw::HidrawTransport] on Linux, or [mock::MockTransport] to test host tools without a keyboard.
+//!
+//! The workspace builds for the keyboard by default, so this crate needs a host target:
+//! `cargo build -p omk-host --target x86_64-unknown-linux-gnu`, and the same for `cargo test`.
+
+use std::{fmt, io, time::Duration};
+
+#[cfg(target_os = "linux")]
+pub mod hidraw;
+pub mod mock;
+
+/// Size in bytes of every Raw HID report, in both directions.
+pub const REPORT_SIZE: usize = 32;
+
+/// Vendor defined usage page of the Raw HID interface.
+pub const USAGE_PAGE: u16 = 0xFF60;
+
+/// Usage of the Raw HID interface.
+pub const USAGE: u8 = 0x61;
+
+/// A Raw HID report.
+pub type Report = [u8; REPORT_SIZE];
+
+/// Errors of the communication with the keyboard.
+#[derive(Debug)]
+pub enum Error {
+    /// No keyboard with a Raw HID interface was found.
+    NotFound,
+    /// The keyboard didn't send a report in time.
+    Timeout,
+    /// The keyboard was disconnected.
+    Disconnected,
+    /// The payload doesn't fit in a report.
+    TooLong(usize),
+    /// Error of the underlying device.
+    Io(io::Error),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::NotFound => write!(f, "no keyboard with a Raw HID interface found"),
+            Error::Timeout => write!(f, "timed out waiting for a report"),
+            Error::Disconnected => write!(f, "keyboard disconnected"),
+            Error::TooLong(len) => write!(f, "payload of {len} bytes exceeds {REPORT_SIZE} bytes"),
+            Error::Io(err) => write!(f, "{err}"),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Self {
+        Error::Io(err)
+    }
+}
+
+/// A channel carrying Raw HID reports to and from the keyboard.
+pub trait Transport {
+    /// Sends a report to the keyboard.
+    fn write_report(&mut self, report: &Report) -> Result<(), Error>;
+
+    /// Waits at most `timeout` for the next report of the keyboard.
+    fn read_report(&mut self, timeout: Duration) -> Result<Report, Error>;
+}
+
+/// A Raw HID connection to the keyboard.
+pub struct RawHid<T: Transport> {
+    transport: T,
+    timeout: Duration,
+}
+
+impl<T: Transport> RawHid<T> {
+    /// Default time to wait for an answer of the keyboard.
+    pub const DEFAULT_TIMEOUT: Duration = Duration::from_millis(500);
+
+    /// Wraps a transport.
+    pub fn new(transport: T) -> Self {
+        Self {
+            transport,
+            timeout: Self::DEFAULT_TIMEOUT,
+        }
+    }
+
+    /// Sets the time to wait for an answer of the keyboard.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Returns the underlying transport.
+    pub fn transport(&mut self) -> &mut T {
+        &mut self.transport
+    }
+
+    /// Sends a payload, padded with zeros up to [REPORT_SIZE] bytes.
+    pub fn send(&mut self, payload: &[u8]) -> Result<(), Error> {
+        if payload.len() > REPORT_SIZE {
+            return Err(Error::TooLong(payload.len()));
+        }
+        let mut report = [0; REPORT_SIZE];
+        report[..payload.len()].copy_from_slice(payload);
+        self.transport.write_report(&report)
+    }
+
+    /// Waits for the next report of the keyboard.
+    pub fn receive(&mut self) -> Result<Report, Error> {
+        self.transport.read_report(self.timeout)
+    }
+
+    /// Sends a payload, then waits for the answer of the keyboard.
+    pub fn request(&mut self, payload: &[u8]) -> Result<Report, Error> {
+        self.send(payload)?;
+        self.receive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::MockTransport;
+
+    #[test]
+    fn send_pads_payload_to_report_size() {
+        let mut raw_hid = RawHid::new(MockTransport::new());
+        raw_hid.send(&[0x01, 0x02, 0x03]).unwrap();
+        let sent = &raw_hid.transport().sent;
+        assert_eq!(sent.len(), 1);
+        assert_eq!(sent[0][..3], [0x01, 0x02, 0x03]);
+        assert!(sent[0][3..].iter().all(|byte| *byte == 0));
+    }
+
+    #[test]
+    fn send_accepts_full_report() {
+        let mut raw_hid = RawHid::new(MockTransport::new());
+        let payload = [0xAA; REPORT_SIZE];
+        raw_hid.send(&payload).unwrap();
+        assert_eq!(raw_hid.transport().sent, [payload]);
+    }
+
+    #[test]
+    fn send_rejects_too_long_payload() {
+        let mut raw_hid = RawHid::new(MockTransport::new());
+        let result = raw_hid.send(&[0; REPORT_SIZE + 1]);
+        assert!(matches!(result, Err(Error::TooLong(len)) if len == REPORT_SIZE + 1));
+        assert!(raw_hid.transport().sent.is_empty());
+    }
+
+    #[test]
+    fn request_returns_answer_of_keyboard() {
+        // Echoes the command byte, followed by its argument incremented
+        let transport = MockTransport::with_responder(|report| {
+            let mut answer = [0; REPORT_SIZE];
+            answer[0] = report[0];
+            answer[1] = report[1] + 1;
+            Some(answer)
+        });
+        let mut raw_hid = RawHid::new(transport);
+        let answer = raw_hid.request(&[0x12, 0x41]).unwrap();
+        assert_eq!(answer[..2], [0x12, 0x42]);
+        assert!(answer[2..].iter().all(|byte| *byte == 0));
+        assert_eq!(raw_hid.transport().sent.len(), 1);
+        assert!(raw_hid.transport().pending.is_empty());
+    }
+
+    #[test]
+    fn request_without_answer_times_out() {
+        let mut raw_hid = RawHid::new(MockTransport::with_responder(|_| None));
+        assert!(matches!(raw_hid.request(&[0x01]), Err(Error::Timeout)));
+        // The request was still sent
+        assert_eq!(raw_hid.transport().sent.len(), 1);
+    }
+
+    #[test]
+    fn receive_returns_reports_in_order() {
+        let mut transport = MockTransport::new();
+        transport.push_report([1; REPORT_SIZE]);
+        transport.push_report([2; REPORT_SIZE]);
+        let mut raw_hid = RawHid::new(transport);
+        assert_eq!(raw_hid.receive().unwrap(), [1; REPORT_SIZE]);
+        assert_eq!(raw_hid.receive().unwrap(), [2; REPORT_SIZE]);
+        assert!(matches!(raw_hid.receive(), Err(Error::Timeout)));
+    }
+
+    #[test]
+    fn too_long_request_is_not_answered() {
+        let mut raw_hid = RawHid::new(MockTransport::with_responder(|report| Some(*report)));
+        assert!(matches!(
+            raw_hid.request(&[0; REPORT_SIZE + 1]),
+            Err(Error::TooLong(_))
+        ));
+        assert!(raw_hid.transport().pending.is_empty());
+    }
+
+    #[test]
+    fn disconnected_keyboard_fails_exchanges() {
+        let mut raw_hid = RawHid::new(MockTransport::with_responder(|report| Some(*report)));
+        raw_hid.transport().disconnect();
+        assert!(matches!(raw_hid.send(&[0x01]), Err(Error::Disconnected)));
+        assert!(matches!(raw_hid.receive(), Err(Error::Disconnected)));
+        assert!(raw_hid.transport().sent.is_empty());
+    }
+}
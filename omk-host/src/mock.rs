@@ -0,0 +1,68 @@
+//! This module provides a transport simulating the keyboard, to test host tools without hardware.
+
+use std::{collections::VecDeque, time::Duration};
+
+use crate::{Error, Report, Transport};
+
+/// Function answering the reports sent to a [MockTransport], `None` meaning no answer.
+pub type Responder = Box<dyn FnMut(&Report) -> Option<Report>>;
+
+/// A transport recording the reports sent, and answering with queued reports or a [Responder].
+#[derive(Default)]
+pub struct MockTransport {
+    /// Reports sent to the keyboard, oldest first.
+    pub sent: Vec<Report>,
+    /// Reports the keyboard will send, oldest first.
+    pub pending: VecDeque<Report>,
+    responder: Option<Responder>,
+    disconnected: bool,
+}
+
+impl MockTransport {
+    /// Creates a transport with no pending report.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates a transport answering each report sent with `responder`.
+    pub fn with_responder(responder: impl FnMut(&Report) -> Option<Report> + 'static) -> Self {
+        Self {
+            responder: Some(Box::new(responder)),
+            ..Self::default()
+        }
+    }
+
+    /// Queues a report sent by the keyboard.
+    pub fn push_report(&mut self, report: Report) {
+        self.pending.push_back(report);
+    }
+
+    /// Simulates the keyboard being unplugged, failing every later exchange.
+    pub fn disconnect(&mut self) {
+        self.disconnected = true;
+    }
+}
+
+impl Transport for MockTransport {
+    fn write_report(&mut self, report: &Report) -> Result<(), Error> {
+        if self.disconnected {
+            return Err(Error::Disconnected);
+        }
+        self.sent.push(*report);
+        if let Some(answer) = self
+            .responder
+            .as_mut()
+            .and_then(|responder| responder(report))
+        {
+            self.pending.push_back(answer);
+        }
+        Ok(())
+    }
+
+    fn read_report(&mut self, _timeout: Duration) -> Result<Report, Error> {
+        if self.disconnected {
+            return Err(Error::Disconnected);
+        }
+        self.pending.pop_front().ok_or(Error::Timeout)
+    }
+}
@@ -0,0 +1,71 @@
+//! This module drives the analog to digital converter, reading the voltage of an analog pin
+//! from 0 (ground) to [ADC_MAX] (VCC), with 10 bits of resolution.
+//!
+//! The analog pins of the ATmega32u4 exposed by [avr_base::pins] are F4 to F7, D7, B4, B5 and B6.
+//! A conversion takes about 100 µs, the ADC clock being the CPU one divided by 128.
+
+use avr_base::{
+    pins::{B4, B5, B6, D7, F4, F5, F6, F7, Pin},
+    register::{ADCH, ADCL, ADCSRA, ADCSRB, ADEN, ADMUX, ADPS, ADSC, DIDR0, DIDR2, MUX5, REFS0},
+};
+
+/// Highest value of a conversion.
+pub const ADC_MAX: u16 = 1023;
+
+/// Returns the ADC channel of a pin, `None` if the pin isn't analog.
+pub const fn adc_channel(pin: Pin) -> Option<u8> {
+    Some(match pin {
+        F4 => 4,
+        F5 => 5,
+        F6 => 6,
+        F7 => 7,
+        D7 => 10,
+        B4 => 11,
+        B5 => 12,
+        B6 => 13,
+        _ => return None,
+    })
+}
+
+/// Enables the ADC, VCC being the reference voltage.
+pub fn adc_init() {
+    ADMUX.write(REFS0);
+    ADCSRA.write(ADEN | ADPS);
+}
+
+/// Sets an analog pin up as an input without pull-up, disabling its digital input buffer,
+/// which would draw current at mid-voltages.
+pub fn adc_pin_init(pin: Pin) {
+    let Some(channel) = adc_channel(pin) else {
+        return;
+    };
+    pin.gpio_set_pin_input();
+    if channel < 8 {
+        DIDR0.write(DIDR0 | (1 << channel));
+    } else {
+        DIDR2.write(DIDR2 | (1 << (channel - 8)));
+    }
+}
+
+/// Converts the voltage of an analog pin, from 0 to [ADC_MAX].
+///
+/// Returns 0 for the pins without ADC channel.
+pub fn adc_read(pin: Pin) -> u16 {
+    let Some(channel) = adc_channel(pin) else {
+        return 0;
+    };
+    ADMUX.write(REFS0 | (channel & 0b111));
+    ADCSRB.write(if channel >= 8 { MUX5 } else { 0 });
+    ADCSRA.write(ADCSRA | ADSC);
+    while ADCSRA & ADSC != 0 {}
+    // Reading the low byte locks the result until the high byte is read
+    let low = ADCL.read();
+    u16::from_le_bytes([low, ADCH.read()])
+}
+
+/// Converts the voltage of an analog pin `samples` times, and returns the average, smoothing the noise.
+pub fn adc_read_average(pin: Pin, samples: u8) -> u16 {
+    let samples = samples.max(1);
+    let sum: u32 = (0..samples).map(|_| adc_read(pin) as u32).sum();
+    (sum / samples as u32) as u16
+}
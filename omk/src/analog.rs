@@ -0,0 +1,391 @@
+//! This module reads the analog inputs of the master half with the [adc]: an analog joystick on
+//! [Keyboard::JOYSTICK_PINS], and slide potentiometers on [Keyboard::SLIDER_PINS].
+//! They are read every [ANALOG_INTERVAL] ms, each reading averaging [Keyboard::ANALOG_SAMPLES] conversions.
+//!
+//! The joystick drives, depending on its [JoystickMode], picked by [Keyboard::JOYSTICK_MODE] or
+//! held keys such as [keys::JoystickScroll](crate::keys::JoystickScroll):
+//! - [JoystickMode::Mouse]: the cursor, up to [Keyboard::JOYSTICK_MOUSE_SPEED], slower near the centre for precise moves.
+//! - [JoystickMode::Scroll]: the wheels, up to [Keyboard::JOYSTICK_SCROLL_SPEED].
+//! - [JoystickMode::Gamepad]: the X and Y axes of the gamepad, see [Keyboard::GAMEPAD].
+//!
+//! Its centre and dead zone are measured by [keys::JoystickCalibrate](crate::keys::JoystickCalibrate),
+//! the stick resting for [CALIBRATION_TIME] ms, and stored in EEPROM, right before the handedness byte.
+//! Until then, the centre is the middle of the range and the dead zone [Keyboard::JOYSTICK_DEADZONE].
+//!
+//! Each slider is split in [Keyboard::SLIDER_STEPS] positions. Its moves are passed to
+//! [Keyboard::slider_handler], whose default changes the volume of the computer with the first slider.
+
+use core::marker::PhantomData;
+
+use avr_base::pins::NO_PIN;
+
+use crate::{
+    Keyboard, OmkKeyboard,
+    adc::{ADC_MAX, adc_init, adc_pin_init, adc_read_average},
+    eeprom::EepromRefMut,
+    is_master,
+    keys::{VOLUME_DOWN, VOLUME_UP},
+    mouse::add_delta,
+    side::HANDEDNESS_ADDRESS,
+    timer::{timer_elapsed, timer_read},
+    usb::{
+        GamepadAxis, add_code, get_mouse_delta, get_wheel_delta, remove_code, set_gamepad_axis,
+        set_mouse_delta, set_wheel_delta, wheel_resolution,
+    },
+};
+
+/// Milliseconds between two readings of the analog inputs.
+pub const ANALOG_INTERVAL: u16 = 10;
+/// Milliseconds the resting stick is sampled for by a calibration.
+pub const CALIBRATION_TIME: u16 = 500;
+/// Maximum number of sliders.
+pub const MAX_SLIDERS: usize = 4;
+/// Milliseconds a volume key is held, then released, by the sliders.
+const VOLUME_TAP_TIME: u16 = 10;
+/// Longest time between two readings accounted for the joystick moves, in milliseconds.
+const MAX_READING_INTERVAL: u32 = 4 * ANALOG_INTERVAL as u32;
+
+/// Identifies a calibration of the joystick in EEPROM.
+const CALIBRATION_MAGIC: u8 = 0x4A;
+
+/// Address of the calibration of the joystick in EEPROM, right before the handedness byte.
+pub(crate) const JOYSTICK_CALIBRATION_ADDRESS: u16 =
+    HANDEDNESS_ADDRESS - size_of::<JoystickCalibration>() as u16;
+
+/// Calibration of the joystick, see the [EEPROM layout](crate::dynamic_keymap#eeprom-layout).
+const JOYSTICK_CALIBRATION_EEPROM: EepromRefMut<'static, JoystickCalibration> =
+    unsafe { EepromRefMut::new(JOYSTICK_CALIBRATION_ADDRESS as *mut JoystickCalibration) };
+
+/// What the analog joystick drives.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JoystickMode {
+    /// Moves the cursor
+    Mouse,
+    /// Scrolls the wheels, pushing up scrolling up
+    Scroll,
+    /// Sets the X and Y axes of the gamepad
+    Gamepad,
+}
+
+/// Centre and dead zone of the joystick, as stored in EEPROM.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(C)]
+struct JoystickCalibration {
+    /// [CALIBRATION_MAGIC] once the joystick was calibrated
+    magic: u8,
+    /// Reading of each axis at rest
+    center: [u16; 2],
+    /// Distance to the centre under which the stick is considered at rest
+    deadzone: u16,
+}
+
+/// Readings of the resting stick, during a calibration.
+#[derive(Debug, Clone, Copy)]
+struct Calibrating {
+    start: u32,
+    sum: [u32; 2],
+    count: u16,
+    min: [u16; 2],
+    max: [u16; 2],
+}
+
+/// State of the analog inputs.
+pub struct OmkAnalog<User> {
+    calibration: JoystickCalibration,
+    calibrating: Option<Calibrating>,
+    /// Current mode of the joystick
+    pub joystick_mode: JoystickMode,
+    last_reading: u32,
+    /// Joystick moves not sent yet, in 1/[MOTION_DIVISOR] of count or wheel unit
+    remainders: [i32; 2],
+    /// Current position of each slider
+    slider_positions: [u8; MAX_SLIDERS],
+    /// Volume steps not sent yet, positive upward
+    pending_volume: i8,
+    /// Keycode of the volume key held
+    volume_key: Option<u8>,
+    /// Time of the last press or release of a volume key
+    volume_timer: u32,
+    _phantom: PhantomData<User>,
+}
+
+/// Divisor of the joystick moves: a full tilt, at 127, squared, per second.
+const MOTION_DIVISOR: i32 = 127 * 127 * 1000;
+
+impl<User: Keyboard> const Default for OmkAnalog<User> {
+    fn default() -> Self {
+        Self {
+            calibration: JoystickCalibration {
+                magic: 0,
+                center: [ADC_MAX / 2; 2],
+                deadzone: User::JOYSTICK_DEADZONE,
+            },
+            calibrating: None,
+            joystick_mode: User::JOYSTICK_MODE,
+            last_reading: 0,
+            remainders: [0; 2],
+            slider_positions: [0; MAX_SLIDERS],
+            pending_volume: 0,
+            volume_key: None,
+            volume_timer: 0,
+            _phantom: PhantomData,
+        }
+    }
+}
+
+/// Tilt of a joystick axis, from -127 to 127, 0 in the dead zone.
+fn axis_tilt(value: u16, center: u16, deadzone: u16) -> i8 {
+    let (offset, range, sign) = if value >= center {
+        (value - center, ADC_MAX - center, 1)
+    } else {
+        (center - value, center, -1)
+    };
+    if offset <= deadzone {
+        return 0;
+    }
+    let range = range.saturating_sub(deadzone).max(1) as u32;
+    let tilt = ((offset - deadzone) as u32 * 127 / range).min(127) as i8;
+    tilt * sign
+}
+
+impl<User: Keyboard> OmkKeyboard<User> {
+    /// Returns true if the keyboard has a joystick.
+    #[inline(always)]
+    fn joystick_present() -> bool {
+        User::JOYSTICK_PINS.0 != NO_PIN
+    }
+
+    /// Returns true if the master half has analog inputs.
+    #[inline(always)]
+    fn analog_present() -> bool {
+        Self::joystick_present() || !User::SLIDER_PINS.is_empty()
+    }
+
+    /// Sets the analog pins up, and loads the calibration of the joystick, on the master.
+    pub(crate) fn analog_init(&mut self) {
+        const {
+            assert!(
+                User::SLIDER_PINS.len() <= MAX_SLIDERS,
+                "Too many sliders, see analog::MAX_SLIDERS"
+            )
+        };
+        if !Self::analog_present() || !is_master() {
+            return;
+        }
+        adc_init();
+        if Self::joystick_present() {
+            adc_pin_init(User::JOYSTICK_PINS.0);
+            adc_pin_init(User::JOYSTICK_PINS.1);
+            let stored = JOYSTICK_CALIBRATION_EEPROM.read();
+            if stored.magic == CALIBRATION_MAGIC {
+                self.analog_state.calibration = stored;
+            }
+        }
+        // The sliders start where they are, without changing the volume
+        for (slider, pin) in User::SLIDER_PINS.iter().enumerate() {
+            adc_pin_init(*pin);
+            let value = adc_read_average(*pin, User::ANALOG_SAMPLES);
+            self.analog_state.slider_positions[slider] = Self::slider_step(value);
+        }
+        self.analog_state.last_reading = timer_read();
+    }
+
+    /// Reads the analog inputs every [ANALOG_INTERVAL] ms, on the master.
+    pub(crate) fn analog_task(&mut self) {
+        if !Self::analog_present() || !is_master() {
+            return;
+        }
+        self.volume_task();
+        let elapsed = timer_elapsed(self.analog_state.last_reading);
+        if elapsed < ANALOG_INTERVAL as u32 {
+            return;
+        }
+        self.analog_state.last_reading = timer_read();
+        if Self::joystick_present() {
+            self.joystick_task(elapsed.min(MAX_READING_INTERVAL));
+        }
+        for (slider, pin) in User::SLIDER_PINS.iter().enumerate() {
+            self.slider_task(slider, adc_read_average(*pin, User::ANALOG_SAMPLES));
+        }
+    }
+
+    /// Starts a calibration of the joystick, which must rest for [CALIBRATION_TIME] ms.
+    pub fn joystick_calibrate(&mut self) {
+        self.analog_state.calibrating = Some(Calibrating {
+            start: timer_read(),
+            sum: [0; 2],
+            count: 0,
+            min: [ADC_MAX; 2],
+            max: [0; 2],
+        });
+    }
+
+    /// Sets the mode of the joystick, see [JoystickMode].
+    pub fn set_joystick_mode(&mut self, mode: JoystickMode) {
+        let analog = &mut self.analog_state;
+        if analog.joystick_mode == JoystickMode::Gamepad {
+            // The gamepad recenters when the joystick drives something else
+            set_gamepad_axis(GamepadAxis::X, 0);
+            set_gamepad_axis(GamepadAxis::Y, 0);
+        }
+        analog.joystick_mode = mode;
+        analog.remainders = [0; 2];
+    }
+
+    /// Changes the volume of the computer by `steps`, positive upward, tapping the volume keys
+    /// one at a time from the next tasks on.
+    pub fn change_volume(&mut self, steps: i8) {
+        let analog = &mut self.analog_state;
+        analog.pending_volume = analog.pending_volume.saturating_add(steps);
+    }
+
+    fn joystick_task(&mut self, elapsed: u32) {
+        let samples = User::ANALOG_SAMPLES;
+        let (x_pin, y_pin) = User::JOYSTICK_PINS;
+        let values = [
+            adc_read_average(x_pin, samples),
+            adc_read_average(y_pin, samples),
+        ];
+        let analog = &mut self.analog_state;
+
+        if let Some(calibrating) = &mut analog.calibrating {
+            for axis in 0..2 {
+                calibrating.sum[axis] += values[axis] as u32;
+                calibrating.min[axis] = calibrating.min[axis].min(values[axis]);
+                calibrating.max[axis] = calibrating.max[axis].max(values[axis]);
+            }
+            calibrating.count += 1;
+            if timer_elapsed(calibrating.start) >= CALIBRATION_TIME as u32 {
+                let count = calibrating.count as u32;
+                // The noise of the resting stick, on each side of the centre, stays in the dead zone
+                let noise = (0..2)
+                    .map(|axis| calibrating.max[axis] - calibrating.min[axis])
+                    .max()
+                    .unwrap_or(0);
+                analog.calibration = JoystickCalibration {
+                    magic: CALIBRATION_MAGIC,
+                    center: [
+                        (calibrating.sum[0] / count) as u16,
+                        (calibrating.sum[1] / count) as u16,
+                    ],
+                    deadzone: User::JOYSTICK_DEADZONE.max(noise),
+                };
+                analog.calibrating = None;
+                let mut eeprom = JOYSTICK_CALIBRATION_EEPROM;
+                // Spare the EEPROM write cycles if the calibration is already stored
+                if eeprom.read() != analog.calibration {
+                    eeprom.write(&analog.calibration);
+                }
+            }
+            return;
+        }
+
+        let calibration = analog.calibration;
+        let mut tilt = [0; 2];
+        for axis in 0..2 {
+            tilt[axis] = axis_tilt(values[axis], calibration.center[axis], calibration.deadzone);
+        }
+        if User::JOYSTICK_INVERT_X {
+            tilt[0] = -tilt[0];
+        }
+        if User::JOYSTICK_INVERT_Y {
+            tilt[1] = -tilt[1];
+        }
+
+        match analog.joystick_mode {
+            JoystickMode::Mouse => {
+                let mut moves = [0; 2];
+                for axis in 0..2 {
+                    // Squared, the tilt moves slowly near the centre
+                    let curve = tilt[axis] as i32 * (tilt[axis] as i32).abs();
+                    let total = analog.remainders[axis] as i64
+                        + curve as i64 * User::JOYSTICK_MOUSE_SPEED as i64 * elapsed as i64;
+                    moves[axis] = (total / MOTION_DIVISOR as i64) as i32;
+                    analog.remainders[axis] = (total % MOTION_DIVISOR as i64) as i32;
+                }
+                if moves != [0, 0] {
+                    let (current_x, current_y) = get_mouse_delta();
+                    set_mouse_delta(
+                        add_delta(current_x, moves[0].clamp(-127, 127) as i16),
+                        add_delta(current_y, moves[1].clamp(-127, 127) as i16),
+                    );
+                }
+            }
+            JoystickMode::Scroll => {
+                let (resolution_v, resolution_h) = wheel_resolution();
+                // Pushing up scrolls up
+                let speeds = [
+                    tilt[0] as i32 * resolution_h as i32,
+                    -(tilt[1] as i32) * resolution_v as i32,
+                ];
+                let mut moves = [0; 2];
+                for axis in 0..2 {
+                    let total = analog.remainders[axis] as i64
+                        + speeds[axis] as i64
+                            * 127
+                            * User::JOYSTICK_SCROLL_SPEED as i64
+                            * elapsed as i64;
+                    moves[axis] = (total / MOTION_DIVISOR as i64) as i32;
+                    analog.remainders[axis] = (total % MOTION_DIVISOR as i64) as i32;
+                }
+                if moves != [0, 0] {
+                    let (current_v, current_h) = get_wheel_delta();
+                    set_wheel_delta(
+                        add_delta(current_v, moves[1].clamp(-127, 127) as i16),
+                        add_delta(current_h, moves[0].clamp(-127, 127) as i16),
+                    );
+                }
+            }
+            JoystickMode::Gamepad => {
+                set_gamepad_axis(GamepadAxis::X, tilt[0]);
+                set_gamepad_axis(GamepadAxis::Y, tilt[1]);
+            }
+        }
+    }
+
+    /// Position of a slider reading, from 0 to `SLIDER_STEPS - 1`.
+    fn slider_step(value: u16) -> u8 {
+        let steps = User::SLIDER_STEPS.max(1) as u32;
+        ((value as u32 * steps / (ADC_MAX as u32 + 1)) as u8).min(steps as u8 - 1)
+    }
+
+    fn slider_task(&mut self, slider: usize, value: u16) {
+        let position = self.analog_state.slider_positions[slider];
+        // A quarter of step of hysteresis keeps the noise from toggling between two positions
+        let width = (ADC_MAX as u32 + 1) / User::SLIDER_STEPS.max(1) as u32;
+        let center = position as u32 * width + width / 2;
+        if (value as u32).abs_diff(center) <= width * 3 / 4 {
+            return;
+        }
+        let new_position = Self::slider_step(value);
+        if new_position != position {
+            self.analog_state.slider_positions[slider] = new_position;
+            let steps = (new_position as i16 - position as i16).clamp(-127, 127) as i8;
+            User::slider_handler(self, slider as u8, steps);
+        }
+    }
+
+    /// Taps the volume keys of [OmkKeyboard::change_volume], holding each for [VOLUME_TAP_TIME] ms.
+    fn volume_task(&mut self) {
+        let analog = &mut self.analog_state;
+        if let Some(code) = analog.volume_key {
+            if timer_elapsed(analog.volume_timer) >= VOLUME_TAP_TIME as u32 {
+                remove_code(code);
+                analog.volume_key = None;
+                analog.volume_timer = timer_read();
+            }
+        } else if analog.pending_volume != 0
+            && timer_elapsed(analog.volume_timer) >= VOLUME_TAP_TIME as u32
+        {
+            let code = if analog.pending_volume > 0 {
+                VOLUME_UP.0
+            } else {
+                VOLUME_DOWN.0
+            };
+            add_code(code);
+            analog.pending_volume -= analog.pending_volume.signum();
+            analog.volume_key = Some(code);
+            analog.volume_timer = timer_read();
+        }
+    }
+}
@@ -0,0 +1,245 @@
+//! This module provides a debug console to the computer, enabled with [Keyboard::CONSOLE].
+//! Text written with [print!](crate::print) and [println!](crate::println), or through [ConsoleWriter],
+//! is queued in a ring buffer, and sent to the host by the master half from its main loop.
+//! While the buffer is full, new text is dropped, so that printing never blocks the keyboard.
+//!
+//! [Console::Cdc] shows up as a serial port, e.g. `/dev/ttyACM0`, to open with any terminal.
+//! [Console::Hid] is the console of QMK, read with `hid_listen` or `qmk console`, and needs no driver.
+//!
+//! [debug!](crate::debug) prints lines only while the debug output is enabled, see [set_debug_enabled].
+
+use core::fmt::{self, Write};
+
+use lufa_rs::{
+    Endpoint_ClearIN, Endpoint_ClearOUT, Endpoint_ClearSETUP, Endpoint_ClearStatusStage,
+    Endpoint_IsOUTReceived, Endpoint_IsReadWriteAllowed, Endpoint_Read_Control_Stream_LE,
+    Endpoint_SelectEndpoint, Endpoint_Write_8, Endpoint_Write_Control_Stream_LE,
+    REQDIR_DEVICETOHOST, REQDIR_HOSTTODEVICE, REQREC_INTERFACE, REQTYPE_CLASS, USB_CONTROL_REQUEST,
+    USB_DEVICE_STATE, UsbDeviceStates,
+};
+
+use crate::{
+    atomic::atomic,
+    usb::{
+        CDC_RX_ENDPOINT_ADDR, CDC_TX_ENDPOINT_ADDR, CDC_TXRX_ENDPOINT_SIZE,
+        HID_CONSOLE_IN_ENDPOINT_ADDR, HID_CONSOLE_REPORT_SIZE,
+    },
+};
+
+/// Transport of the debug console.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Console {
+    /// No console, text written to it is discarded.
+    Disabled,
+    /// USB CDC-ACM virtual serial port.
+    Cdc,
+    /// HID interface on usage page 0xFF31, compatible with the QMK console.
+    Hid,
+}
+
+/// Size of the ring buffer, a power of two.
+const CONSOLE_BUFFER_SIZE: usize = 128;
+
+/// CDC class request setting the line coding (baud rate, ...), which is meaningless here.
+const CDC_REQ_SET_LINE_CODING: u8 = 0x20;
+/// CDC class request reading the line coding.
+const CDC_REQ_GET_LINE_CODING: u8 = 0x21;
+/// CDC class request setting the DTR and RTS lines.
+const CDC_REQ_SET_CONTROL_LINE_STATE: u8 = 0x22;
+/// DTR bit of the control line state, set while a terminal has the port open.
+const CDC_CONTROL_LINE_OUT_DTR: u16 = 1 << 0;
+
+static mut CONSOLE: Console = Console::Disabled;
+
+static mut BUFFER: [u8; CONSOLE_BUFFER_SIZE] = [0; CONSOLE_BUFFER_SIZE];
+/// Index of the next byte to send
+static mut HEAD: usize = 0;
+/// Count of bytes waiting in the buffer
+static mut LEN: usize = 0;
+/// Bytes dropped because the buffer was full
+static mut DROPPED: u16 = 0;
+
+/// Line coding of the virtual serial port, as set by the host: baud rate, stop bits, parity, data bits.
+static mut LINE_CODING: [u8; 7] = [0x00, 0xC2, 0x01, 0x00, 0, 0, 8];
+/// Set while a terminal has the port open.
+static mut HOST_LISTENING: bool = false;
+
+/// Set while [debug!](crate::debug) prints.
+static mut DEBUG_ENABLED: bool = true;
+
+/// Selects the console transport. Called at boot, before USB is initialized.
+pub(crate) fn console_init(console: Console) {
+    unsafe { CONSOLE = console };
+}
+
+/// Returns the console transport.
+#[inline(always)]
+pub fn console() -> Console {
+    unsafe { CONSOLE }
+}
+
+/// Returns the count of bytes dropped because the buffer was full, since boot.
+pub fn console_dropped() -> u16 {
+    unsafe { DROPPED }
+}
+
+/// Enables or disables the output of [debug!](crate::debug), enabled at boot.
+pub fn set_debug_enabled(enabled: bool) {
+    unsafe { DEBUG_ENABLED = enabled };
+}
+
+/// Returns true if [debug!](crate::debug) prints.
+#[inline(always)]
+pub fn debug_enabled() -> bool {
+    unsafe { DEBUG_ENABLED }
+}
+
+/// Queues bytes to send, dropping the ones not fitting in the buffer.
+pub fn console_write(bytes: &[u8]) {
+    if console() == Console::Disabled {
+        return;
+    }
+    // Text may be written from interrupt handlers
+    atomic(|| unsafe {
+        for byte in bytes {
+            if LEN == CONSOLE_BUFFER_SIZE {
+                DROPPED = DROPPED.saturating_add(1);
+                continue;
+            }
+            BUFFER[(HEAD + LEN) % CONSOLE_BUFFER_SIZE] = *byte;
+            LEN += 1;
+        }
+    });
+}
+
+/// Sink writing formatted text to the console.
+pub struct ConsoleWriter;
+
+impl Write for ConsoleWriter {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        console_write(s.as_bytes());
+        Ok(())
+    }
+}
+
+/// Writes formatted text to the console, used by [print!](crate::print).
+pub fn console_print(args: fmt::Arguments) {
+    if console() != Console::Disabled {
+        let _ = ConsoleWriter.write_fmt(args);
+    }
+}
+
+/// Prints to the debug console, see [crate::console].
+#[macro_export]
+macro_rules! print {
+    ($($arg:tt)*) => {
+        $crate::console::console_print(format_args!($($arg)*))
+    };
+}
+
+/// Prints to the debug console, with a newline, see [crate::console].
+#[macro_export]
+macro_rules! println {
+    () => {
+        $crate::console::console_write(b"\r\n")
+    };
+    ($($arg:tt)*) => {{
+        $crate::console::console_print(format_args!($($arg)*));
+        $crate::console::console_write(b"\r\n");
+    }};
+}
+
+/// Prints a line to the debug console while the debug output is enabled, see [crate::console].
+#[macro_export]
+macro_rules! debug {
+    ($($arg:tt)*) => {
+        if $crate::console::debug_enabled() {
+            $crate::println!($($arg)*);
+        }
+    };
+}
+
+/// Sends the queued text to the host, called from the USB task of the master.
+pub(crate) fn console_task() {
+    if unsafe { USB_DEVICE_STATE } != UsbDeviceStates::DeviceStateConfigured as u8 {
+        return;
+    }
+    match console() {
+        Console::Disabled => {}
+        Console::Cdc => unsafe {
+            // Nothing is read from the host, discard what it sends
+            Endpoint_SelectEndpoint(CDC_RX_ENDPOINT_ADDR);
+            if Endpoint_IsOUTReceived() {
+                Endpoint_ClearOUT();
+            }
+
+            // Keep the text until a terminal opens the port
+            if HOST_LISTENING {
+                send_buffer(CDC_TX_ENDPOINT_ADDR, CDC_TXRX_ENDPOINT_SIZE, false);
+            }
+        },
+        // Reports are always full, padded with zeros
+        Console::Hid => send_buffer(HID_CONSOLE_IN_ENDPOINT_ADDR, HID_CONSOLE_REPORT_SIZE, true),
+    }
+}
+
+/// Sends up to `size` bytes of the buffer in a packet of the `endpoint`, if it is ready.
+fn send_buffer(endpoint: u8, size: u8, pad: bool) {
+    unsafe {
+        if LEN == 0 {
+            return;
+        }
+        Endpoint_SelectEndpoint(endpoint);
+        if !Endpoint_IsReadWriteAllowed() {
+            return;
+        }
+        atomic(|| {
+            let count = LEN.min(size as usize);
+            for _ in 0..count {
+                Endpoint_Write_8(BUFFER[HEAD]);
+                HEAD = (HEAD + 1) % CONSOLE_BUFFER_SIZE;
+            }
+            LEN -= count;
+            if pad {
+                for _ in count..size as usize {
+                    Endpoint_Write_8(0);
+                }
+            }
+        });
+        Endpoint_ClearIN();
+    }
+}
+
+/// Handles the CDC class requests of the console control interface.
+pub(crate) fn cdc_control_request() {
+    const DEVICE_TO_HOST: u8 = (REQDIR_DEVICETOHOST | REQTYPE_CLASS | REQREC_INTERFACE) as u8;
+    const HOST_TO_DEVICE: u8 = (REQDIR_HOSTTODEVICE | REQTYPE_CLASS | REQREC_INTERFACE) as u8;
+
+    unsafe {
+        let request_type = USB_CONTROL_REQUEST.bm_request_type;
+        match USB_CONTROL_REQUEST.b_request {
+            CDC_REQ_GET_LINE_CODING if request_type == DEVICE_TO_HOST => {
+                Endpoint_ClearSETUP();
+                Endpoint_Write_Control_Stream_LE(
+                    &raw const LINE_CODING as *const _,
+                    LINE_CODING.len() as u16,
+                );
+                Endpoint_ClearOUT();
+            }
+            CDC_REQ_SET_LINE_CODING if request_type == HOST_TO_DEVICE => {
+                Endpoint_ClearSETUP();
+                Endpoint_Read_Control_Stream_LE(
+                    &raw mut LINE_CODING as *mut _,
+                    LINE_CODING.len() as u16,
+                );
+                Endpoint_ClearIN();
+            }
+            CDC_REQ_SET_CONTROL_LINE_STATE if request_type == HOST_TO_DEVICE => {
+                Endpoint_ClearSETUP();
+                Endpoint_ClearStatusStage();
+                HOST_LISTENING = USB_CONTROL_REQUEST.w_value & CDC_CONTROL_LINE_OUT_DTR != 0;
+            }
+            _ => {}
+        }
+    }
+}
@@ -0,0 +1,363 @@
+//! This module provides the debouncing algorithms of the matrix.
+//! The algorithm is selected with [Keyboard::Debouncer], and the debouncing time with [Keyboard::DEBOUNCE].
+//!
+//! - [SymDeferGlobal]: any change restarts a single timer, the whole matrix is updated once it expires.
+//! - [SymDeferPr]: a timer per row, the row is updated once it has been stable for the debouncing time.
+//! - [SymDeferPk]: same, with a timer per key.
+//! - [SymEagerPk]: a key change is reported immediately, then the key is ignored for the debouncing time.
+//! - [AsymEagerDeferPk]: presses are reported immediately, releases once stable for the debouncing time.
+
+use crate::{
+    Keyboard,
+    timer::{timer_elapsed, timer_read},
+};
+
+/// A debouncing algorithm, filtering the raw matrix of this half into the debounced one.
+pub trait Debouncer<User: Keyboard> {
+    /// Updates the debounced matrix of this half from the raw one.
+    ///
+    /// `changed` is true if the raw matrix changed since the last scan.
+    /// Returns `true` if the debounced matrix changed.
+    fn debounce(
+        &mut self,
+        raw: &[User::MatrixRowType],
+        cooked: &mut [User::MatrixRowType],
+        changed: bool,
+    ) -> bool;
+}
+
+/// Returns the milliseconds elapsed since `last_update`, saturated to `u8`, and moves `last_update` forward.
+fn elapsed_since(last_update: &mut u32) -> u8 {
+    let elapsed = timer_elapsed(*last_update);
+    *last_update = last_update.wrapping_add(elapsed);
+    elapsed.min(u8::MAX as u32) as u8
+}
+
+/// Calls `update` on each key of this half, with its raw state, its debounced state and its counter.
+/// `update` returns the new debounced state of the key.
+///
+/// Returns `true` if the debounced matrix changed.
+fn update_keys<User: Keyboard>(
+    raw: &[User::MatrixRowType],
+    cooked: &mut [User::MatrixRowType],
+    counters: &mut [[u8; User::MATRIX_COLUMNS]],
+    mut update: impl FnMut(bool, bool, &mut u8) -> bool,
+) -> bool {
+    let mut cooked_changed = false;
+    for row in 0..User::ROWS_PER_HAND {
+        let mut new_row = 0.into();
+        let mut mask: User::MatrixRowType = 1.into();
+        for column in 0..User::MATRIX_COLUMNS {
+            let raw_pressed = raw[row] & mask != 0.into();
+            let pressed = cooked[row] & mask != 0.into();
+            if update(raw_pressed, pressed, &mut counters[row][column]) {
+                new_row |= mask;
+            }
+            mask <<= 1;
+        }
+        if new_row != cooked[row] {
+            cooked[row] = new_row;
+            cooked_changed = true;
+        }
+    }
+    cooked_changed
+}
+
+/// Symmetric deferred debouncing, with a single timer for the whole half.
+///
+/// Any bounce delays every key, but it is the lightest algorithm.
+pub struct SymDeferGlobal {
+    debouncing: bool,
+    debouncing_time: u32,
+}
+
+impl const Default for SymDeferGlobal {
+    fn default() -> Self {
+        Self {
+            debouncing: false,
+            debouncing_time: 0,
+        }
+    }
+}
+
+impl<User: Keyboard> Debouncer<User> for SymDeferGlobal {
+    fn debounce(
+        &mut self,
+        raw: &[User::MatrixRowType],
+        cooked: &mut [User::MatrixRowType],
+        changed: bool,
+    ) -> bool {
+        let mut cooked_changed = false;
+
+        if changed {
+            self.debouncing = true;
+            self.debouncing_time = timer_read();
+        } else if self.debouncing && timer_elapsed(self.debouncing_time) >= User::DEBOUNCE as u32 {
+            if *cooked != *raw {
+                cooked.copy_from_slice(raw);
+                cooked_changed = true;
+            }
+            self.debouncing = false;
+        }
+
+        cooked_changed
+    }
+}
+
+/// Symmetric deferred debouncing, with a timer per row.
+pub struct SymDeferPr<User: Keyboard> {
+    counters: [u8; User::ROWS_PER_HAND],
+    /// Raw rows of the last scan, a row changing restarting its own timer only
+    last_raw: [User::MatrixRowType; User::ROWS_PER_HAND],
+    last_update: u32,
+}
+
+impl<User: Keyboard> const Default for SymDeferPr<User> {
+    fn default() -> Self {
+        Self {
+            counters: [0; _],
+            last_raw: [0.into(); _],
+            last_update: 0,
+        }
+    }
+}
+
+impl<User: Keyboard> Debouncer<User> for SymDeferPr<User> {
+    fn debounce(
+        &mut self,
+        raw: &[User::MatrixRowType],
+        cooked: &mut [User::MatrixRowType],
+        _changed: bool,
+    ) -> bool {
+        let elapsed = elapsed_since(&mut self.last_update);
+        defer_rows(
+            raw,
+            &mut self.last_raw,
+            cooked,
+            &mut self.counters,
+            elapsed,
+            User::DEBOUNCE,
+        )
+    }
+}
+
+/// Updates the rows of [SymDeferPr], `counters` holding the milliseconds left before each row is
+/// committed, `elapsed` ms after the last call.
+///
+/// Returns `true` if a debounced row changed.
+fn defer_rows<Row: PartialEq + Copy>(
+    raw: &[Row],
+    last_raw: &mut [Row],
+    cooked: &mut [Row],
+    counters: &mut [u8],
+    elapsed: u8,
+    debounce: u8,
+) -> bool {
+    let mut cooked_changed = false;
+    for row in 0..counters.len() {
+        let counter = &mut counters[row];
+        let bounced = raw[row] != last_raw[row];
+        last_raw[row] = raw[row];
+        if raw[row] == cooked[row] {
+            // Bounced back to the debounced state
+            *counter = 0;
+        } else if *counter == 0 || bounced {
+            // A change of the row restarts its timer, it must be stable for the whole time
+            *counter = debounce;
+        } else if *counter <= elapsed {
+            *counter = 0;
+            cooked[row] = raw[row];
+            cooked_changed = true;
+        } else {
+            *counter -= elapsed;
+        }
+    }
+    cooked_changed
+}
+
+/// Symmetric deferred debouncing, with a timer per key.
+pub struct SymDeferPk<User: Keyboard> {
+    counters: [[u8; User::MATRIX_COLUMNS]; User::ROWS_PER_HAND],
+    last_update: u32,
+}
+
+impl<User: Keyboard> const Default for SymDeferPk<User> {
+    fn default() -> Self {
+        Self {
+            counters: [[0; _]; _],
+            last_update: 0,
+        }
+    }
+}
+
+impl<User: Keyboard> Debouncer<User> for SymDeferPk<User> {
+    fn debounce(
+        &mut self,
+        raw: &[User::MatrixRowType],
+        cooked: &mut [User::MatrixRowType],
+        _changed: bool,
+    ) -> bool {
+        let elapsed = elapsed_since(&mut self.last_update);
+        update_keys::<User>(
+            raw,
+            cooked,
+            &mut self.counters,
+            |raw_pressed, pressed, counter| {
+                if raw_pressed == pressed {
+                    // Bounced back to the debounced state
+                    *counter = 0;
+                } else if *counter == 0 {
+                    *counter = User::DEBOUNCE;
+                } else if *counter <= elapsed {
+                    *counter = 0;
+                    return raw_pressed;
+                } else {
+                    *counter -= elapsed;
+                }
+                pressed
+            },
+        )
+    }
+}
+
+/// Symmetric eager debouncing, with a timer per key.
+///
+/// Gives the lowest latency, but is sensitive to noise, as a single glitch is reported as a key change.
+pub struct SymEagerPk<User: Keyboard> {
+    counters: [[u8; User::MATRIX_COLUMNS]; User::ROWS_PER_HAND],
+    last_update: u32,
+}
+
+impl<User: Keyboard> const Default for SymEagerPk<User> {
+    fn default() -> Self {
+        Self {
+            counters: [[0; _]; _],
+            last_update: 0,
+        }
+    }
+}
+
+impl<User: Keyboard> Debouncer<User> for SymEagerPk<User> {
+    fn debounce(
+        &mut self,
+        raw: &[User::MatrixRowType],
+        cooked: &mut [User::MatrixRowType],
+        _changed: bool,
+    ) -> bool {
+        let elapsed = elapsed_since(&mut self.last_update);
+        update_keys::<User>(
+            raw,
+            cooked,
+            &mut self.counters,
+            |raw_pressed, pressed, counter| {
+                *counter = counter.saturating_sub(elapsed);
+                if *counter == 0 && raw_pressed != pressed {
+                    // Report the change, then ignore the bounces
+                    *counter = User::DEBOUNCE;
+                    return raw_pressed;
+                }
+                pressed
+            },
+        )
+    }
+}
+
+/// Set in the counter of a key when it is a deferred release, and not an eager press.
+const RELEASE_PENDING: u8 = 1 << 7;
+
+/// Asymmetric debouncing, with a timer per key: eager on press, deferred on release.
+///
+/// [Keyboard::DEBOUNCE] must be below 128 ms.
+pub struct AsymEagerDeferPk<User: Keyboard> {
+    counters: [[u8; User::MATRIX_COLUMNS]; User::ROWS_PER_HAND],
+    last_update: u32,
+}
+
+impl<User: Keyboard> const Default for AsymEagerDeferPk<User> {
+    fn default() -> Self {
+        Self {
+            counters: [[0; _]; _],
+            last_update: 0,
+        }
+    }
+}
+
+impl<User: Keyboard> Debouncer<User> for AsymEagerDeferPk<User> {
+    fn debounce(
+        &mut self,
+        raw: &[User::MatrixRowType],
+        cooked: &mut [User::MatrixRowType],
+        _changed: bool,
+    ) -> bool {
+        const {
+            assert!(
+                User::DEBOUNCE < RELEASE_PENDING,
+                "AsymEagerDeferPk needs a DEBOUNCE below 128 ms"
+            )
+        };
+        let elapsed = elapsed_since(&mut self.last_update);
+        update_keys::<User>(
+            raw,
+            cooked,
+            &mut self.counters,
+            |raw_pressed, pressed, counter| {
+                let release_pending = *counter & RELEASE_PENDING != 0;
+                let time = *counter & !RELEASE_PENDING;
+                if time != 0 {
+                    if release_pending && raw_pressed {
+                        // Bounced back to pressed, cancel the release
+                        *counter = 0;
+                    } else if time <= elapsed {
+                        *counter = 0;
+                        if release_pending {
+                            return false;
+                        }
+                    } else {
+                        *counter -= elapsed;
+                    }
+                } else if raw_pressed != pressed {
+                    if raw_pressed {
+                        *counter = User::DEBOUNCE;
+                        return true;
+                    }
+                    *counter = RELEASE_PENDING | User::DEBOUNCE;
+                }
+                pressed
+            },
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn defer_rows_independent() {
+        let mut last_raw = [0u16; 2];
+        let mut cooked = [0u16; 2];
+        let mut counters = [0u8; 2];
+        // Row 0 settles on column 13 at once, row 1 bounces every millisecond
+        for ms in 0..5u16 {
+            let raw = [1 << 13, ms % 2];
+            assert!(!defer_rows(
+                &raw,
+                &mut last_raw,
+                &mut cooked,
+                &mut counters,
+                1,
+                5
+            ));
+        }
+        let raw = [1 << 13, 1];
+        assert!(defer_rows(
+            &raw,
+            &mut last_raw,
+            &mut cooked,
+            &mut counters,
+            1,
+            5
+        ));
+        assert_eq!(cooked, [1 << 13, 0]);
+    }
+}
@@ -0,0 +1,459 @@
+//! This module stores an editable copy of the keymap in EEPROM, when [Keyboard::DYNAMIC_KEYMAP] is enabled.
+//! Keys are stored as 16 bits QMK keycodes, the ones used by VIA, so that a configurator can edit them live.
+//! Keys without a keycode ([CustomKey::keycode] returning `None`) are stored as [KEYCODE_STATIC],
+//! which keeps the key of [Keyboard::KEYMAP] at this position.
+//!
+//! The keymap is copied from [Keyboard::KEYMAP] at boot if the header doesn't match the current layout.
+//! Edits are stored in the EEPROM of the half connected to the computer.
+//!
+//! # EEPROM layout
+//!
+//! From address 0, the EEPROM holds:
+//! - the `.eeprom` section of the firmware, with the `#[eeprom]` statics,
+//! - the header of the dynamic keymap, then the keymap, layer by layer, in big endian, then the macros buffer,
+//! - free space,
+//! - the calibration of the joystick,
+//! - the handedness byte, the last one.
+//!
+//! The calibration and the handedness aren't `#[eeprom]` statics, so that flashing the `.eeprom`
+//! section of a firmware keeps them.
+
+use avr_delay::delay_ms;
+use lufa_rs::USB_USBTask;
+
+use crate::{
+    Keyboard, OmkKeyboard,
+    analog::JOYSTICK_CALIBRATION_ADDRESS,
+    eeprom::{EepromPtr, EepromPtrMut},
+    is_master,
+    keymap::{CustomKey, Key},
+    keys::{
+        LayerHold, MouseAccelerator0, MouseAccelerator1, MouseAccelerator2, MouseButton4,
+        MouseButton5, MouseDown, MouseLeft, MouseLeftClick, MouseRight, MouseRightClick, MouseUp,
+        MouseWheelClick, MouseWheelDown, MouseWheelLeft, MouseWheelRight, MouseWheelUp, NO_OP,
+        RESET, TRANSPARENT_UP,
+    },
+    primitive::{IndexByValue, progmem::ProgmemRef},
+    progmem,
+    usb::{add_code, remove_code, send_next_keyboard_report},
+};
+
+/// Empty key.
+pub const KC_NO: u16 = 0x0000;
+/// Transparent key, see [crate::keys::TransparentUp].
+pub const KC_TRNS: u16 = 0x0001;
+/// First mouse keycode: up, down, left, right, then the left, right and wheel buttons.
+pub const KC_MS_UP: u16 = 0x00CD;
+/// Mouse buttons 4 and 5.
+pub const KC_MS_BTN4: u16 = 0x00D4;
+/// First mouse wheel keycode: up, down, left, right.
+pub const KC_MS_WH_UP: u16 = 0x00D9;
+/// First mouse accelerator keycode, from the slowest to the fastest, see [crate::mouse].
+pub const KC_MS_ACCEL0: u16 = 0x00DD;
+/// Momentary layer switch, the layer being in the low 5 bits, see [LayerHold].
+pub const QK_MOMENTARY: u16 = 0x5220;
+/// Macro played from the macros buffer, its number being in the low 7 bits, see [MacroKey].
+pub const QK_MACRO: u16 = 0x7700;
+/// Jump to the bootloader.
+pub const QK_BOOT: u16 = 0x7C00;
+/// Key kept from [Keyboard::KEYMAP], for keys without keycode. First keyboard specific keycode.
+pub const KEYCODE_STATIC: u16 = 0x7E00;
+
+/// Identifies a dynamic keymap in EEPROM.
+const EEPROM_MAGIC: u16 = 0x4F4D;
+/// Size of the header: magic, layer count, then key count per layer, padded.
+const HEADER_SIZE: u16 = 8;
+
+unsafe extern "C" {
+    /// End of the `.eeprom` section, defined by the linker script of avr-gcc.
+    static __eeprom_end: u8;
+}
+
+/// Address of the header, right after the `.eeprom` section.
+fn header_address() -> u16 {
+    // The linker maps the EEPROM at 0x810000, 16 bits pointers keep the address in the EEPROM
+    &raw const __eeprom_end as usize as u16
+}
+
+/// Address of the keymap.
+pub(crate) fn keymap_address() -> u16 {
+    header_address() + HEADER_SIZE
+}
+
+/// Prefix of a special action in a macro.
+const SS_QMK_PREFIX: u8 = 1;
+/// Macro action: press then release the following keycode.
+const SS_TAP_CODE: u8 = 1;
+/// Macro action: press the following keycode.
+const SS_DOWN_CODE: u8 = 2;
+/// Macro action: release the following keycode.
+const SS_UP_CODE: u8 = 3;
+/// Macro action: wait for the following milliseconds, in ASCII digits terminated by `|`.
+const SS_DELAY_CODE: u8 = 4;
+/// Macro actions on 16 bits keycodes, which aren't supported.
+const SS_TAP_CODE_16: u8 = 5;
+const SS_UP_CODE_16: u8 = 7;
+
+/// Left shift, in the keyboard report.
+const KC_LEFT_SHIFT: u8 = 0xE1;
+/// Flag of the ASCII table for characters needing shift.
+const SHIFTED: u8 = 0x80;
+
+/// Keycodes of the printable ASCII characters on a US layout, from space to `~`.
+#[rustfmt::skip]
+const ASCII_TO_KEYCODE: [u8; 95] = [
+    0x2C, 0x1E | SHIFTED, 0x34 | SHIFTED, 0x20 | SHIFTED, // space ! " #
+    0x21 | SHIFTED, 0x22 | SHIFTED, 0x24 | SHIFTED, 0x34, // $ % & '
+    0x26 | SHIFTED, 0x27 | SHIFTED, 0x25 | SHIFTED, 0x2E | SHIFTED, // ( ) * +
+    0x36, 0x2D, 0x37, 0x38, // , - . /
+    0x27, 0x1E, 0x1F, 0x20, 0x21, 0x22, 0x23, 0x24, 0x25, 0x26, // 0 - 9
+    0x33 | SHIFTED, 0x33, 0x36 | SHIFTED, 0x2E, 0x37 | SHIFTED, 0x38 | SHIFTED, // : ; < = > ?
+    0x1F | SHIFTED, // @
+    0x04 | SHIFTED, 0x05 | SHIFTED, 0x06 | SHIFTED, 0x07 | SHIFTED, 0x08 | SHIFTED, // A - E
+    0x09 | SHIFTED, 0x0A | SHIFTED, 0x0B | SHIFTED, 0x0C | SHIFTED, 0x0D | SHIFTED, // F - J
+    0x0E | SHIFTED, 0x0F | SHIFTED, 0x10 | SHIFTED, 0x11 | SHIFTED, 0x12 | SHIFTED, // K - O
+    0x13 | SHIFTED, 0x14 | SHIFTED, 0x15 | SHIFTED, 0x16 | SHIFTED, 0x17 | SHIFTED, // P - T
+    0x18 | SHIFTED, 0x19 | SHIFTED, 0x1A | SHIFTED, 0x1B | SHIFTED, 0x1C | SHIFTED, // U - Y
+    0x1D | SHIFTED, // Z
+    0x2F, 0x31, 0x30, 0x23 | SHIFTED, 0x2D | SHIFTED, 0x35, // [ \ ] ^ _ `
+    0x04, 0x05, 0x06, 0x07, 0x08, 0x09, 0x0A, 0x0B, 0x0C, 0x0D, 0x0E, 0x0F, 0x10, // a - m
+    0x11, 0x12, 0x13, 0x14, 0x15, 0x16, 0x17, 0x18, 0x19, 0x1A, 0x1B, 0x1C, 0x1D, // n - z
+    0x2F | SHIFTED, 0x31 | SHIFTED, 0x30 | SHIFTED, 0x35 | SHIFTED, // { | } ~
+];
+
+/// Reads a byte of the EEPROM.
+pub(crate) fn eeprom_read(address: u16) -> u8 {
+    unsafe { EepromPtr::new(address as *const u8).read_byte() }
+}
+
+/// Writes a byte of the EEPROM, sparing the write cycle if the value is already stored.
+pub(crate) fn eeprom_update(address: u16, value: u8) {
+    let ptr = EepromPtrMut::new(address as *mut u8);
+    unsafe {
+        if ptr.read_byte() != value {
+            ptr.write_byte(value);
+        }
+    }
+}
+
+/// Keycodes having a [DynamicKey], as their first keycode and their count.
+/// The other keycodes do nothing.
+const DYNAMIC_KEYCODES: [(u16, u16); 4] = [
+    (0, 0x100),
+    (QK_MOMENTARY, 0x20),
+    (QK_MACRO, 0x80),
+    (QK_BOOT, 1),
+];
+
+/// Number of keycodes having a [DynamicKey].
+const DYNAMIC_KEY_COUNT: usize = {
+    let mut count = 0;
+    let mut range = 0;
+    while range < DYNAMIC_KEYCODES.len() {
+        count += DYNAMIC_KEYCODES[range].1 as usize;
+        range += 1;
+    }
+    count
+};
+
+/// Returns a key per keycode of [DYNAMIC_KEYCODES], in order.
+const fn dynamic_keys() -> [DynamicKey; DYNAMIC_KEY_COUNT] {
+    let mut keys = [DynamicKey(0); DYNAMIC_KEY_COUNT];
+    let mut index = 0;
+    let mut range = 0;
+    while range < DYNAMIC_KEYCODES.len() {
+        let (first, count) = DYNAMIC_KEYCODES[range];
+        let mut keycode = first;
+        while keycode < first + count {
+            keys[index] = DynamicKey(keycode);
+            index += 1;
+            keycode += 1;
+        }
+        range += 1;
+    }
+    keys
+}
+
+/// Keys of the dynamic keymap, so that any of them is a `&'static dyn CustomKey` without using RAM.
+#[progmem]
+static DYNAMIC_KEYS: [DynamicKey; DYNAMIC_KEY_COUNT] = dynamic_keys();
+
+/// A key of the dynamic keymap, decoding its keycode on each press and release.
+///
+/// Keys only live in [DYNAMIC_KEYS], in progmem: the keycode is read from there, never through `self.0`.
+#[derive(Clone, Copy)]
+pub struct DynamicKey(u16);
+
+impl DynamicKey {
+    /// Returns the key of a keycode.
+    pub fn from_keycode<User: Keyboard>(keycode: u16) -> &'static dyn CustomKey<User> {
+        if keycode == KC_NO {
+            return NO_OP;
+        }
+        if keycode == KC_TRNS {
+            return TRANSPARENT_UP;
+        }
+        let mut index = 0;
+        for (first, count) in DYNAMIC_KEYCODES {
+            if keycode >= first && keycode - first < count {
+                let key = DYNAMIC_KEYS.at(index + (keycode - first) as usize);
+                // The reference is only used by the methods below, which read it from progmem
+                return unsafe { &*key.as_ptr().address() };
+            }
+            index += count as usize;
+        }
+        NO_OP
+    }
+
+    /// Returns the keycode of the key, read from [DYNAMIC_KEYS].
+    fn code(&self) -> u16 {
+        unsafe { ProgmemRef::new(self as *const Self) }.read().0
+    }
+
+    /// Calls `f` with the key matching the keycode, if it has one.
+    fn with_key<User: Keyboard>(&self, f: impl FnOnce(&dyn CustomKey<User>)) {
+        match self.code() {
+            c if c == KC_MS_UP => f(&MouseUp),
+            c if c == KC_MS_UP + 1 => f(&MouseDown),
+            c if c == KC_MS_UP + 2 => f(&MouseLeft),
+            c if c == KC_MS_UP + 3 => f(&MouseRight),
+            c if c == KC_MS_UP + 4 => f(&MouseLeftClick),
+            c if c == KC_MS_UP + 5 => f(&MouseRightClick),
+            c if c == KC_MS_UP + 6 => f(&MouseWheelClick),
+            c if c == KC_MS_BTN4 => f(&MouseButton4),
+            c if c == KC_MS_BTN4 + 1 => f(&MouseButton5),
+            c if c == KC_MS_WH_UP => f(&MouseWheelUp),
+            c if c == KC_MS_WH_UP + 1 => f(&MouseWheelDown),
+            c if c == KC_MS_WH_UP + 2 => f(&MouseWheelLeft),
+            c if c == KC_MS_WH_UP + 3 => f(&MouseWheelRight),
+            c if c == KC_MS_ACCEL0 => f(&MouseAccelerator0),
+            c if c == KC_MS_ACCEL0 + 1 => f(&MouseAccelerator1),
+            c if c == KC_MS_ACCEL0 + 2 => f(&MouseAccelerator2),
+            c if c <= 0xFF => f(&Key(c as u8)),
+            c if c & !0x1F == QK_MOMENTARY => f(&LayerHold((c & 0x1F) as u8)),
+            c if c & !0x7F == QK_MACRO => f(&MacroKey((c & 0x7F) as u8)),
+            QK_BOOT => f(RESET),
+            _ => {}
+        }
+    }
+}
+
+impl<User: Keyboard> CustomKey<User> for DynamicKey {
+    fn complete_on_pressed(&self, keyboard: &mut OmkKeyboard<User>, row: u8, column: u8) {
+        self.with_key::<User>(|key| key.complete_on_pressed(keyboard, row, column));
+    }
+
+    fn complete_on_released(
+        &self,
+        keyboard: &mut OmkKeyboard<User>,
+        row: u8,
+        column: u8,
+        key_actual_layer: u8,
+    ) {
+        self.with_key::<User>(|key| {
+            key.complete_on_released(keyboard, row, column, key_actual_layer)
+        });
+    }
+
+    fn keycode(&self) -> Option<u16> {
+        Some(self.code())
+    }
+}
+
+/// Types the macro of this number stored in the macros buffer, on press.
+pub struct MacroKey(pub u8);
+
+impl<User: Keyboard> CustomKey<User> for MacroKey {
+    fn on_pressed(&self, _keyboard: &mut OmkKeyboard<User>) {
+        // Only the master half talks to the host
+        if is_master() {
+            OmkKeyboard::<User>::play_macro(self.0);
+        }
+    }
+
+    fn keycode(&self) -> Option<u16> {
+        Some(QK_MACRO | self.0 as u16)
+    }
+}
+
+/// Sends the keyboard report, then waits for the host to read it.
+fn flush_report() {
+    send_next_keyboard_report();
+    unsafe { USB_USBTask() };
+    delay_ms::<10>();
+}
+
+/// Presses or releases a basic keycode, and sends it to the host.
+fn macro_key(keycode: u8, pressed: bool) {
+    if pressed {
+        add_code(keycode);
+    } else {
+        remove_code(keycode);
+    }
+    flush_report();
+}
+
+impl<User: Keyboard> OmkKeyboard<User> {
+    /// Size in bytes of the keymap in EEPROM.
+    pub(crate) const DYNAMIC_KEYMAP_SIZE: u16 =
+        (User::LAYER_COUNT * User::MATRIX_KEYS_COUNT * 2) as u16;
+    /// Address of the macros buffer in EEPROM.
+    pub(crate) fn macros_address() -> u16 {
+        keymap_address() + Self::DYNAMIC_KEYMAP_SIZE
+    }
+
+    /// Copies [Keyboard::KEYMAP] into EEPROM if the stored keymap doesn't match the layout.
+    pub(crate) fn dynamic_keymap_init() {
+        const {
+            assert!(
+                HEADER_SIZE + Self::DYNAMIC_KEYMAP_SIZE + User::DYNAMIC_KEYMAP_MACRO_BUFFER_SIZE
+                    <= JOYSTICK_CALIBRATION_ADDRESS,
+                "The dynamic keymap and its macros don't fit in EEPROM"
+            )
+        };
+        // The size of the `.eeprom` section is only known once linked
+        assert!(
+            Self::macros_address() + User::DYNAMIC_KEYMAP_MACRO_BUFFER_SIZE
+                <= JOYSTICK_CALIBRATION_ADDRESS,
+            "The dynamic keymap doesn't fit in EEPROM after the .eeprom section"
+        );
+        let [magic_high, magic_low] = EEPROM_MAGIC.to_be_bytes();
+        let [keys_high, keys_low] = (User::MATRIX_KEYS_COUNT as u16).to_be_bytes();
+        let header = [
+            magic_high,
+            magic_low,
+            User::LAYER_COUNT as u8,
+            keys_high,
+            keys_low,
+        ];
+        let valid = header
+            .iter()
+            .enumerate()
+            .all(|(i, byte)| eeprom_read(header_address() + i as u16) == *byte);
+        if !valid {
+            Self::dynamic_keymap_reset();
+            Self::dynamic_keymap_macro_reset();
+            for (i, byte) in header.iter().enumerate() {
+                eeprom_update(header_address() + i as u16, *byte);
+            }
+        }
+    }
+
+    /// Copies [Keyboard::KEYMAP] into EEPROM.
+    pub fn dynamic_keymap_reset() {
+        for layer in 0..User::LAYER_COUNT {
+            for index in 0..User::MATRIX_KEYS_COUNT {
+                let key = User::KEYMAP.at(layer).at(index).read();
+                Self::set_keycode(layer as u8, index, key.keycode().unwrap_or(KEYCODE_STATIC));
+            }
+        }
+    }
+
+    /// Clears the macros buffer.
+    pub fn dynamic_keymap_macro_reset() {
+        for offset in 0..User::DYNAMIC_KEYMAP_MACRO_BUFFER_SIZE {
+            eeprom_update(Self::macros_address() + offset, 0);
+        }
+    }
+
+    /// Returns the keycode stored at an index of a layer.
+    pub fn get_keycode(layer: u8, index: usize) -> u16 {
+        let address =
+            keymap_address() + ((layer as usize * User::MATRIX_KEYS_COUNT + index) * 2) as u16;
+        u16::from_be_bytes([eeprom_read(address), eeprom_read(address + 1)])
+    }
+
+    /// Stores the keycode at an index of a layer.
+    pub fn set_keycode(layer: u8, index: usize, keycode: u16) {
+        let address =
+            keymap_address() + ((layer as usize * User::MATRIX_KEYS_COUNT + index) * 2) as u16;
+        let [high, low] = keycode.to_be_bytes();
+        eeprom_update(address, high);
+        eeprom_update(address + 1, low);
+    }
+
+    /// Returns the key of the dynamic keymap, `None` if it is kept from [Keyboard::KEYMAP].
+    pub(crate) fn get_dynamic_key(layer: u8, index: usize) -> Option<&'static dyn CustomKey<User>> {
+        match Self::get_keycode(layer, index) {
+            KEYCODE_STATIC => None,
+            keycode => Some(DynamicKey::from_keycode(keycode)),
+        }
+    }
+
+    /// Types a macro of the macros buffer, macros being separated by a null byte.
+    pub fn play_macro(number: u8) {
+        let mut address = Self::macros_address();
+        let end = address + User::DYNAMIC_KEYMAP_MACRO_BUFFER_SIZE;
+        // Skip the previous macros
+        let mut skipped = 0;
+        while skipped < number {
+            if address >= end {
+                return;
+            }
+            if eeprom_read(address) == 0 {
+                skipped += 1;
+            }
+            address += 1;
+        }
+
+        let mut next = || {
+            if address >= end {
+                return 0;
+            }
+            address += 1;
+            eeprom_read(address - 1)
+        };
+        loop {
+            match next() {
+                0 => break,
+                SS_QMK_PREFIX => match next() {
+                    SS_TAP_CODE => {
+                        let keycode = next();
+                        macro_key(keycode, true);
+                        macro_key(keycode, false);
+                    }
+                    SS_DOWN_CODE => macro_key(next(), true),
+                    SS_UP_CODE => macro_key(next(), false),
+                    SS_DELAY_CODE => {
+                        let mut delay: u16 = 0;
+                        loop {
+                            match next() {
+                                digit @ b'0'..=b'9' => {
+                                    delay = delay.saturating_mul(10) + (digit - b'0') as u16
+                                }
+                                _ => break,
+                            }
+                        }
+                        for _ in 0..delay {
+                            delay_ms::<1>();
+                        }
+                    }
+                    SS_TAP_CODE_16..=SS_UP_CODE_16 => {
+                        next();
+                        next();
+                    }
+                    _ => {}
+                },
+                b'\n' => Self::type_keycode(0x28, false),
+                b'\t' => Self::type_keycode(0x2B, false),
+                c @ b' '..=b'~' => {
+                    let keycode = ASCII_TO_KEYCODE[(c - b' ') as usize];
+                    Self::type_keycode(keycode & !SHIFTED, keycode & SHIFTED != 0);
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// Taps a keycode, holding shift around it if needed.
+    fn type_keycode(keycode: u8, shift: bool) {
+        if shift {
+            macro_key(KC_LEFT_SHIFT, true);
+        }
+        macro_key(keycode, true);
+        macro_key(keycode, false);
+        if shift {
+            macro_key(KC_LEFT_SHIFT, false);
+        }
+    }
+}
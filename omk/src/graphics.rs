@@ -316,7 +316,11 @@ impl<User: Keyboard + PrivateConfig> OmkKeyboard<User> {
         }
     }
 
-    pub fn draw_u8(mut n: u8, offset_x: u8, offset_y: u8) {
+    pub fn draw_u8(n: u8, offset_x: u8, offset_y: u8) {
+        Self::draw_u16(n as u16, offset_x, offset_y);
+    }
+
+    pub fn draw_u16(mut n: u16, offset_x: u8, offset_y: u8) {
         if !User::HAVE_SCREEN {
             return;
         }
@@ -331,7 +335,7 @@ impl<User: Keyboard + PrivateConfig> OmkKeyboard<User> {
         }
         for i in 0..len {
             Self::draw_char(
-                (b'0' + n % 10) as char,
+                (b'0' + (n % 10) as u8) as char,
                 offset_x + (len - i - 1) * User::CHAR_WIDTH,
                 offset_y,
             );
@@ -5,32 +5,29 @@ const I2C_TIMEOUT_INFINITE: u32 = u16::MAX as u32;
 
 use crate::timer::timer_elapsed16;
 use crate::timer::timer_read;
+use core::iter::once;
 use core::ptr::read_volatile;
 use core::ptr::write_volatile;
 
 // I2C
-#[allow(dead_code)]
 const I2C_ACTION_READ: u8 = 0x01;
 const I2C_ACTION_WRITE: u8 = 0x00;
 
 // Registres I2C/TWI pour ATmega32u4 (Section 22.9 de la datasheet)
 const TWBR: *mut u8 = 0xB8 as *mut u8; // Bit Rate Register
 const TWSR: *mut u8 = 0xB9 as *mut u8; // Status Register
-#[allow(dead_code)]
 const TWAR: *mut u8 = 0xBA as *mut u8; // Address Register
 const TWDR: *mut u8 = 0xBB as *mut u8; // Data Register
 const TWCR: *mut u8 = 0xBC as *mut u8; // Control Register
 
 // Masques de bits pour TWCR
 const TWINT: u8 = 0x80; // Interrupt Flag
-#[allow(dead_code)]
 const TWEA: u8 = 0x40; // Enable Acknowledge
 const TWSTA: u8 = 0x20; // Start Condition
 const TWSTO: u8 = 0x10; // Stop Condition
 #[allow(dead_code)]
 const TWWC: u8 = 0x08; // Write Collision
 const TWEN: u8 = 0x04; // Enable
-#[allow(dead_code)]
 const TWIE: u8 = 0x01; // Interrupt Enable
 
 // Codes d'état TWSR (masqués avec TWPS)
@@ -38,6 +35,19 @@ const START: u8 = 0x08; // Start condition transmitted
 const REP_START: u8 = 0x10; // Repeated start transmitted
 const MT_SLA_ACK: u8 = 0x18; // SLA+W transmitted, ACK received
 const MT_DATA_ACK: u8 = 0x28; // Data transmitted, ACK received
+const MR_SLA_ACK: u8 = 0x40; // SLA+R transmitted, ACK received
+const MR_DATA_ACK: u8 = 0x50; // Data received, ACK returned
+const MR_DATA_NACK: u8 = 0x58; // Data received, NACK returned
+
+// Target mode status codes
+const SR_SLA_ACK: u8 = 0x60; // Own SLA+W received, ACK returned
+const SR_ARB_LOST_SLA_ACK: u8 = 0x68; // Arbitration lost, own SLA+W received
+const SR_DATA_ACK: u8 = 0x80; // Data received after own SLA+W, ACK returned
+const SR_STOP: u8 = 0xA0; // STOP or repeated START received while addressed
+const ST_SLA_ACK: u8 = 0xA8; // Own SLA+R received, ACK returned
+const ST_ARB_LOST_SLA_ACK: u8 = 0xB0; // Arbitration lost, own SLA+R received
+const ST_DATA_ACK: u8 = 0xB8; // Data transmitted, ACK received
+const BUS_ERROR: u8 = 0x00; // Illegal START or STOP condition
 
 const F_SCL: u64 = 400000;
 const F_CPU: u64 = 16000000;
@@ -126,7 +136,7 @@ pub fn i2c_write(data: u8, timeout: u16) -> Result<(), I2CError> {
         i2c_wait(timeout)?;
 
         match read_volatile(TWSR) & 0xF8 {
-            MT_SLA_ACK | MT_DATA_ACK => Result::Ok(()),
+            MT_SLA_ACK | MT_DATA_ACK | MR_SLA_ACK => Result::Ok(()),
             _ => Err(I2CError()),
         }
     }
@@ -157,3 +167,209 @@ pub fn i2c_transmit<T: Iterator<Item = u8>>(
     i2c_stop();
     Ok(())
 }
+
+/// Reads a byte of data from the I2C bus.
+///
+/// # Arguments
+/// * `ack` - Whether the byte should be acknowledged, `false` for the last byte of a transfer.
+/// * `timeout` - The timeout duration in milliseconds.
+///
+/// Returns the received byte, or an `I2CError` otherwise.
+pub fn i2c_read(ack: bool, timeout: u16) -> Result<u8, I2CError> {
+    unsafe {
+        write_volatile(TWCR, TWINT | TWEN | if ack { TWEA } else { 0 });
+
+        i2c_wait(timeout)?;
+
+        match read_volatile(TWSR) & 0xF8 {
+            MR_DATA_ACK | MR_DATA_NACK => Ok(read_volatile(TWDR)),
+            _ => Err(I2CError()),
+        }
+    }
+}
+
+/// Receives a sequence of bytes from a specific I2C address.
+///
+/// # Arguments
+/// * `address` - The I2C address of the target device.
+/// * `data` - The buffer to fill with the received bytes.
+/// * `timeout` - The timeout duration in milliseconds.
+///
+/// Returns `Ok(())` if the reception was successful, or an `I2CError` otherwise.
+pub fn i2c_receive(address: u8, data: &mut [u8], timeout: u16) -> Result<(), I2CError> {
+    i2c_start(timeout)?;
+
+    // Set address
+    i2c_write(address | I2C_ACTION_READ, timeout)?;
+
+    let len = data.len();
+    for (i, byte) in data.iter_mut().enumerate() {
+        *byte = i2c_read(i + 1 < len, timeout)?;
+    }
+
+    i2c_stop();
+    Ok(())
+}
+
+/// Transmits a sequence of bytes to a specific I2C address, then receives bytes from it after
+/// a repeated start, without releasing the bus in between.
+///
+/// # Arguments
+/// * `address` - The I2C address of the target device.
+/// * `data` - An iterator over the bytes to transmit, e.g. the register to read.
+/// * `buffer` - The buffer to fill with the received bytes.
+/// * `timeout` - The timeout duration in milliseconds.
+///
+/// Returns `Ok(())` if the transfer was successful, or an `I2CError` otherwise.
+pub fn i2c_write_read<T: Iterator<Item = u8>>(
+    address: u8,
+    data: T,
+    buffer: &mut [u8],
+    timeout: u16,
+) -> Result<(), I2CError> {
+    let result = (|| {
+        i2c_start(timeout)?;
+
+        // Set address
+        i2c_write(address | I2C_ACTION_WRITE, timeout)?;
+
+        for byte in data {
+            i2c_write(byte, timeout)?;
+        }
+
+        // Repeated start, the bus stays ours
+        i2c_start(timeout)?;
+        i2c_write(address | I2C_ACTION_READ, timeout)?;
+
+        let len = buffer.len();
+        for (i, byte) in buffer.iter_mut().enumerate() {
+            *byte = i2c_read(i + 1 < len, timeout)?;
+        }
+        Ok(())
+    })();
+
+    // Release the bus even if the transfer failed, or the next one couldn't start
+    i2c_stop();
+    result
+}
+
+/// Reads consecutive registers of a device, starting at `register`.
+///
+/// Returns `Ok(())` if the reception was successful, or an `I2CError` otherwise.
+pub fn i2c_read_register(
+    address: u8,
+    register: u8,
+    buffer: &mut [u8],
+    timeout: u16,
+) -> Result<(), I2CError> {
+    i2c_write_read(address, once(register), buffer, timeout)
+}
+
+/// Timeout of the transfers of [I2CController], in milliseconds.
+const I2C_CONTROLLER_TIMEOUT: u16 = 10;
+
+/// A device on an I2C bus, the microcontroller being the controller.
+///
+/// Device drivers talk to an [I2CBus], so that they can run against a mock bus on the host.
+/// Addresses are 7 bits addresses, shifted left by one.
+pub trait I2CBus {
+    /// Configures the bus, at boot.
+    fn init(&mut self) {}
+    /// Transmits `data` to the device at `address`.
+    fn write(&mut self, address: u8, data: &[u8]) -> Result<(), I2CError>;
+    /// Transmits `data` to the device at `address`, then fills `buffer` after a repeated start.
+    fn write_read(&mut self, address: u8, data: &[u8], buffer: &mut [u8]) -> Result<(), I2CError>;
+}
+
+/// The I2C bus of the microcontroller.
+#[derive_const(Default)]
+pub struct I2CController;
+
+impl I2CBus for I2CController {
+    fn init(&mut self) {
+        i2c_init();
+    }
+    fn write(&mut self, address: u8, data: &[u8]) -> Result<(), I2CError> {
+        i2c_transmit(address, data.iter().copied(), I2C_CONTROLLER_TIMEOUT)
+    }
+    fn write_read(&mut self, address: u8, data: &[u8], buffer: &mut [u8]) -> Result<(), I2CError> {
+        i2c_write_read(
+            address,
+            data.iter().copied(),
+            buffer,
+            I2C_CONTROLLER_TIMEOUT,
+        )
+    }
+}
+
+/// A register map exposed on the bus when the TWI is in target (slave) mode.
+///
+/// The first byte written by the controller after addressing the target selects the register,
+/// following bytes are written to consecutive registers, and reads start from the selected one.
+pub trait I2CTargetRegisters {
+    /// Returns the value of the register at `register`, sent to the controller.
+    fn read(register: u8) -> u8;
+    /// Writes `data`, received from the controller, to the register at `register`.
+    fn write(register: u8, data: u8);
+    /// Called when the controller ends a transfer with this target.
+    fn stop() {}
+}
+
+/// Register currently selected by the controller.
+static mut TARGET_REGISTER: u8 = 0;
+/// Whether the register has already been selected in the current write transfer.
+static mut TARGET_REGISTER_SELECTED: bool = false;
+
+/// Initializes the I2C interface in target (slave) mode.
+///
+/// # Arguments
+/// * `address` - The 7 bits address this device answers to.
+///
+/// Once initialized, transfers are handled by [i2c_target_interrupt] from the TWI interrupt.
+pub fn i2c_target_init(address: u8) {
+    unsafe {
+        write_volatile(TWAR, address << 1);
+        write_volatile(TWCR, TWIE | TWEA | TWINT | TWEN);
+    }
+}
+
+/// Handles the TWI interrupt in target mode, serving the register map `R`.
+///
+/// # Safety
+/// Should only be called by the TWI interrupt, after [i2c_target_init].
+#[inline(always)]
+pub unsafe fn i2c_target_interrupt<R: I2CTargetRegisters>() {
+    unsafe {
+        match read_volatile(TWSR) & 0xF8 {
+            SR_SLA_ACK | SR_ARB_LOST_SLA_ACK => {
+                TARGET_REGISTER_SELECTED = false;
+            }
+            SR_DATA_ACK => {
+                let data = read_volatile(TWDR);
+                if TARGET_REGISTER_SELECTED {
+                    R::write(TARGET_REGISTER, data);
+                    TARGET_REGISTER = TARGET_REGISTER.wrapping_add(1);
+                } else {
+                    TARGET_REGISTER = data;
+                    TARGET_REGISTER_SELECTED = true;
+                }
+            }
+            ST_SLA_ACK | ST_ARB_LOST_SLA_ACK | ST_DATA_ACK => {
+                write_volatile(TWDR, R::read(TARGET_REGISTER));
+                TARGET_REGISTER = TARGET_REGISTER.wrapping_add(1);
+            }
+            SR_STOP => {
+                R::stop();
+            }
+            BUS_ERROR => {
+                // Release the bus and wait to be addressed again
+                write_volatile(TWCR, TWIE | TWEA | TWINT | TWSTO | TWEN);
+                return;
+            }
+            _ => {}
+        }
+
+        // Clear the interrupt flag, and keep answering to our address
+        write_volatile(TWCR, TWIE | TWEA | TWINT | TWEN);
+    }
+}
@@ -0,0 +1,121 @@
+//! This module provides the idle mode of the matrix, saving power while no key is pressed.
+//! After [Keyboard::IDLE_TIMEOUT] ms without any key down, every line of the matrix is driven low,
+//! so that a key press pulls its other line low, and the CPU sleeps between two interrupts instead of scanning.
+//! The timer interrupt wakes it up every millisecond, and a key press on a port B pin wakes it up at once.
+//! Full scanning resumes as soon as a key is pressed.
+
+use core::arch::asm;
+
+use avr_base::{
+    pins::{NO_PIN, PINB_ADDRESS, PORT_SHIFTER, Pin},
+    register::{PCICR, PCIE0, PCIF0, PCIFR, PCMSK0, SE, SMCR},
+};
+
+use crate::{
+    Keyboard, OmkKeyboard,
+    matrix::MatrixTopology,
+    timer::{timer_elapsed, timer_read},
+};
+
+static mut IDLE: bool = false;
+static mut LAST_ACTIVITY: u32 = 0;
+
+/// Puts the CPU in idle sleep until the next interrupt, the timer one coming within a millisecond.
+pub fn cpu_sleep() {
+    SMCR.write(SE);
+    unsafe { asm!("sleep") };
+    SMCR.write(0);
+}
+
+impl<User: Keyboard> OmkKeyboard<User> {
+    /// Returns true if the matrix is in idle mode.
+    #[inline(always)]
+    pub fn is_idle() -> bool {
+        unsafe { IDLE }
+    }
+
+    /// Puts the CPU to sleep until the next interrupt, if the matrix is in idle mode.
+    pub fn idle_sleep() {
+        if Self::is_idle() {
+            cpu_sleep();
+        }
+    }
+
+    /// Enters the idle mode if no key has been down for [Keyboard::IDLE_TIMEOUT] ms.
+    pub(crate) fn idle_update(&self, keys_down: bool) {
+        const {
+            assert!(
+                User::IDLE_TIMEOUT == 0 || User::IDLE_TIMEOUT > User::DEBOUNCE as u16,
+                "IDLE_TIMEOUT must be above DEBOUNCE"
+            )
+        };
+        if keys_down {
+            unsafe { LAST_ACTIVITY = timer_read() };
+        } else if unsafe { timer_elapsed(LAST_ACTIVITY) } >= User::IDLE_TIMEOUT as u32 {
+            Self::idle_enter();
+        }
+    }
+
+    /// Returns true if a key is down while in idle mode, in which case the idle mode is left.
+    pub(crate) fn idle_wake_up(&self) -> bool {
+        let any_low = |pins: &[Pin]| pins.iter().any(|pin| !Self::read_matrix_pin(*pin));
+        let key_down = match User::MATRIX_TOPOLOGY {
+            MatrixTopology::Col2Row => any_low(&User::COL_PINS),
+            MatrixTopology::Row2Col => any_low(&User::ROW_PINS),
+            MatrixTopology::DirectPins => User::DIRECT_PINS.iter().any(|row| any_low(row)),
+            MatrixTopology::Duplex => true,
+        } || any_low(&Self::encoder_switch_pins());
+        if key_down {
+            PCICR.write(PCICR & !PCIE0);
+            PCMSK0.write(0);
+            // Unselect every line
+            self.matrix_init();
+            unsafe {
+                IDLE = false;
+                LAST_ACTIVITY = timer_read();
+            }
+        }
+        key_down
+    }
+
+    /// Drives low the lines of the matrix, and arms the pin change interrupt on the ones read.
+    fn idle_enter() {
+        match User::MATRIX_TOPOLOGY {
+            MatrixTopology::Col2Row => {
+                for pin in User::ROW_PINS {
+                    if pin != NO_PIN {
+                        Self::gpio_atomic_set_pin_output_low(pin);
+                    }
+                }
+                Self::idle_arm_pin_change(&User::COL_PINS);
+            }
+            MatrixTopology::Row2Col => {
+                for pin in User::COL_PINS {
+                    if pin != NO_PIN {
+                        Self::gpio_atomic_set_pin_output_low(pin);
+                    }
+                }
+                Self::idle_arm_pin_change(&User::ROW_PINS);
+            }
+            MatrixTopology::DirectPins => {
+                for row in User::DIRECT_PINS {
+                    Self::idle_arm_pin_change(&row);
+                }
+            }
+            // Both diode directions can't be watched at once
+            MatrixTopology::Duplex => return,
+        }
+        PCIFR.write(PCIF0);
+        PCICR.write(PCICR | PCIE0);
+        unsafe { IDLE = true };
+    }
+
+    /// Enables the pin change interrupt of the pins of port B, the only port having one.
+    fn idle_arm_pin_change(pins: &[Pin]) {
+        for pin in pins {
+            if *pin != NO_PIN && pin.0 >> PORT_SHIFTER == PINB_ADDRESS {
+                PCMSK0.write(PCMSK0 | 1 << (pin.0 & 0xF));
+            }
+        }
+    }
+}
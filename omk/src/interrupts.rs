@@ -31,4 +31,16 @@ pub trait InterruptsHandler<User: crate::Keyboard + InterruptsHandler<User>>:
     unsafe fn serial_interrupt() {
         OmkKeyboard::<User>::serial_interrupt();
     }
+    /// # Safety
+    /// Should only be called by TWI interrupt. #[entry] macro should take care of that
+    #[inline(always)]
+    unsafe fn i2c_interrupt() {
+        OmkKeyboard::<User>::split_i2c_interrupt();
+    }
+    /// # Safety
+    /// Should only be called by PCINT0 interrupt. #[entry] macro should take care of that
+    ///
+    /// It only wakes the CPU up from the idle mode sleep.
+    #[inline(always)]
+    unsafe fn pin_change_interrupt() {}
 }
@@ -33,6 +33,12 @@ pub trait CustomKey<User: Keyboard>: Send + Sync {
 
     /// Defines the action to perform when the key is released.
     fn on_released(&self, _keyboard: &mut OmkKeyboard<User>) {}
+
+    /// QMK keycode of the key, to store it in the dynamic keymap.
+    /// `None` if the key has no keycode, in which case the dynamic keymap keeps it as is.
+    fn keycode(&self) -> Option<u16> {
+        None
+    }
 }
 
 /// Represents a basic key with a predefined keycode.
@@ -48,6 +54,10 @@ impl<User: Keyboard> CustomKey<User> for Key {
     fn on_released(&self, _keyboard: &mut OmkKeyboard<User>) {
         remove_code(self.0);
     }
+
+    fn keycode(&self) -> Option<u16> {
+        Some(self.0 as u16)
+    }
 }
 
 /// Represents a single layer in the keymap.
@@ -59,3 +69,10 @@ pub type Layer<User: Keyboard> = [&'static dyn CustomKey<User>; User::MATRIX_KEY
 ///
 /// The keymap is a 3D array where each layer contains a 2D array of custom keys.
 pub type Keymap<User: Keyboard> = [Layer<User>; User::LAYER_COUNT];
+
+/// Represents the keys tapped by the rotary encoders, see [crate::rotary_encoder].
+///
+/// For each layer and each encoder, the encoders of the left half first, the key tapped by a
+/// counter-clockwise step then the one tapped by a clockwise step.
+pub type EncoderMap<User: Keyboard> =
+    [[[&'static dyn CustomKey<User>; 2]; User::ENCODER_COUNT]; User::LAYER_COUNT];
@@ -6,12 +6,21 @@ use core::cell::SyncUnsafeCell;
 use keyboard_macros::key_alias;
 
 use crate::{
-    Keyboard, OmkKeyboard, is_master,
+    Keyboard, OmkKeyboard,
+    analog::JoystickMode,
+    dynamic_keymap::{
+        KC_MS_ACCEL0, KC_MS_BTN4, KC_MS_UP, KC_MS_WH_UP, KC_NO, KC_TRNS, QK_BOOT, QK_MOMENTARY,
+    },
+    is_master,
     keymap::{CustomKey, Key},
+    pointing::PointingMode,
     serial::wait_for_next_serial_interrupt,
+    side::store_handedness,
     usb::{
-        mouse_left_click_press, mouse_left_click_release, mouse_right_click_press,
-        mouse_right_click_release, mouse_wheel_click_press, mouse_wheel_click_release,
+        ABSOLUTE_POINTER_MAX, gamepad_button_press, gamepad_button_release, mouse_button_press,
+        mouse_button_release, mouse_left_click_press, mouse_left_click_release,
+        mouse_right_click_press, mouse_right_click_release, mouse_wheel_click_press,
+        mouse_wheel_click_release, set_pointer_position,
     },
 };
 
@@ -316,13 +325,23 @@ pub const TRANSPARENT_UP: &TransparentUp = &TransparentUp;
 /// Reset the keyboard on press
 pub const RESET: &Reset = &Reset;
 
+/// Store in EEPROM that the half this key is on is the left one
+pub const HAND_L: &SetHandedness = &SetHandedness { left: true };
+
+/// Store in EEPROM that the half this key is on is the right one
+pub const HAND_R: &SetHandedness = &SetHandedness { left: false };
+
 // ******************************
 // And their impl
 
 /// Represents a no-operation key that does nothing when pressed.
 pub struct NoOpKey;
 
-impl<User: Keyboard> CustomKey<User> for NoOpKey {}
+impl<User: Keyboard> CustomKey<User> for NoOpKey {
+    fn keycode(&self) -> Option<u16> {
+        Some(KC_NO)
+    }
+}
 
 /// Represents a key that changes the current layer up by a specified amount.
 pub struct LayerUp(pub u8);
@@ -362,6 +381,10 @@ impl<User: Keyboard> CustomKey<User> for LayerHold {
     ) {
         keyboard.layer = key_actual_layer;
     }
+
+    fn keycode(&self) -> Option<u16> {
+        Some(QK_MOMENTARY | self.0 as u16)
+    }
 }
 
 /// Represents a key that transparently passes the key press to the layer above.
@@ -388,6 +411,10 @@ impl<User: Keyboard> CustomKey<User> for TransparentUp {
             .get_key(layer, row, column)
             .complete_on_pressed(keyboard, row, column);
     }
+
+    fn keycode(&self) -> Option<u16> {
+        Some(KC_TRNS)
+    }
 }
 
 pub struct Reset;
@@ -401,6 +428,26 @@ impl<User: Keyboard> CustomKey<User> for Reset {
 
         avr_base::reset_to_bootloader();
     }
+
+    fn keycode(&self) -> Option<u16> {
+        Some(QK_BOOT)
+    }
+}
+
+/// Stores the handedness in EEPROM, on the half this key physically is on only.
+/// It is used from the next boot on, if [Keyboard::HANDEDNESS] is [crate::side::Handedness::Eeprom].
+pub struct SetHandedness {
+    pub left: bool,
+}
+
+impl<User: Keyboard> CustomKey<User> for SetHandedness {
+    fn complete_on_pressed(&self, _keyboard: &mut OmkKeyboard<User>, row: u8, _column: u8) {
+        // Both halves see the key press, only the one owning the row stores it
+        let this_hand = OmkKeyboard::<User>::this_hand_offset() as u8;
+        if (this_hand..this_hand + User::ROWS_PER_HAND as u8).contains(&row) {
+            store_handedness(self.left);
+        }
+    }
 }
 
 //assume hold and tap_hold are modifier, aka press-releasing them does nothing
@@ -453,7 +500,7 @@ impl<
 }
 
 macro_rules! mouse_movement {
-    ($struct:ident, $field:ident) => {
+    ($struct:ident, $field:ident, $keycode:expr) => {
         pub struct $struct;
 
         impl<User: Keyboard> CustomKey<User> for $struct {
@@ -463,14 +510,44 @@ macro_rules! mouse_movement {
             fn on_released(&self, keyboard: &mut OmkKeyboard<User>) {
                 keyboard.mouse_state.$field = false;
             }
+            fn keycode(&self) -> Option<u16> {
+                Some($keycode)
+            }
         }
     };
 }
 
-mouse_movement! { MouseUp, up }
-mouse_movement! { MouseDown, down }
-mouse_movement! { MouseLeft, left }
-mouse_movement! { MouseRight, right }
+mouse_movement! { MouseUp, up, KC_MS_UP }
+mouse_movement! { MouseDown, down, KC_MS_UP + 1 }
+mouse_movement! { MouseLeft, left, KC_MS_UP + 2 }
+mouse_movement! { MouseRight, right, KC_MS_UP + 3 }
+mouse_movement! { MouseWheelUp, wheel_up, KC_MS_WH_UP }
+mouse_movement! { MouseWheelDown, wheel_down, KC_MS_WH_UP + 1 }
+mouse_movement! { MouseWheelLeft, wheel_left, KC_MS_WH_UP + 2 }
+mouse_movement! { MouseWheelRight, wheel_right, KC_MS_WH_UP + 3 }
+
+/// Sets the speed of the mouse keys while held, see [crate::mouse].
+macro_rules! mouse_accelerator {
+    ($struct:ident, $accelerator:expr) => {
+        pub struct $struct;
+
+        impl<User: Keyboard> CustomKey<User> for $struct {
+            fn on_pressed(&self, keyboard: &mut OmkKeyboard<User>) {
+                keyboard.mouse_state.accelerators |= 1 << $accelerator;
+            }
+            fn on_released(&self, keyboard: &mut OmkKeyboard<User>) {
+                keyboard.mouse_state.accelerators &= !(1 << $accelerator);
+            }
+            fn keycode(&self) -> Option<u16> {
+                Some(KC_MS_ACCEL0 + $accelerator)
+            }
+        }
+    };
+}
+
+mouse_accelerator! { MouseAccelerator0, 0 }
+mouse_accelerator! { MouseAccelerator1, 1 }
+mouse_accelerator! { MouseAccelerator2, 2 }
 
 pub struct MouseLeftClick;
 
@@ -481,6 +558,9 @@ impl<User: Keyboard> CustomKey<User> for MouseLeftClick {
     fn on_released(&self, _: &mut OmkKeyboard<User>) {
         mouse_left_click_release();
     }
+    fn keycode(&self) -> Option<u16> {
+        Some(KC_MS_UP + 4)
+    }
 }
 
 pub struct MouseRightClick;
@@ -492,6 +572,9 @@ impl<User: Keyboard> CustomKey<User> for MouseRightClick {
     fn on_released(&self, _: &mut OmkKeyboard<User>) {
         mouse_right_click_release();
     }
+    fn keycode(&self) -> Option<u16> {
+        Some(KC_MS_UP + 5)
+    }
 }
 
 pub struct MouseWheelClick;
@@ -503,6 +586,130 @@ impl<User: Keyboard> CustomKey<User> for MouseWheelClick {
     fn on_released(&self, _: &mut OmkKeyboard<User>) {
         mouse_wheel_click_release();
     }
+    fn keycode(&self) -> Option<u16> {
+        Some(KC_MS_UP + 6)
+    }
+}
+
+macro_rules! mouse_button {
+    ($struct:ident, $button:expr) => {
+        pub struct $struct;
+
+        impl<User: Keyboard> CustomKey<User> for $struct {
+            fn on_pressed(&self, _: &mut OmkKeyboard<User>) {
+                mouse_button_press($button);
+            }
+            fn on_released(&self, _: &mut OmkKeyboard<User>) {
+                mouse_button_release($button);
+            }
+            fn keycode(&self) -> Option<u16> {
+                Some(KC_MS_BTN4 + $button - 3)
+            }
+        }
+    };
+}
+
+mouse_button! { MouseButton4, 3 }
+mouse_button! { MouseButton5, 4 }
+
+/// Moves the cursor to an absolute screen position, from 0 to [ABSOLUTE_POINTER_MAX] on each axis.
+/// Needs [Keyboard::ABSOLUTE_POINTER].
+pub struct PointerWarp {
+    pub x: u16,
+    pub y: u16,
+}
+
+impl PointerWarp {
+    /// Moves the cursor to the center of a region of the screen, split in `columns` x `rows`.
+    ///
+    /// ```ignore
+    /// // Top right region of a 3x3 grid
+    /// PointerWarp::grid(2, 0, 3, 3)
+    /// ```
+    pub const fn grid(column: u8, row: u8, columns: u8, rows: u8) -> Self {
+        const fn center(index: u8, count: u8) -> u16 {
+            ((2 * index as u32 + 1) * ABSOLUTE_POINTER_MAX as u32 / (2 * count as u32)) as u16
+        }
+        Self {
+            x: center(column, columns),
+            y: center(row, rows),
+        }
+    }
+}
+
+impl<User: Keyboard> CustomKey<User> for PointerWarp {
+    fn on_pressed(&self, _: &mut OmkKeyboard<User>) {
+        set_pointer_position(self.x, self.y);
+    }
+}
+
+/// Switches the pointing device to a mode while held, see [crate::pointing].
+macro_rules! pointing_mode {
+    ($struct:ident, $mode:expr) => {
+        pub struct $struct;
+
+        impl<User: Keyboard> CustomKey<User> for $struct {
+            fn on_pressed(&self, keyboard: &mut OmkKeyboard<User>) {
+                keyboard.set_pointing_mode($mode);
+            }
+            fn on_released(&self, keyboard: &mut OmkKeyboard<User>) {
+                keyboard.set_pointing_mode(PointingMode::Normal);
+            }
+        }
+    };
+}
+
+pointing_mode! { PointingSniping, PointingMode::Sniping }
+pointing_mode! { PointingDragScroll, PointingMode::DragScroll }
+
+/// Sets the resolution of the pointing device, in counts per inch.
+pub struct PointingCpi(pub u16);
+
+impl<User: Keyboard> CustomKey<User> for PointingCpi {
+    fn on_pressed(&self, keyboard: &mut OmkKeyboard<User>) {
+        keyboard.set_pointing_cpi(self.0);
+    }
+}
+
+/// Switches the joystick to a mode while held, see [crate::analog].
+macro_rules! joystick_mode {
+    ($struct:ident, $mode:expr) => {
+        pub struct $struct;
+
+        impl<User: Keyboard> CustomKey<User> for $struct {
+            fn on_pressed(&self, keyboard: &mut OmkKeyboard<User>) {
+                keyboard.set_joystick_mode($mode);
+            }
+            fn on_released(&self, keyboard: &mut OmkKeyboard<User>) {
+                keyboard.set_joystick_mode(User::JOYSTICK_MODE);
+            }
+        }
+    };
+}
+
+joystick_mode! { JoystickMouse, JoystickMode::Mouse }
+joystick_mode! { JoystickScroll, JoystickMode::Scroll }
+joystick_mode! { JoystickGamepad, JoystickMode::Gamepad }
+
+/// Measures the centre and the dead zone of the joystick, which must rest meanwhile, and stores them in EEPROM.
+pub struct JoystickCalibrate;
+
+impl<User: Keyboard> CustomKey<User> for JoystickCalibrate {
+    fn on_pressed(&self, keyboard: &mut OmkKeyboard<User>) {
+        keyboard.joystick_calibrate();
+    }
+}
+
+/// A button of the gamepad, from 0 to 7. Needs [Keyboard::GAMEPAD].
+pub struct GamepadButton(pub u8);
+
+impl<User: Keyboard> CustomKey<User> for GamepadButton {
+    fn on_pressed(&self, _: &mut OmkKeyboard<User>) {
+        gamepad_button_press(self.0);
+    }
+    fn on_released(&self, _: &mut OmkKeyboard<User>) {
+        gamepad_button_release(self.0);
+    }
 }
 
 pub struct DummyKey;
@@ -18,48 +18,71 @@
 // We are on only one proc, with one thread, so there is no need to worry about static mut ref
 #![allow(static_mut_refs)]
 
-use avr_base::{
-    pins::Pin,
-    register::{USBCON, USBE},
-};
+use avr_base::pins::{NO_PIN, Pin};
 use avr_delay::{delay_ms, delay_us};
 use core::{
     arch::asm,
     cell::SyncUnsafeCell,
-    marker::PhantomData,
     ops::{BitAnd, BitOrAssign, ShlAssign, ShrAssign},
     panic::PanicInfo,
 };
 mod limited_storage;
 use crate::{
+    analog::{JoystickMode, OmkAnalog},
+    console::{Console, console_init, console_task},
+    debounce::{Debouncer, SymDeferGlobal},
     init::disable_watchdog,
     interrupts::InterruptsHandler,
-    keymap::{CustomKey, Keymap},
+    keymap::{CustomKey, EncoderMap, Keymap},
     limited_storage::LimitedStorage,
+    matrix::MatrixTopology,
+    mouse::{MouseKeysMode, OmkMouse},
+    pointing::{NoPointingDevice, OmkPointing, PointingDevice},
     primitive::{Array2D, BinPackedArray, IndexByValue, progmem::ProgmemRef},
-    rotary_encoder::RotaryEncoder,
-    serial::shared_memory::{MasterSharedMemory, SlaveSharedMemory},
-    timer::timer_init,
-    usb::{events::hid_task, get_mouse_delta, set_mouse_delta},
+    rotary_encoder::{EncoderMapState, RotaryEncoder},
+    serial::{
+        SplitTransport,
+        shared_memory::{MasterSharedMemory, SlaveSharedMemory},
+    },
+    side::{Handedness, MasterElection, side_init},
+    timer::{timer_init, timer_read_us},
+    usb::{
+        absolute_pointer_init, events::hid_task, gamepad_init, raw_hid_read, raw_hid_send,
+        send_remote_wakeup,
+    },
 };
 use keyboard_macros::progmem;
 pub use limited_storage::Oom;
-use lufa_rs::{USB_Init, USB_USBTask};
+use lufa_rs::USB_USBTask;
 
+pub mod adc;
+pub mod analog;
 pub mod atomic;
+pub mod console;
+pub mod debounce;
+pub mod dynamic_keymap;
 pub mod graphics;
 pub mod i2c;
+pub mod idle;
 pub mod init;
 pub mod keymap;
 pub mod keys;
 pub mod matrix;
+pub mod mouse;
+pub mod pointing;
 pub mod primitive;
+pub mod profiling;
 pub use primitive::{eeprom, progmem};
 pub mod interrupts;
 pub mod rotary_encoder;
 pub mod serial;
+pub mod side;
+pub mod spi;
+pub mod suspend;
+pub use side::{is_left, is_master, is_right, side};
 pub mod timer;
 pub mod usb;
+pub mod via;
 
 pub trait Keyboard: Sized + const Default + 'static + PrivateConfig {
     type const LAYER_COUNT: usize;
@@ -67,7 +90,7 @@ pub trait Keyboard: Sized + const Default + 'static + PrivateConfig {
 
     type const MATRIX_ROWS: usize;
     type const MATRIX_COLUMNS: usize;
-    /// Smallest type containing at least MATRIX_ROWS bits
+    /// Smallest type containing at least MATRIX_COLUMNS bits, e.g. `u16` for 14 columns
     type MatrixRowType: PartialEq
         + BitAnd<Output = Self::MatrixRowType>
         + BitOrAssign
@@ -78,24 +101,77 @@ pub trait Keyboard: Sized + const Default + 'static + PrivateConfig {
 
     const ROW_PINS: [Pin; Self::ROWS_PER_HAND];
     const COL_PINS: [Pin; Self::MATRIX_COLUMNS];
-    const LEFT_ENCODER_PIN1: Pin;
-    const LEFT_ENCODER_PIN2: Pin;
-    const RIGHT_ENCODER_PIN1: Pin;
-    const RIGHT_ENCODER_PIN2: Pin;
-    const ROTARY_ENCODER_PIN1: Pin = if is_left() {
-        Self::LEFT_ENCODER_PIN1
-    } else {
-        Self::RIGHT_ENCODER_PIN1
-    };
-    const ROTARY_ENCODER_PIN2: Pin = if is_left() {
-        Self::LEFT_ENCODER_PIN2
-    } else {
-        Self::RIGHT_ENCODER_PIN2
-    };
+    /// How the switches are wired, see [MatrixTopology]
+    const MATRIX_TOPOLOGY: MatrixTopology = MatrixTopology::Col2Row;
+    /// Ignore the key changes that may be ghosts, on boards with no diodes on part of the matrix
+    const ANTI_GHOSTING: bool = false;
+    /// Minimum time between two scans while the host is suspended, in milliseconds. 0 keeps the full rate.
+    const SUSPEND_SCAN_INTERVAL: u16 = 0;
+    /// Measure the scan rate and the latencies, see [profiling]
+    const PROFILING: bool = false;
+    /// Debug console to the computer, see [console]
+    const CONSOLE: Console = Console::Disabled;
+    /// USB vendor ID
+    const USB_VENDOR_ID: u16 = 0xFC32;
+    /// USB product ID
+    const USB_PRODUCT_ID: u16 = 0x0287;
+    /// Version of the keyboard, in binary coded decimal: 0x0123 is 1.2.3
+    const USB_DEVICE_VERSION: u16 = 0x0001;
+    /// Manufacturer name shown by the computer
+    const USB_MANUFACTURER: &'static str = "Surv&madcodder";
+    /// Product name shown by the computer
+    const USB_PRODUCT: &'static str = "Rust Keyboard & Mouse";
+    /// Give a serial number, read from the microcontroller, to tell apart several keyboards on a computer
+    const USB_SERIAL_NUMBER: bool = false;
+    /// Milliseconds without any key down before the matrix goes idle, see [idle]. 0 disables it.
+    /// It must be above `DEBOUNCE`.
+    const IDLE_TIMEOUT: u16 = 0;
+    /// Pin of each key of a half, used when `MATRIX_TOPOLOGY` is [MatrixTopology::DirectPins]
+    const DIRECT_PINS: [[Pin; Self::MATRIX_COLUMNS]; Self::ROWS_PER_HAND] =
+        [[NO_PIN; Self::MATRIX_COLUMNS]; Self::ROWS_PER_HAND];
+    /// Number of rotary encoders of each half, 0 without encoders
+    type const ENCODERS_PER_HAND: usize;
+    /// Pins A and B of each rotary encoder of the left half
+    const LEFT_ENCODERS: [(Pin, Pin); Self::ENCODERS_PER_HAND];
+    /// Pins A and B of each rotary encoder of the right half, the ones of the left half by default
+    const RIGHT_ENCODERS: [(Pin, Pin); Self::ENCODERS_PER_HAND] = Self::LEFT_ENCODERS;
+    /// Switch pin of each rotary encoder of the left half, pressed when low, `NO_PIN` without switch
+    const LEFT_ENCODER_SWITCHES: [Pin; Self::ENCODERS_PER_HAND] = [NO_PIN; Self::ENCODERS_PER_HAND];
+    /// Switch pin of each rotary encoder of the right half, pressed when low, `NO_PIN` without switch
+    const RIGHT_ENCODER_SWITCHES: [Pin; Self::ENCODERS_PER_HAND] =
+        [NO_PIN; Self::ENCODERS_PER_HAND];
+    /// Row and column in the matrix of its half of the switch of each encoder, a position without key,
+    /// the switch then being a key of the keymap. Mandatory when an encoder has a switch
+    const ENCODER_SWITCH_POSITIONS: Option<[(u8, u8); Self::ENCODERS_PER_HAND]> = None;
+    /// Pulses of the encoders making a step
     const ROTARY_ENCODER_RESOLUTION: i8 = 1;
+    /// Steps of the encoders spun fast are multiplied by up to this factor, 1 disables the acceleration
+    const ENCODER_ACCELERATION: u8 = 1;
+    /// Milliseconds between two steps of an encoder under which they are accelerated
+    const ENCODER_ACCELERATION_INTERVAL: u16 = 60;
+    /// Keys tapped by each step of the encoders, see [rotary_encoder]
+    const ENCODER_MAP: Option<progmem::ProgmemRef<EncoderMap<Self>>> = None;
+
+    /// Debouncing algorithm of the matrix, see [debounce]
+    type Debouncer: Debouncer<Self> + const Default = SymDeferGlobal;
+    /// Debouncing time, in milliseconds. 0 disables the debouncing
+    const DEBOUNCE: u8 = 5;
 
     const RED_LED_PIN: Pin;
-    const SOFT_SERIAL_PIN: Pin;
+    /// `false` for unibody keyboards, which have no link to another half and scan the whole matrix.
+    /// `ROWS_PER_HAND` must then be `MATRIX_ROWS`, and the keymap follows the matrix row by row.
+    const SPLIT: bool = true;
+    /// Pin of the link between both halves, needed by [SplitTransport::SoftSerial] only
+    const SOFT_SERIAL_PIN: Pin = NO_PIN;
+    /// Link used between both halves
+    const SPLIT_TRANSPORT: SplitTransport = SplitTransport::SoftSerial;
+    /// 7 bits I2C address of the slave half, used when `SPLIT_TRANSPORT` is [SplitTransport::I2C]
+    const SPLIT_I2C_ADDRESS: u8 = 0x32;
+    /// How each half knows whether it is the left or the right one
+    const HANDEDNESS: Handedness = Handedness::BuildVariable;
+    /// How the half connected to the computer is detected at boot.
+    /// The left half by default, [MasterElection::Vbus] lets either half be plugged in.
+    const MASTER_ELECTION: MasterElection = MasterElection::Left;
 
     const FONT_DIM: (u8, u8, usize);
     type const CHAR_WIDTH: u8;
@@ -106,20 +182,80 @@ pub trait Keyboard: Sized + const Default + 'static + PrivateConfig {
 
     /// This **MUST** be in progmem !
     const KEYMAP: progmem::ProgmemRef<Keymap<Self>>;
-
-    const MATRIX_ROW_SHIFTER: Self::MatrixRowType = if is_left() {
-        1
-    } else {
-        1 << (Self::MATRIX_COLUMNS - 1)
-    }
-    .into();
-
-    const THIS_HAND_OFFSET: usize = if is_right() { Self::ROWS_PER_HAND } else { 0 };
-    const OTHER_HAND_OFFSET: usize = Self::ROWS_PER_HAND - Self::THIS_HAND_OFFSET;
-
+    /// Copy the keymap in EEPROM, editable from a VIA configurator, see [dynamic_keymap] and [via]
+    const DYNAMIC_KEYMAP: bool = false;
+    /// Number of macros of the dynamic keymap
+    const DYNAMIC_KEYMAP_MACRO_COUNT: u8 = 16;
+    /// Size in bytes of the macros buffer of the dynamic keymap, in EEPROM after the keymap
+    const DYNAMIC_KEYMAP_MACRO_BUFFER_SIZE: u16 = 200;
+
+    /// How the speed of the mouse keys evolves while they are held, see [mouse]
+    const MOUSE_KEYS_MODE: MouseKeysMode = MouseKeysMode::Accelerated;
+    /// Milliseconds between two moves of the cursor
+    const MOUSE_INTERVAL: u8 = 16;
+    /// Cursor move of each interval, when the keys are pressed
     const MOUSE_BASE_SPEED: u8 = 1;
+    /// Cursor move of each interval, at full speed
     const MOUSE_MAX_SPEED: u8 = 12;
-    const MOUSE_ACCELERATION: f32 = 1.;
+    /// Milliseconds of hold to reach `MOUSE_MAX_SPEED`
+    const MOUSE_TIME_TO_MAX: u16 = 1000;
+    /// Cursor moves of [MouseKeysMode::Constant], picked by the accelerator keys 0, 1 and 2, the middle one without
+    const MOUSE_CONSTANT_SPEEDS: [u8; 3] = [2, 6, 12];
+    /// Part of the cursor speed lost each interval once the keys are released, out of 256, with [MouseKeysMode::Inertia]
+    const MOUSE_INERTIA_FRICTION: u8 = 24;
+    /// Milliseconds between two steps of the wheels
+    const WHEEL_INTERVAL: u8 = 80;
+    /// Wheel step of each interval, when the keys are pressed
+    const WHEEL_BASE_SPEED: u8 = 1;
+    /// Wheel step of each interval, at full speed
+    const WHEEL_MAX_SPEED: u8 = 4;
+    /// Milliseconds of hold to reach `WHEEL_MAX_SPEED`
+    const WHEEL_TIME_TO_MAX: u16 = 2000;
+    /// Wheel steps of [MouseKeysMode::Constant], picked by the accelerator keys 0, 1 and 2, the middle one without
+    const WHEEL_CONSTANT_SPEEDS: [u8; 3] = [1, 1, 3];
+    /// Add an absolute pointer to the mouse interface, moving the cursor to screen positions
+    /// with [usb::set_pointer_position] or [keys::PointerWarp]
+    const ABSOLUTE_POINTER: bool = false;
+
+    /// Pointing device read by the master half, see [pointing]
+    type PointingDevice: PointingDevice + const Default = NoPointingDevice;
+    /// Chip select pin of the pointing device, on the SPI bus
+    const POINTING_CS_PIN: Pin = NO_PIN;
+    /// Resolution of the pointing device, in counts per inch
+    const POINTING_CPI: u16 = 1600;
+    /// Resolution of the pointing device in [pointing::PointingMode::Sniping]
+    const POINTING_SNIPING_CPI: u16 = 400;
+    /// Counts of the pointing device making a wheel detent in [pointing::PointingMode::DragScroll]
+    const POINTING_DRAG_SCROLL_DIVISOR: u8 = 32;
+    /// Reverse the horizontal motion of the pointing device
+    const POINTING_INVERT_X: bool = false;
+    /// Reverse the vertical motion of the pointing device
+    const POINTING_INVERT_Y: bool = false;
+    /// Add a gamepad to the mouse interface, driven with [usb::set_gamepad_axis] and [keys::GamepadButton].
+    /// It is added anyway when the joystick starts in [JoystickMode::Gamepad]
+    const GAMEPAD: bool = false;
+
+    /// Horizontal and vertical axis pins of the analog joystick of the master half, `NO_PIN` without
+    /// joystick, see [analog]
+    const JOYSTICK_PINS: (Pin, Pin) = (NO_PIN, NO_PIN);
+    /// What the joystick drives when no mode key is held, see [JoystickMode]
+    const JOYSTICK_MODE: JoystickMode = JoystickMode::Mouse;
+    /// Cursor speed at full tilt of the joystick, in counts per second
+    const JOYSTICK_MOUSE_SPEED: u16 = 800;
+    /// Wheel speed at full tilt of the joystick, in detents per second
+    const JOYSTICK_SCROLL_SPEED: u16 = 10;
+    /// Distance to the centre, out of [adc::ADC_MAX], under which the joystick rests. A calibration may widen it
+    const JOYSTICK_DEADZONE: u16 = 40;
+    /// Reverse the horizontal axis of the joystick
+    const JOYSTICK_INVERT_X: bool = false;
+    /// Reverse the vertical axis of the joystick
+    const JOYSTICK_INVERT_Y: bool = false;
+    /// Pins of the slide potentiometers of the master half, at most [analog::MAX_SLIDERS], see [analog]
+    const SLIDER_PINS: &'static [Pin] = &[];
+    /// Positions of each slider along its travel
+    const SLIDER_STEPS: u8 = 32;
+    /// Conversions averaged by each reading of an analog input
+    const ANALOG_SAMPLES: u8 = 4;
 
     const FONTPLATE: Array2D<
         { Self::FONT_WIDTH },
@@ -128,7 +264,40 @@ pub trait Keyboard: Sized + const Default + 'static + PrivateConfig {
         ProgmemRef<BinPackedArray<{ Self::FONT_SIZE }>>,
     > = Array2D::<_, _, _, ProgmemRef<_>>::from_existing(unsafe { Self::USER_FONTPLATE.cast() });
 
-    fn rotary_encoder_handler(_keyboard: &mut OmkKeyboard<Self>, _rotation: (i8, i8)) {}
+    /// Called with the encoder steps since the last task, the encoders of the left half first.
+    fn rotary_encoder_handler(
+        _keyboard: &mut OmkKeyboard<Self>,
+        _rotation: [i8; Self::ENCODER_COUNT],
+    ) {
+    }
+
+    /// Called with the encoder pulses since the last task, the encoders of the left half first.
+    /// Passing them to [OmkKeyboard::scroll_wheel] scrolls in high resolution.
+    fn rotary_encoder_pulses_handler(
+        _keyboard: &mut OmkKeyboard<Self>,
+        _pulses: [i8; Self::ENCODER_COUNT],
+    ) {
+    }
+
+    /// Called on the master when a slider moves to another of its `SLIDER_STEPS` positions,
+    /// with the positions moved, positive toward VCC. By default the first slider changes the volume.
+    fn slider_handler(keyboard: &mut OmkKeyboard<Self>, slider: u8, steps: i8) {
+        if slider == 0 {
+            keyboard.change_volume(steps);
+        }
+    }
+
+    /// Called when the host suspends or resumes the USB bus, on both halves.
+    fn suspend_handler(_keyboard: &mut OmkKeyboard<Self>, _suspended: bool) {}
+
+    /// Called on the master with each Raw HID report received from the host.
+    /// Answers are sent with [usb::raw_hid_send].
+    /// With `DYNAMIC_KEYMAP`, only the reports which aren't VIA commands are passed.
+    fn raw_hid_receive(
+        _keyboard: &mut OmkKeyboard<Self>,
+        _data: &mut [u8; usb::RAW_HID_REPORT_SIZE as usize],
+    ) {
+    }
 
     /// A Holder for all suplementary data that you want accessible from the interrupts handlers.
     /// You need to implement Default on it for initialisation.
@@ -136,8 +305,11 @@ pub trait Keyboard: Sized + const Default + 'static + PrivateConfig {
 }
 
 pub trait PrivateConfig {
+    /// `MATRIX_ROWS / 2` on split keyboards, `MATRIX_ROWS` on unibody ones
     type const ROWS_PER_HAND: usize;
     type const MATRIX_KEYS_COUNT: usize;
+    /// `ENCODERS_PER_HAND * 2` on split keyboards, `ENCODERS_PER_HAND` on unibody ones
+    type const ENCODER_COUNT: usize;
     type const FONT_SIZE: usize;
     type const FONT_WIDTH: u8;
     type const FONT_HEIGHT: u8;
@@ -154,39 +326,21 @@ pub struct OmkKeyboard<User: Keyboard + PrivateConfig> {
     pub raw_matrix: [User::MatrixRowType; User::ROWS_PER_HAND],
     pub previous_matrix: [User::MatrixRowType; User::MATRIX_ROWS],
     pub current_matrix: [User::MatrixRowType; User::MATRIX_ROWS],
+    pub debouncer: User::Debouncer,
 
     pub layer: u8,
     pub keys_actual_layer: [i8; User::MATRIX_KEYS_COUNT],
     pub mouse_state: OmkMouse<User>,
+    pub pointing_state: OmkPointing<User>,
+    pub analog_state: OmkAnalog<User>,
+    pub encoder_map_state: EncoderMapState<User>,
+    /// Whether the host has suspended the USB bus
+    pub suspended: bool,
 
     next_press_handler_override: Option<(PressHandler<User>, u8)>,
     release_handler_overrides: LimitedStorage<10, (UnPressHandler<User>, u8)>,
 }
 
-pub struct OmkMouse<User> {
-    pub up: bool,
-    pub down: bool,
-    pub left: bool,
-    pub right: bool,
-    buttons_held_duration: u8,
-    current_speed: u8,
-    _phantom: PhantomData<User>,
-}
-
-impl<User: Keyboard> const Default for OmkMouse<User> {
-    fn default() -> Self {
-        Self {
-            up: false,
-            down: false,
-            left: false,
-            right: false,
-            buttons_held_duration: 0,
-            current_speed: User::MOUSE_BASE_SPEED,
-            _phantom: PhantomData,
-        }
-    }
-}
-
 pub struct OmkShared<User: Keyboard> {
     pub master_memory: MasterSharedMemory<User>,
     pub slave_memory: SlaveSharedMemory<User>,
@@ -210,9 +364,14 @@ impl<User: Keyboard> OmkMetaHolder<User> {
                 raw_matrix: [0.into(); _],
                 previous_matrix: [0.into(); _],
                 current_matrix: [0.into(); _],
+                debouncer: User::Debouncer::default(),
                 layer: 0,
                 keys_actual_layer: [0; _],
                 mouse_state: OmkMouse::default(),
+                pointing_state: OmkPointing::default(),
+                analog_state: OmkAnalog::default(),
+                encoder_map_state: EncoderMapState::default(),
+                suspended: false,
                 next_press_handler_override: None,
                 release_handler_overrides: LimitedStorage::new(),
             }),
@@ -252,19 +411,25 @@ impl<User: Keyboard> OmkKeyboard<User> {
         User::RED_LED_PIN.gpio_write_pin_high();
         disable_watchdog();
         timer_init();
-        let _ = Self::init_graphics();
-        self.serial_init();
+        // The USB descriptors depend on these, they must be set before USB starts
+        console_init(User::CONSOLE);
+        absolute_pointer_init(User::ABSOLUTE_POINTER);
+        gamepad_init(Self::gamepad_present());
+        side_init::<User>();
+        // Over an I2C split link, the slave is a target on the bus and cannot drive its screen
+        if is_master() || User::SPLIT_TRANSPORT != SplitTransport::I2C {
+            let _ = Self::init_graphics();
+        }
+        if User::SPLIT {
+            self.serial_init();
+        }
         RotaryEncoder::<User>::init();
         self.matrix_init();
-
-        if is_master() {
-            unsafe {
-                USB_Init();
-            }
-        } else {
-            // Needed for the code to works directly after flash, but seems to crash LUFA, which already resolve the problem on flash
-            USBCON.write(USBCON & !USBE);
+        if User::DYNAMIC_KEYMAP {
+            Self::dynamic_keymap_init();
         }
+        self.pointing_init();
+        self.analog_init();
 
         // Enable interrupts
         unsafe { asm!("sei") };
@@ -273,14 +438,31 @@ impl<User: Keyboard> OmkKeyboard<User> {
     where
         User: InterruptsHandler<User>,
     {
-        let rotary = RotaryEncoder::<User>::task(self);
+        Self::idle_sleep();
+        self.suspend_task();
+        let task_start = if User::PROFILING { timer_read_us() } else { 0 };
+        let (rotary, pulses) = RotaryEncoder::<User>::task(self);
         User::rotary_encoder_handler(self, rotary);
-        let mut changed = rotary.0 != 0 || rotary.1 != 0;
+        User::rotary_encoder_pulses_handler(self, pulses);
+        let mut changed = rotary.iter().any(|steps| *steps != 0);
+        self.encoder_map_task(&rotary);
         changed |= self.matrix_task();
         self.mouse_task();
-        let _ = Self::render(changed);
+        self.pointing_task();
+        self.analog_task();
+        // The screen stays off while suspended
+        if !self.suspended {
+            let render_start = if User::PROFILING { timer_read_us() } else { 0 };
+            let _ = Self::render(changed);
+            if User::PROFILING {
+                profiling::render_done(render_start);
+            }
+        }
 
         self.usb_task();
+        if User::PROFILING {
+            profiling::task_done(task_start);
+        }
     }
 
     pub fn usb_task(&mut self)
@@ -289,47 +471,78 @@ impl<User: Keyboard> OmkKeyboard<User> {
     {
         if is_master() {
             hid_task();
+            if let Some(mut data) = raw_hid_read() {
+                if User::DYNAMIC_KEYMAP && self.via_command(&mut data) {
+                    raw_hid_send(&data);
+                } else {
+                    User::raw_hid_receive(self, &mut data);
+                }
+            }
+            console_task();
             unsafe {
                 USB_USBTask();
             }
         }
     }
-    pub fn mouse_task(&mut self) {
-        if self.mouse_state.up
-            || self.mouse_state.down
-            || self.mouse_state.left
-            || self.mouse_state.right
-        {
-            let (mut x, mut y) = get_mouse_delta();
-            self.mouse_state.buttons_held_duration += 1;
-
-            self.mouse_state.current_speed = User::MOUSE_BASE_SPEED
-                + libm::roundf(
-                    self.mouse_state.buttons_held_duration as f32 * User::MOUSE_ACCELERATION / 20.,
-                ) as u8;
-
-            if self.mouse_state.current_speed > User::MOUSE_MAX_SPEED {
-                self.mouse_state.current_speed = User::MOUSE_MAX_SPEED;
-            }
-
-            if self.mouse_state.up {
-                y -= self.mouse_state.current_speed as i8;
-            }
-            if self.mouse_state.down {
-                y += self.mouse_state.current_speed as i8;
-            }
-            if self.mouse_state.left {
-                x -= self.mouse_state.current_speed as i8;
-            }
-            if self.mouse_state.right {
-                x += self.mouse_state.current_speed as i8;
-            }
-            set_mouse_delta(x, y);
+    /// Offset of the rows of this half in the whole matrix.
+    #[inline(always)]
+    pub fn this_hand_offset() -> usize {
+        if is_right() { User::ROWS_PER_HAND } else { 0 }
+    }
+    /// Offset of the rows of the other half in the whole matrix.
+    #[inline(always)]
+    pub fn other_hand_offset() -> usize {
+        User::ROWS_PER_HAND - Self::this_hand_offset()
+    }
+    /// Returns true if the mouse interface has the gamepad, with [Keyboard::GAMEPAD] or a joystick
+    /// in [JoystickMode::Gamepad].
+    pub const fn gamepad_present() -> bool {
+        User::GAMEPAD
+            || (User::JOYSTICK_PINS.0.0 != NO_PIN.0
+                && matches!(User::JOYSTICK_MODE, JoystickMode::Gamepad))
+    }
+    /// Bit of the first column in a matrix row, columns being mirrored on the right half.
+    #[inline(always)]
+    pub fn matrix_row_shifter() -> User::MatrixRowType {
+        // Shifted as a MatrixRowType, a u8 shift overflowing past 8 columns
+        let mut bit: User::MatrixRowType = 1.into();
+        if !is_left() {
+            bit <<= (User::MATRIX_COLUMNS - 1) as u8;
+        }
+        bit
+    }
+    /// Pins of the rotary encoders of this half.
+    #[inline(always)]
+    pub fn rotary_encoder_pins() -> [(Pin, Pin); User::ENCODERS_PER_HAND] {
+        if is_left() {
+            User::LEFT_ENCODERS
+        } else {
+            User::RIGHT_ENCODERS
+        }
+    }
+    /// Switch pins of the rotary encoders of this half.
+    #[inline(always)]
+    pub fn encoder_switch_pins() -> [Pin; User::ENCODERS_PER_HAND] {
+        if is_left() {
+            User::LEFT_ENCODER_SWITCHES
+        } else {
+            User::RIGHT_ENCODER_SWITCHES
+        }
+    }
+    /// Index of the first encoder of this half, the encoders of the left half being first.
+    #[inline(always)]
+    pub fn this_hand_encoder_offset() -> usize {
+        if is_right() {
+            User::ENCODERS_PER_HAND
         } else {
-            self.mouse_state.buttons_held_duration = 0;
-            self.mouse_state.current_speed = User::MOUSE_BASE_SPEED;
+            0
         }
     }
+    /// Index of the first encoder of the other half.
+    #[inline(always)]
+    pub fn other_hand_encoder_offset() -> usize {
+        User::ENCODERS_PER_HAND - Self::this_hand_encoder_offset()
+    }
 
     pub fn get_layer_up(&mut self, count: u8) -> u8 {
         self.layer - count
@@ -344,20 +557,37 @@ impl<User: Keyboard> OmkKeyboard<User> {
     pub fn layer_down(&mut self, count: u8) {
         self.layer -= count;
     }
+    /// Index in a keymap layer of a key of the matrix.
+    pub fn keymap_index(column: u8, row: u8) -> usize {
+        if !User::SPLIT {
+            return (column + row * User::MATRIX_COLUMNS as u8) as usize;
+        }
+        // Keymap rows span both halves, the left half first
+        (column
+            + (row % User::ROWS_PER_HAND as u8) * User::MATRIX_COLUMNS as u8 * 2
+            + if row >= User::ROWS_PER_HAND as u8 {
+                User::MATRIX_COLUMNS as u8
+            } else {
+                0
+            }) as usize
+    }
     pub fn get_key(&self, layer: u8, column: u8, row: u8) -> &'static dyn CustomKey<User> {
-        User::KEYMAP
-            .at(layer as usize)
-            .at((column
-                + (row % User::ROWS_PER_HAND as u8) * User::MATRIX_COLUMNS as u8 * 2
-                + if row >= User::ROWS_PER_HAND as u8 {
-                    User::MATRIX_COLUMNS as u8
-                } else {
-                    0
-                }) as usize)
-            .read()
+        let index = Self::keymap_index(column, row);
+        if User::DYNAMIC_KEYMAP
+            && let Some(key) = Self::get_dynamic_key(layer, index)
+        {
+            return key;
+        }
+        User::KEYMAP.at(layer as usize).at(index).read()
     }
 
     pub fn key_pressed(&mut self, column: u8, row: u8) {
+        if User::PROFILING {
+            profiling::key_edge();
+        }
+        if self.suspended && is_master() {
+            send_remote_wakeup();
+        }
         self.keys_actual_layer[(row * User::MATRIX_COLUMNS as u8 + column) as usize] =
             self.layer as i8;
 
@@ -376,6 +606,9 @@ impl<User: Keyboard> OmkKeyboard<User> {
     }
 
     pub fn key_released(&mut self, column: u8, row: u8) {
+        if User::PROFILING {
+            profiling::key_edge();
+        }
         let key_actual_layer =
             self.keys_actual_layer[(row * User::MATRIX_COLUMNS as u8 + column) as usize];
         if key_actual_layer >= 0 {
@@ -434,26 +667,3 @@ impl<User: Keyboard> OmkKeyboard<User> {
         self.send_key_release(key);
     }
 }
-
-// Side
-
-/// Return true if `IS_KEYBOARD_RIGHT` env variable is set, false otherwise
-#[inline(always)]
-pub const fn side() -> bool {
-    match option_env!("IS_KEYBOARD_RIGHT") {
-        Some(x) => !x.is_empty(),
-        None => false,
-    }
-}
-#[inline(always)]
-pub const fn is_left() -> bool {
-    !side()
-}
-#[inline(always)]
-pub const fn is_right() -> bool {
-    side()
-}
-#[inline(always)]
-pub const fn is_master() -> bool {
-    is_left()
-}
@@ -2,11 +2,8 @@
 //! It includes methods for initializing, scanning, and processing the matrix state.
 
 use crate::{
-    Keyboard, OmkKeyboard,
-    atomic::atomic,
-    interrupts::InterruptsHandler,
-    is_left,
-    timer::{timer_elapsed, timer_read},
+    Keyboard, OmkKeyboard, atomic::atomic, console::Console, debounce::Debouncer,
+    interrupts::InterruptsHandler, is_left, profiling,
 };
 
 use avr_base::pins::{GPIO_INPUT_PIN_DELAY, NO_PIN, Pin};
@@ -14,6 +11,22 @@ use avr_delay::{delay_cycles, delay_us};
 
 pub type const MATRIX_IO_DELAY: u64 = 30;
 
+/// How the switches are wired to the microcontroller, see [Keyboard::MATRIX_TOPOLOGY].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MatrixTopology {
+    /// Diodes pointing from the columns to the rows: rows are driven low, columns are read.
+    Col2Row,
+    /// Diodes pointing from the rows to the columns: columns are driven low, rows are read.
+    Row2Col,
+    /// One pin per key, tied to the ground when pressed, listed in [Keyboard::DIRECT_PINS].
+    /// `ROW_PINS` and `COL_PINS` are unused.
+    DirectPins,
+    /// Japanese duplex matrix, two switches with opposite diodes on each row and column intersection.
+    /// The COL2ROW switch of a pin is on an even column, and the ROW2COL one on the next odd column,
+    /// thus each column pin is listed twice in `COL_PINS`.
+    Duplex,
+}
+
 impl<User: Keyboard> OmkKeyboard<User> {
     /// Sets a GPIO pin as output and drives it low atomically.
     ///
@@ -67,11 +80,27 @@ impl<User: Keyboard> OmkKeyboard<User> {
         }
     }
 
-    /// Reads the columns of a specific row in the keyboard matrix and updates the matrix state.
-    fn matrix_read_cols_on_row(&self, current_matrix: &mut [User::MatrixRowType], current_row: u8) {
-        // Start with a clear matrix row
-        let mut current_row_value = 0.into();
+    /// Returns the bit of a column in a matrix row, columns being mirrored on the right half.
+    fn column_bit(column: u8) -> User::MatrixRowType {
+        let mut bit = Self::matrix_row_shifter();
+        if is_left() {
+            bit <<= column;
+        } else {
+            bit >>= column;
+        }
+        bit
+    }
 
+    /// Reads the columns of a specific row in the keyboard matrix and updates the matrix state.
+    ///
+    /// Only the columns `first_column`, `first_column + step`, ... are read.
+    fn matrix_read_cols_on_row(
+        &self,
+        current_matrix: &mut [User::MatrixRowType],
+        current_row: u8,
+        first_column: u8,
+        step: u8,
+    ) {
         if !Self::select_row(current_row) {
             // Select row
             return; // skip NO_PIN row
@@ -80,29 +109,112 @@ impl<User: Keyboard> OmkKeyboard<User> {
         delay_cycles::<{ GPIO_INPUT_PIN_DELAY }>();
 
         // For each col...
-        let mut row_shifter = User::MATRIX_ROW_SHIFTER;
-        for col_index in 0..User::MATRIX_COLUMNS {
-            let pin_state = Self::read_matrix_pin(User::COL_PINS[col_index]);
+        for col_index in (first_column..User::MATRIX_COLUMNS as u8).step_by(step as usize) {
+            let pin_state = Self::read_matrix_pin(User::COL_PINS[col_index as usize]);
 
             // Populate the matrix row with the state of the col pin
-            current_row_value |= if pin_state { 0.into() } else { row_shifter };
-            if is_left() {
-                row_shifter <<= 1;
-            } else {
-                row_shifter >>= 1;
+            if !pin_state {
+                current_matrix[current_row as usize] |= Self::column_bit(col_index);
             }
         }
 
         // Unselect row
         Self::unselect_row(current_row);
         delay_us::<{ MATRIX_IO_DELAY }>();
+    }
 
-        // Update the matrix
-        current_matrix[current_row as usize] = current_row_value;
+    /// Reads the rows of a specific column in the keyboard matrix and updates the matrix state.
+    ///
+    /// This is the [MatrixTopology::Row2Col] counterpart of `matrix_read_cols_on_row`.
+    fn matrix_read_rows_on_col(&self, current_matrix: &mut [User::MatrixRowType], current_col: u8) {
+        let pin = User::COL_PINS[current_col as usize];
+        if pin == NO_PIN {
+            return;
+        }
+        // Select col
+        Self::gpio_atomic_set_pin_output_low(pin);
+
+        delay_cycles::<{ GPIO_INPUT_PIN_DELAY }>();
+
+        // For each row...
+        let column_bit = Self::column_bit(current_col);
+        for row_index in 0..User::ROWS_PER_HAND {
+            let pin_state = Self::read_matrix_pin(User::ROW_PINS[row_index]);
+
+            // Populate the matrix row with the state of the row pin
+            if !pin_state {
+                current_matrix[row_index] |= column_bit;
+            }
+        }
+
+        // Unselect col
+        Self::gpio_atomic_set_pin_input_high(pin);
+        delay_us::<{ MATRIX_IO_DELAY }>();
+    }
+
+    /// Reads every direct pin and updates the matrix state.
+    fn matrix_read_direct_pins(&self, current_matrix: &mut [User::MatrixRowType]) {
+        for row_index in 0..User::ROWS_PER_HAND {
+            for col_index in 0..User::MATRIX_COLUMNS {
+                // NO_PIN reads as released
+                if !Self::read_matrix_pin(User::DIRECT_PINS[row_index][col_index]) {
+                    current_matrix[row_index] |= Self::column_bit(col_index as u8);
+                }
+            }
+        }
     }
 
     /// Initializes the keyboard matrix by setting all rows and columns to their default states.
     pub fn matrix_init(&self) {
+        const {
+            assert!(
+                User::ROWS_PER_HAND
+                    == if User::SPLIT {
+                        User::MATRIX_ROWS / 2
+                    } else {
+                        User::MATRIX_ROWS
+                    },
+                "ROWS_PER_HAND must be MATRIX_ROWS / 2 on split keyboards, MATRIX_ROWS on unibody ones"
+            )
+        };
+        const {
+            assert!(
+                size_of::<User::MatrixRowType>() * 8 >= User::MATRIX_COLUMNS,
+                "MatrixRowType must hold a bit per column, e.g. u16 for 14 columns"
+            )
+        };
+        const {
+            let mut switch = 0;
+            while switch < User::ENCODERS_PER_HAND {
+                match User::ENCODER_SWITCH_POSITIONS {
+                    Some(positions) => {
+                        let (row, column) = positions[switch];
+                        assert!(
+                            (row as usize) < User::ROWS_PER_HAND
+                                && (column as usize) < User::MATRIX_COLUMNS,
+                            "ENCODER_SWITCH_POSITIONS must be in the matrix of a half"
+                        )
+                    }
+                    None => assert!(
+                        User::LEFT_ENCODER_SWITCHES[switch].0 == NO_PIN.0
+                            && User::RIGHT_ENCODER_SWITCHES[switch].0 == NO_PIN.0,
+                        "ENCODER_SWITCH_POSITIONS must be set when an encoder has a switch"
+                    ),
+                }
+                switch += 1;
+            }
+        };
+        if User::MATRIX_TOPOLOGY == MatrixTopology::DirectPins {
+            for row in User::DIRECT_PINS {
+                for pin in row {
+                    if pin != NO_PIN {
+                        Self::gpio_atomic_set_pin_input_high(pin);
+                    }
+                }
+            }
+            return;
+        }
+        // Unselected rows and columns are all pulled-up inputs, whatever the diode direction
         for row in 0..User::ROWS_PER_HAND {
             Self::unselect_row(row as u8);
         }
@@ -115,9 +227,49 @@ impl<User: Keyboard> OmkKeyboard<User> {
     ///
     /// Returns `true` if the matrix state has changed, or `false` otherwise.
     pub fn matrix_scan(&mut self) -> bool {
+        if User::IDLE_TIMEOUT != 0 && Self::is_idle() && !self.idle_wake_up() {
+            return false;
+        }
+
         let mut new_matrix = [0.into(); User::ROWS_PER_HAND];
-        for row in 0..User::ROWS_PER_HAND {
-            self.matrix_read_cols_on_row(&mut new_matrix, row as u8);
+        match User::MATRIX_TOPOLOGY {
+            MatrixTopology::Col2Row => {
+                for row in 0..User::ROWS_PER_HAND {
+                    self.matrix_read_cols_on_row(&mut new_matrix, row as u8, 0, 1);
+                }
+            }
+            MatrixTopology::Row2Col => {
+                for col in 0..User::MATRIX_COLUMNS {
+                    self.matrix_read_rows_on_col(&mut new_matrix, col as u8);
+                }
+            }
+            MatrixTopology::DirectPins => self.matrix_read_direct_pins(&mut new_matrix),
+            MatrixTopology::Duplex => {
+                // Even columns are wired COL2ROW, odd ones ROW2COL
+                for row in 0..User::ROWS_PER_HAND {
+                    self.matrix_read_cols_on_row(&mut new_matrix, row as u8, 0, 2);
+                }
+                for col in (1..User::MATRIX_COLUMNS).step_by(2) {
+                    self.matrix_read_rows_on_col(&mut new_matrix, col as u8);
+                }
+            }
+        }
+
+        // Encoder switches are keys outside the matrix, at a free position of it
+        if let Some(positions) = User::ENCODER_SWITCH_POSITIONS {
+            for (switch, pin) in Self::encoder_switch_pins().iter().enumerate() {
+                if !Self::read_matrix_pin(*pin) {
+                    let (row, column) = positions[switch];
+                    new_matrix[row as usize] |= Self::column_bit(column);
+                }
+            }
+        }
+
+        if User::PROFILING && profiling::scan_done() && User::CONSOLE != Console::Disabled {
+            profiling::print_profiling_stats();
+        }
+        if User::IDLE_TIMEOUT != 0 {
+            self.idle_update(new_matrix.iter().any(|row| *row != 0.into()));
         }
 
         let changed = if self.raw_matrix == new_matrix {
@@ -138,32 +290,38 @@ impl<User: Keyboard> OmkKeyboard<User> {
         User: InterruptsHandler<User>,
     {
         let our_matrix_changed = self.matrix_scan();
-        self.serial_task();
+        if User::SPLIT {
+            self.serial_task();
+        }
         self.key_task(our_matrix_changed)
     }
 
     /// Processes key events based on the current and previous matrix states.
     pub fn key_task(&mut self, our_matrix_changed: bool) -> bool {
+        let other = Self::other_hand_offset();
+        // On unibody keyboards, the whole matrix is ours
         let changed = our_matrix_changed
-            || unsafe {
-                self.previous_matrix
-                    [User::OTHER_HAND_OFFSET..(User::OTHER_HAND_OFFSET + User::ROWS_PER_HAND)]
-                    .as_mut_array::<{ User::ROWS_PER_HAND }>()
-                    .unwrap_unchecked()
-                    != self.current_matrix
-                        [User::OTHER_HAND_OFFSET..(User::OTHER_HAND_OFFSET + User::ROWS_PER_HAND)]
-                        .as_mut_array()
+            || User::SPLIT
+                && unsafe {
+                    self.previous_matrix[other..(other + User::ROWS_PER_HAND)]
+                        .as_mut_array::<{ User::ROWS_PER_HAND }>()
                         .unwrap_unchecked()
-            };
+                        != self.current_matrix[other..(other + User::ROWS_PER_HAND)]
+                            .as_mut_array()
+                            .unwrap_unchecked()
+                };
         if changed {
             for row in 0..User::MATRIX_ROWS as u8 {
                 if self.previous_matrix[row as usize] != self.current_matrix[row as usize] {
+                    if User::ANTI_GHOSTING && self.has_ghost_in_row(row as usize) {
+                        // Keep the previous state of the row until the ambiguity is resolved
+                        continue;
+                    }
                     for column in 0..User::MATRIX_COLUMNS as u8 {
-                        let current_press =
-                            self.current_matrix[row as usize] & (1 << column).into();
-                        if self.previous_matrix[row as usize] & (1 << column).into()
-                            != current_press
-                        {
+                        let mut column_mask: User::MatrixRowType = 1.into();
+                        column_mask <<= column;
+                        let current_press = self.current_matrix[row as usize] & column_mask;
+                        if self.previous_matrix[row as usize] & column_mask != current_press {
                             if current_press != 0.into() {
                                 self.key_pressed(column, row)
                             } else {
@@ -171,42 +329,58 @@ impl<User: Keyboard> OmkKeyboard<User> {
                             }
                         }
                     }
+                    self.previous_matrix[row as usize] = self.current_matrix[row as usize];
                 }
             }
-            self.previous_matrix = self.current_matrix;
         }
         changed
     }
-}
 
-static mut DEBOUNCING: bool = false;
-static mut DEBOUNCING_TIME: u32 = 0;
+    /// Returns the number of keys pressed in a matrix row.
+    fn pressed_count(row_value: User::MatrixRowType) -> u8 {
+        let mut count = 0;
+        let mut mask: User::MatrixRowType = 1.into();
+        for _ in 0..User::MATRIX_COLUMNS {
+            if row_value & mask != 0.into() {
+                count += 1;
+            }
+            mask <<= 1;
+        }
+        count
+    }
 
-pub const DEBOUNCE: u32 = 5;
+    /// Returns `true` if the keys pressed on a row may include a ghost.
+    ///
+    /// Without diodes, pressing three corners of a rectangle also shows the fourth one as pressed,
+    /// so a row sharing two pressed columns with another row of the same half is ambiguous.
+    fn has_ghost_in_row(&self, row: usize) -> bool {
+        let row_value = self.current_matrix[row];
+        if Self::pressed_count(row_value) < 2 {
+            return false;
+        }
+        // Rows of different halves don't share any line
+        let half = row - row % User::ROWS_PER_HAND;
+        (half..half + User::ROWS_PER_HAND).any(|other_row| {
+            other_row != row && Self::pressed_count(self.current_matrix[other_row] & row_value) >= 2
+        })
+    }
+}
 
 impl<User: Keyboard> OmkKeyboard<User> {
     /// Debounces the matrix state to filter out noise and ensure stable key detection.
     fn debounce(&mut self, changed: bool) -> bool {
-        let self_matrix = unsafe {
-            self.current_matrix
-                [User::THIS_HAND_OFFSET..User::THIS_HAND_OFFSET + User::ROWS_PER_HAND]
-                .as_mut_array()
-                .unwrap_unchecked()
-        };
-
-        let mut cooked_changed = false;
+        let this = Self::this_hand_offset();
+        let self_matrix = &mut self.current_matrix[this..this + User::ROWS_PER_HAND];
 
-        if changed {
-            unsafe { DEBOUNCING = true };
-            unsafe { DEBOUNCING_TIME = timer_read() };
-        } else if unsafe { DEBOUNCING } && unsafe { timer_elapsed(DEBOUNCING_TIME) } >= DEBOUNCE {
-            if *self_matrix != self.raw_matrix {
-                *self_matrix = self.raw_matrix;
-                cooked_changed = true;
+        if User::DEBOUNCE == 0 {
+            if *self_matrix == self.raw_matrix {
+                return false;
             }
-            unsafe { DEBOUNCING = false };
+            self_matrix.copy_from_slice(&self.raw_matrix);
+            return true;
         }
 
-        cooked_changed
+        self.debouncer
+            .debounce(&self.raw_matrix, self_matrix, changed)
     }
 }
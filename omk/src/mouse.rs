@@ -0,0 +1,278 @@
+//! This module implements the mouse keys, moving the cursor and the wheels from the keyboard.
+//! While its keys are held, the cursor moves every [Keyboard::MOUSE_INTERVAL] ms and the wheels
+//! every [Keyboard::WHEEL_INTERVAL] ms, the first step being immediate. Their speed follows
+//! [Keyboard::MOUSE_KEYS_MODE]:
+//! - [MouseKeysMode::Accelerated]: the speed grows linearly from the base speed to the max speed.
+//! - [MouseKeysMode::Kinetic]: the speed grows quadratically, staying slow for precise moves.
+//! - [MouseKeysMode::Constant]: fixed speeds, the accelerator keys picking one.
+//! - [MouseKeysMode::Inertia]: the keys push the cursor, which glides and slows down once they are released.
+//!
+//! Out of [MouseKeysMode::Constant], holding an accelerator key sets the speed to the base one,
+//! the middle one or the max one.
+//!
+//! Wheel speeds are in detents. Encoders scroll with [OmkKeyboard::scroll_wheel], which sends
+//! every pulse to the hosts supporting high resolution scrolling.
+
+use core::marker::PhantomData;
+
+use crate::{
+    Keyboard, OmkKeyboard,
+    timer::{timer_elapsed, timer_read},
+    usb::{get_mouse_delta, get_wheel_delta, set_mouse_delta, set_wheel_delta, wheel_resolution},
+};
+
+/// How the speed of the mouse keys evolves while they are held.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MouseKeysMode {
+    /// Linear ramp from the base speed to the max speed
+    Accelerated,
+    /// Quadratic ramp from the base speed to the max speed
+    Kinetic,
+    /// Speed picked by the accelerator keys, the middle one without
+    Constant,
+    /// The cursor accelerates while the keys are held, and slows down with friction once released
+    Inertia,
+}
+
+/// Fixed point shift of the inertia velocity, in 1/16 of count per interval.
+const INERTIA_SHIFT: u8 = 4;
+/// Speed factor of diagonal moves, 1/sqrt(2) in 1/256 units.
+const DIAGONAL_FACTOR: i16 = 181;
+
+/// Timing of a group of held mouse keys.
+#[derive(Clone, Copy)]
+struct Ramp {
+    held: bool,
+    /// Time the keys were pressed
+    start: u32,
+    /// Time of the last step
+    last_step: u32,
+}
+
+impl Ramp {
+    const RELEASED: Self = Self {
+        held: false,
+        start: 0,
+        last_step: 0,
+    };
+
+    /// Returns the milliseconds since the keys were pressed if a step is due, `None` otherwise.
+    fn step(&mut self, active: bool, interval: u8) -> Option<u32> {
+        if !active {
+            self.held = false;
+            return None;
+        }
+        let now = timer_read();
+        if !self.held {
+            *self = Self {
+                held: true,
+                start: now,
+                last_step: now,
+            };
+            return Some(0);
+        }
+        if timer_elapsed(self.last_step) < interval as u32 {
+            return None;
+        }
+        self.last_step = now;
+        Some(timer_elapsed(self.start))
+    }
+}
+
+pub struct OmkMouse<User> {
+    pub up: bool,
+    pub down: bool,
+    pub left: bool,
+    pub right: bool,
+    pub wheel_up: bool,
+    pub wheel_down: bool,
+    pub wheel_left: bool,
+    pub wheel_right: bool,
+    /// Held accelerator keys, a bit per key
+    pub accelerators: u8,
+    movement: Ramp,
+    wheel: Ramp,
+    /// Cursor velocity of [MouseKeysMode::Inertia], in 1/16 of count per interval
+    velocity: (i16, i16),
+    /// Encoder pulses not scrolled yet, in 1/[wheel_resolution] of pulse
+    wheel_pulses: i16,
+    _phantom: PhantomData<User>,
+}
+
+impl<User: Keyboard> const Default for OmkMouse<User> {
+    fn default() -> Self {
+        Self {
+            up: false,
+            down: false,
+            left: false,
+            right: false,
+            wheel_up: false,
+            wheel_down: false,
+            wheel_left: false,
+            wheel_right: false,
+            accelerators: 0,
+            movement: Ramp::RELEASED,
+            wheel: Ramp::RELEASED,
+            velocity: (0, 0),
+            wheel_pulses: 0,
+            _phantom: PhantomData,
+        }
+    }
+}
+
+/// Returns -1, 0 or 1 for a pair of opposite keys.
+fn direction(negative: bool, positive: bool) -> i16 {
+    positive as i16 - negative as i16
+}
+
+/// Adds `delta` to a pending report delta, saturating to the report range.
+pub(crate) fn add_delta(current: i8, delta: i16) -> i8 {
+    (current as i16 + delta).clamp(-127, 127) as i8
+}
+
+impl<User: Keyboard> OmkMouse<User> {
+    /// Speed after the keys are held `held` ms, from `base` to `max`, reached after `time_to_max` ms.
+    fn speed(
+        &self,
+        held: u32,
+        base: u8,
+        max: u8,
+        time_to_max: u16,
+        constant_speeds: [u8; 3],
+    ) -> u8 {
+        // The fastest held accelerator wins
+        let accelerator = (0..3).rev().find(|i| self.accelerators & (1 << i) != 0);
+        match (User::MOUSE_KEYS_MODE, accelerator) {
+            (MouseKeysMode::Constant, accelerator) => constant_speeds[accelerator.unwrap_or(1)],
+            (_, Some(0)) => base,
+            (_, Some(1)) => base + max.saturating_sub(base) / 2,
+            (_, Some(_)) => max,
+            (mode, None) => {
+                let time_to_max = time_to_max.max(1) as u32;
+                let held = held.min(time_to_max);
+                let range = max.saturating_sub(base) as u32;
+                let ramp = if mode == MouseKeysMode::Kinetic {
+                    range * held / time_to_max * held / time_to_max
+                } else {
+                    range * held / time_to_max
+                };
+                base + ramp as u8
+            }
+        }
+    }
+
+    /// Accelerates the cursor toward the held keys, or slows it down if none is held.
+    fn inertia_step(&mut self, x: i16, y: i16) {
+        const {
+            assert!(
+                User::MOUSE_INERTIA_FRICTION > 0,
+                "MOUSE_INERTIA_FRICTION must be above 0, for the cursor to stop"
+            )
+        };
+        let active = x != 0 || y != 0 || self.velocity != (0, 0);
+        if self.movement.step(active, User::MOUSE_INTERVAL).is_none() {
+            return;
+        }
+        let base = (User::MOUSE_BASE_SPEED as i16) << INERTIA_SHIFT;
+        let max = (User::MOUSE_MAX_SPEED as i16) << INERTIA_SHIFT;
+        // Velocity gained each interval, to reach the max speed after MOUSE_TIME_TO_MAX ms
+        let acceleration = (max as i32 * User::MOUSE_INTERVAL as i32
+            / User::MOUSE_TIME_TO_MAX.max(1) as i32)
+            .max(1) as i16;
+        let axis = |velocity: i16, direction: i16| match direction {
+            // Start at the base speed, even when reversing
+            1 => (velocity + acceleration).clamp(base, max),
+            -1 => (velocity - acceleration).clamp(-max, -base),
+            _ => {
+                let slowed =
+                    (velocity as i32 * (256 - User::MOUSE_INERTIA_FRICTION as i32) / 256) as i16;
+                if slowed.abs() < 1 << INERTIA_SHIFT {
+                    0
+                } else {
+                    slowed
+                }
+            }
+        };
+        self.velocity = (axis(self.velocity.0, x), axis(self.velocity.1, y));
+
+        let (current_x, current_y) = get_mouse_delta();
+        set_mouse_delta(
+            add_delta(current_x, self.velocity.0 / (1 << INERTIA_SHIFT)),
+            add_delta(current_y, self.velocity.1 / (1 << INERTIA_SHIFT)),
+        );
+    }
+}
+
+impl<User: Keyboard> OmkKeyboard<User> {
+    /// Moves the cursor and the wheels according to the held mouse keys.
+    pub fn mouse_task(&mut self) {
+        const {
+            assert!(
+                User::MOUSE_BASE_SPEED <= User::MOUSE_MAX_SPEED,
+                "MOUSE_BASE_SPEED must not be above MOUSE_MAX_SPEED"
+            );
+            assert!(
+                User::WHEEL_BASE_SPEED <= User::WHEEL_MAX_SPEED,
+                "WHEEL_BASE_SPEED must not be above WHEEL_MAX_SPEED"
+            );
+        };
+        let mouse = &mut self.mouse_state;
+
+        let x = direction(mouse.left, mouse.right);
+        let y = direction(mouse.up, mouse.down);
+        if User::MOUSE_KEYS_MODE == MouseKeysMode::Inertia {
+            mouse.inertia_step(x, y);
+        } else if let Some(held) = mouse.movement.step(x != 0 || y != 0, User::MOUSE_INTERVAL) {
+            let mut speed = mouse.speed(
+                held,
+                User::MOUSE_BASE_SPEED,
+                User::MOUSE_MAX_SPEED,
+                User::MOUSE_TIME_TO_MAX,
+                User::MOUSE_CONSTANT_SPEEDS,
+            ) as i16;
+            // Diagonal moves keep the speed of straight ones
+            if x != 0 && y != 0 {
+                speed = (speed * DIAGONAL_FACTOR / 256).max(1);
+            }
+            let (current_x, current_y) = get_mouse_delta();
+            set_mouse_delta(
+                add_delta(current_x, x * speed),
+                add_delta(current_y, y * speed),
+            );
+        }
+
+        let h = direction(mouse.wheel_left, mouse.wheel_right);
+        let v = direction(mouse.wheel_down, mouse.wheel_up);
+        if let Some(held) = mouse.wheel.step(h != 0 || v != 0, User::WHEEL_INTERVAL) {
+            let speed = mouse.speed(
+                held,
+                User::WHEEL_BASE_SPEED,
+                User::WHEEL_MAX_SPEED,
+                User::WHEEL_TIME_TO_MAX,
+                User::WHEEL_CONSTANT_SPEEDS,
+            ) as i16;
+            let (resolution_v, resolution_h) = wheel_resolution();
+            let (current_v, current_h) = get_wheel_delta();
+            set_wheel_delta(
+                add_delta(current_v, v * speed * resolution_v as i16),
+                add_delta(current_h, h * speed * resolution_h as i16),
+            );
+        }
+    }
+
+    /// Scrolls the vertical wheel by encoder `pulses`, `pulses_per_detent` making a detent.
+    ///
+    /// Hosts which enabled the high resolution scrolling get every pulse, the others whole detents.
+    pub fn scroll_wheel(&mut self, pulses: i8, pulses_per_detent: i8) {
+        let pulses_per_detent = pulses_per_detent.max(1) as i16;
+        let mouse = &mut self.mouse_state;
+        mouse.wheel_pulses += pulses as i16 * wheel_resolution().0 as i16;
+        // Pulses not making a whole wheel unit are kept for the next call
+        let delta = mouse.wheel_pulses / pulses_per_detent;
+        mouse.wheel_pulses -= delta * pulses_per_detent;
+        if delta != 0 {
+            let (current_v, current_h) = get_wheel_delta();
+            set_wheel_delta(add_delta(current_v, delta), current_h);
+        }
+    }
+}
@@ -0,0 +1,332 @@
+//! This module reads the pointing device of the keyboard, a trackball sensor or a trackpad, selected
+//! with [Keyboard::PointingDevice]. The master half reads it at most once per millisecond, and adds
+//! its motion and buttons to the mouse report.
+//!
+//! The device may be on either half: the slave half sends its motion and buttons to the master,
+//! which merges them with the ones of its own device. Over the I2C split link, the slave cannot
+//! drive a device on the I2C bus though.
+//!
+//! The motion depends on the [PointingMode]:
+//! - [PointingMode::Normal]: moves the cursor, at [Keyboard::POINTING_CPI].
+//! - [PointingMode::Sniping]: moves the cursor slower, at [Keyboard::POINTING_SNIPING_CPI], for precise moves.
+//! - [PointingMode::DragScroll]: scrolls the wheels, [Keyboard::POINTING_DRAG_SCROLL_DIVISOR] counts making a detent.
+//!
+//! Drivers are provided for sensors on the SPI bus, wired to [Keyboard::POINTING_CS_PIN]:
+//! [Pmw3360], [Pmw3389] and [Adns5050]. They are generic over the [SpiBus], [PointingSpi] being the one of the keyboard,
+//! and live in [omk_drivers] to be tested on the host.
+//! The [CirquePinnacle] trackpad is on the I2C bus, generic over the [I2CBus], [I2CController] being the one of the keyboard.
+
+mod cirque_pinnacle;
+
+use core::{marker::PhantomData, num::Wrapping};
+
+pub use cirque_pinnacle::{CirquePinnacle, DefaultPinnacleConfig, PinnacleConfig};
+pub use omk_drivers::pointing::{
+    Adns5050, NoFirmware, NoPointingDevice, Pmw33xx, Pmw33xxFirmware, Pmw3360, Pmw3389,
+    PointingDevice, PointingError, PointingReport,
+};
+
+use crate::{
+    Keyboard, OmkKeyboard,
+    atomic::atomic_access,
+    i2c::{I2CBus, I2CController, I2CError},
+    is_master,
+    mouse::add_delta,
+    serial::SplitTransport,
+    spi::{
+        SpiBus, SpiConfig, SpiError, spi_chip_select_init, spi_delay_us, spi_init, spi_start,
+        spi_stop, spi_transfer,
+    },
+    timer::timer_read,
+    usb::{
+        get_mouse_delta, get_wheel_delta, mouse_button_press, mouse_button_release,
+        set_mouse_delta, set_wheel_delta, wheel_resolution,
+    },
+};
+
+impl From<I2CError> for PointingError {
+    fn from(_: I2CError) -> Self {
+        Self()
+    }
+}
+
+/// The SPI bus of the keyboard, the device being selected by [Keyboard::POINTING_CS_PIN].
+pub struct PointingSpi<User>(PhantomData<User>);
+
+impl<User> const Default for PointingSpi<User> {
+    fn default() -> Self {
+        Self(PhantomData)
+    }
+}
+
+impl<User: Keyboard> SpiBus for PointingSpi<User> {
+    fn init(&mut self) {
+        spi_init();
+        spi_chip_select_init(User::POINTING_CS_PIN);
+    }
+    fn start(&mut self, config: SpiConfig) -> Result<(), SpiError> {
+        spi_start(User::POINTING_CS_PIN, config)
+    }
+    fn stop(&mut self) {
+        spi_stop(User::POINTING_CS_PIN);
+    }
+    fn transfer(&mut self, data: u8) -> Result<u8, SpiError> {
+        spi_transfer(data)
+    }
+    fn delay_us(&mut self, us: u16) {
+        spi_delay_us(us);
+    }
+}
+
+/// What the motion of the pointing device does.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PointingMode {
+    /// Moves the cursor
+    Normal,
+    /// Moves the cursor at a lower resolution
+    Sniping,
+    /// Scrolls the wheels
+    DragScroll,
+}
+
+pub struct OmkPointing<User: Keyboard> {
+    pub device: User::PointingDevice,
+    mode: PointingMode,
+    /// Set once the device is initialized
+    ready: bool,
+    /// Resolution out of the sniping mode
+    cpi: u16,
+    /// Resolution set on the device
+    device_cpi: u16,
+    /// Buttons of the last read of the device
+    device_buttons: u8,
+    /// Buttons of the devices pressed in the mouse report
+    buttons: u8,
+    /// Motion not sent yet, a report holding at most 127 counts
+    pending: (i16, i16),
+    /// Drag scroll motion not making a wheel unit yet, vertical then horizontal
+    scroll: (i32, i32),
+    /// Time of the last read
+    last_read: u32,
+    /// Motion totals of the slave half at the last task, x, y and wheel
+    prev_slave: (Wrapping<i16>, Wrapping<i16>, Wrapping<i8>),
+}
+
+impl<User: Keyboard> const Default for OmkPointing<User> {
+    fn default() -> Self {
+        Self {
+            device: User::PointingDevice::default(),
+            mode: PointingMode::Normal,
+            ready: false,
+            cpi: User::POINTING_CPI,
+            device_cpi: User::POINTING_CPI,
+            device_buttons: 0,
+            buttons: 0,
+            pending: (0, 0),
+            scroll: (0, 0),
+            last_read: 0,
+            prev_slave: (Wrapping(0), Wrapping(0), Wrapping(0)),
+        }
+    }
+}
+
+impl<User: Keyboard> OmkKeyboard<User> {
+    /// Initializes the pointing device.
+    ///
+    /// On split keyboards, both halves try, the one without the device failing and leaving it unused.
+    pub(crate) fn pointing_init(&mut self) {
+        if !Self::pointing_driven() {
+            return;
+        }
+        let pointing = &mut self.pointing_state;
+        pointing.ready = pointing.device.init(pointing.cpi).is_ok();
+    }
+
+    /// Returns `false` when this half cannot drive the device, a target on the I2C bus being
+    /// unable to drive another device on it.
+    fn pointing_driven() -> bool {
+        User::PointingDevice::PRESENT
+            && (is_master()
+                || !User::PointingDevice::I2C_DEVICE
+                || User::SPLIT_TRANSPORT != SplitTransport::I2C)
+    }
+
+    /// Reads the device of this half at `cpi`, at most once per millisecond.
+    ///
+    /// Returns no motion and the last buttons when the device isn't read.
+    fn pointing_read(&mut self, cpi: u16) -> PointingReport {
+        let pointing = &mut self.pointing_state;
+        let idle = PointingReport {
+            buttons: pointing.device_buttons,
+            ..PointingReport::default()
+        };
+        if !pointing.ready {
+            return idle;
+        }
+        if cpi != pointing.device_cpi && pointing.device.set_cpi(cpi).is_ok() {
+            pointing.device_cpi = cpi;
+        }
+        let now = timer_read();
+        if now == pointing.last_read {
+            return idle;
+        }
+        pointing.last_read = now;
+        let Ok(report) = pointing.device.read() else {
+            return idle;
+        };
+        pointing.device_buttons = report.buttons;
+        report
+    }
+
+    /// Reads the pointing devices, and adds their motion and buttons to the mouse report.
+    ///
+    /// The slave half sends the motion of its device to the master, which merges it with its own.
+    pub fn pointing_task(&mut self) {
+        if !User::PointingDevice::PRESENT {
+            return;
+        }
+        if !is_master() {
+            if !Self::pointing_driven() {
+                return;
+            }
+            // The master sends the resolution, zero until its first transaction
+            let cpi = unsafe {
+                atomic_access(self, |_, shared| shared.master_memory.master_pointing_cpi)
+            };
+            let cpi = if cpi == 0 {
+                self.pointing_state.cpi
+            } else {
+                cpi
+            };
+            let report = self.pointing_read(cpi);
+            unsafe {
+                atomic_access(self, |_, shared| {
+                    // Totals wrap, the master only looks at their differences
+                    let slave = &mut shared.slave_memory;
+                    slave.slave_pointing_x += report.x;
+                    slave.slave_pointing_y += report.y;
+                    slave.slave_pointing_wheel += report.wheel;
+                    slave.slave_pointing_buttons = report.buttons;
+                })
+            };
+            return;
+        }
+
+        let cpi = if self.pointing_state.mode == PointingMode::Sniping {
+            User::POINTING_SNIPING_CPI
+        } else {
+            self.pointing_state.cpi
+        };
+        let mut report = self.pointing_read(cpi);
+        if User::SPLIT {
+            let slave = unsafe {
+                atomic_access(self, |kb, shared| {
+                    shared.master_memory.master_pointing_cpi = cpi;
+                    let slave = &shared.slave_memory;
+                    let prev = &mut kb.pointing_state.prev_slave;
+                    let report = PointingReport {
+                        x: (slave.slave_pointing_x - prev.0).0,
+                        y: (slave.slave_pointing_y - prev.1).0,
+                        buttons: slave.slave_pointing_buttons,
+                        wheel: (slave.slave_pointing_wheel - prev.2).0,
+                    };
+                    *prev = (
+                        slave.slave_pointing_x,
+                        slave.slave_pointing_y,
+                        slave.slave_pointing_wheel,
+                    );
+                    report
+                })
+            };
+            report.x = report.x.saturating_add(slave.x);
+            report.y = report.y.saturating_add(slave.y);
+            report.buttons |= slave.buttons;
+            report.wheel = report.wheel.saturating_add(slave.wheel);
+        }
+        let pointing = &mut self.pointing_state;
+
+        let changed = report.buttons ^ pointing.buttons;
+        for button in 0..8 {
+            if changed & (1 << button) == 0 {
+                continue;
+            }
+            if report.buttons & (1 << button) != 0 {
+                mouse_button_press(button);
+            } else {
+                mouse_button_release(button);
+            }
+        }
+        pointing.buttons = report.buttons;
+
+        let x = if User::POINTING_INVERT_X {
+            report.x.saturating_neg()
+        } else {
+            report.x
+        };
+        let y = if User::POINTING_INVERT_Y {
+            report.y.saturating_neg()
+        } else {
+            report.y
+        };
+        if pointing.mode == PointingMode::DragScroll {
+            // Moving up scrolls up, as on touchpads
+            let (resolution_v, resolution_h) = wheel_resolution();
+            let divisor = User::POINTING_DRAG_SCROLL_DIVISOR.max(1) as i32;
+            pointing.scroll.0 -= y as i32 * resolution_v as i32;
+            pointing.scroll.1 += x as i32 * resolution_h as i32;
+            let v = pointing.scroll.0 / divisor;
+            let h = pointing.scroll.1 / divisor;
+            pointing.scroll.0 -= v * divisor;
+            pointing.scroll.1 -= h * divisor;
+            if v != 0 || h != 0 {
+                let (current_v, current_h) = get_wheel_delta();
+                set_wheel_delta(
+                    add_delta(current_v, v.clamp(-127, 127) as i16),
+                    add_delta(current_h, h.clamp(-127, 127) as i16),
+                );
+            }
+        } else {
+            pointing.pending.0 = pointing.pending.0.saturating_add(x);
+            pointing.pending.1 = pointing.pending.1.saturating_add(y);
+            if pointing.pending != (0, 0) {
+                // What doesn't fit in the report is sent with the next ones
+                let (current_x, current_y) = get_mouse_delta();
+                let new_x = add_delta(current_x, pointing.pending.0);
+                let new_y = add_delta(current_y, pointing.pending.1);
+                pointing.pending.0 -= new_x as i16 - current_x as i16;
+                pointing.pending.1 -= new_y as i16 - current_y as i16;
+                set_mouse_delta(new_x, new_y);
+            }
+        }
+
+        if report.wheel != 0 {
+            self.scroll_wheel(report.wheel, User::PointingDevice::WHEEL_PULSES_PER_DETENT);
+        }
+    }
+
+    /// Returns the mode of the pointing device.
+    pub fn pointing_mode(&self) -> PointingMode {
+        self.pointing_state.mode
+    }
+
+    /// Sets the mode of the pointing device, see [crate::pointing].
+    pub fn set_pointing_mode(&mut self, mode: PointingMode) {
+        let pointing = &mut self.pointing_state;
+        if pointing.mode == mode {
+            return;
+        }
+        pointing.mode = mode;
+        pointing.pending = (0, 0);
+        pointing.scroll = (0, 0);
+    }
+
+    /// Returns the resolution of the pointing device out of the sniping mode, in counts per inch.
+    pub fn pointing_cpi(&self) -> u16 {
+        self.pointing_state.cpi
+    }
+
+    /// Sets the resolution of the pointing device out of the sniping mode, in counts per inch.
+    pub fn set_pointing_cpi(&mut self, cpi: u16) {
+        self.pointing_state.cpi = cpi;
+    }
+}
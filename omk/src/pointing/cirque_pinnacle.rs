@@ -0,0 +1,301 @@
+//! This module drives the Cirque Pinnacle trackpads, e.g. the TM040040 and TM035035, on the I2C bus.
+//!
+//! By default, the trackpad runs in relative mode: it computes the motion itself, and clicks when
+//! tapped, or right clicks when tapped in its top right corner, see [PinnacleConfig::TAP_TO_CLICK].
+//!
+//! With [PinnacleConfig::CIRCULAR_SCROLL], it runs in absolute mode instead, the motion and the
+//! taps being computed here: touching the outer ring of the trackpad, then sliding along it scrolls,
+//! clockwise scrolling down.
+
+use core::marker::PhantomData;
+
+use crate::{
+    i2c::I2CBus,
+    pointing::{PointingDevice, PointingError, PointingReport},
+    timer::{timer_elapsed, timer_read},
+};
+
+/// I2C address of the trackpad, shifted for the bus.
+const PINNACLE_ADDRESS: u8 = 0x2A << 1;
+/// Firmware ID of the Pinnacle ASIC.
+const PINNACLE_FIRMWARE_ID: u8 = 0x07;
+
+/// Register Access Protocol, prefixes of the register addresses
+const RAP_READ: u8 = 0xA0;
+const RAP_WRITE: u8 = 0x80;
+
+const REG_FIRMWARE_ID: u8 = 0x00;
+const REG_STATUS1: u8 = 0x02;
+const REG_SYS_CONFIG1: u8 = 0x03;
+const REG_FEED_CONFIG1: u8 = 0x04;
+const REG_FEED_CONFIG2: u8 = 0x05;
+const REG_Z_IDLE: u8 = 0x0A;
+const REG_PACKET_BYTE0: u8 = 0x12;
+
+/// Bit of Status1 set when a packet is ready
+const STATUS1_DATA_READY: u8 = 1 << 2;
+/// Bits of FeedConfig1
+const FEED_ENABLE: u8 = 1 << 0;
+const FEED_ABSOLUTE: u8 = 1 << 1;
+/// Bits of FeedConfig2
+const FEED2_ALL_TAPS_DISABLE: u8 = 1 << 1;
+const FEED2_SCROLL_DISABLE: u8 = 1 << 3;
+const FEED2_GLIDE_EXTEND_DISABLE: u8 = 1 << 4;
+
+/// Bounds of the absolute coordinates, the sensor reporting up to 2047 x 1535
+const X_MIN: i16 = 128;
+const X_MAX: i16 = 1920;
+const Y_MIN: i16 = 64;
+const Y_MAX: i16 = 1472;
+/// Absolute counts across the trackpad, horizontally
+const X_RANGE: i32 = (X_MAX - X_MIN) as i32;
+
+/// Angle of a wheel pulse of the circular scroll, in 1/256 of radian, a detent being 1/12 of turn.
+const SCROLL_PULSE_ANGLE: i32 = 34;
+/// Wheel pulses of the circular scroll making a detent.
+const SCROLL_PULSES_PER_DETENT: i8 = 4;
+/// Longest touch making a tap, in milliseconds.
+const TAP_TIME: u32 = 200;
+/// Largest motion of a touch making a tap, in absolute counts.
+const TAP_TRAVEL: u16 = 40;
+/// Duration of the click of a tap, in milliseconds, long enough for the host to see it.
+const TAP_CLICK_TIME: u32 = 20;
+
+/// Settings of a Cirque Pinnacle trackpad.
+pub trait PinnacleConfig {
+    /// Diameter of the trackpad, in millimeters, for the resolution
+    const DIAMETER_MM: u8 = 40;
+    /// Tapping the trackpad clicks
+    const TAP_TO_CLICK: bool = true;
+    /// Sliding along the edge of the trackpad scrolls
+    const CIRCULAR_SCROLL: bool = false;
+    /// Width of the scroll ring, in percent of the radius of the trackpad
+    const SCROLL_RING: u8 = 25;
+}
+
+/// The default settings of a Cirque Pinnacle trackpad.
+pub struct DefaultPinnacleConfig;
+
+impl PinnacleConfig for DefaultPinnacleConfig {}
+
+/// A touch of the trackpad, in absolute mode.
+#[derive(Clone, Copy)]
+struct Touch {
+    /// Last position, relative to the center of the trackpad, in horizontal counts
+    position: (i16, i16),
+    /// Time the trackpad was touched
+    start: u32,
+    /// Distance traveled since the trackpad was touched
+    travel: u16,
+    /// Set if the touch started on the scroll ring
+    scrolling: bool,
+}
+
+/// A Cirque Pinnacle trackpad on `Bus`.
+pub struct CirquePinnacle<Bus, Config: PinnacleConfig = DefaultPinnacleConfig> {
+    bus: Bus,
+    /// Resolution asked for, the motion being scaled from the one of the trackpad
+    cpi: u16,
+    /// Scaled motion not reported yet, in 1/`NATIVE_CPI` of count
+    remainder: (i32, i32),
+    /// Buttons of the last packet, kept until the next one
+    buttons: u8,
+    touch: Option<Touch>,
+    /// Scroll angle not making a wheel pulse yet, in 1/256 of radian
+    angle: i32,
+    /// Time of the last tap, while it is clicking
+    tapped: Option<u32>,
+    _config: PhantomData<Config>,
+}
+
+impl<Bus: const Default, Config: PinnacleConfig> const Default for CirquePinnacle<Bus, Config> {
+    fn default() -> Self {
+        Self {
+            bus: Bus::default(),
+            cpi: 0,
+            remainder: (0, 0),
+            buttons: 0,
+            touch: None,
+            angle: 0,
+            tapped: None,
+            _config: PhantomData,
+        }
+    }
+}
+
+impl<Bus: I2CBus, Config: PinnacleConfig> CirquePinnacle<Bus, Config> {
+    /// Resolution of the trackpad, its counts spanning its diameter.
+    const NATIVE_CPI: i32 = X_RANGE * 254 / (Config::DIAMETER_MM as i32 * 10);
+
+    fn write_register(&mut self, register: u8, value: u8) -> Result<(), PointingError> {
+        Ok(self
+            .bus
+            .write(PINNACLE_ADDRESS, &[RAP_WRITE | register, value])?)
+    }
+
+    fn read_registers(&mut self, register: u8, buffer: &mut [u8]) -> Result<(), PointingError> {
+        Ok(self
+            .bus
+            .write_read(PINNACLE_ADDRESS, &[RAP_READ | register], buffer)?)
+    }
+
+    /// Reads the next packet, `None` if none is ready.
+    fn read_packet<const N: usize>(&mut self) -> Result<Option<[u8; N]>, PointingError> {
+        let mut status = [0];
+        self.read_registers(REG_STATUS1, &mut status)?;
+        if status[0] & STATUS1_DATA_READY == 0 {
+            return Ok(None);
+        }
+        let mut packet = [0; N];
+        self.read_registers(REG_PACKET_BYTE0, &mut packet)?;
+        // Clearing the flags lets the trackpad send the next packet
+        self.write_register(REG_STATUS1, 0x00)?;
+        Ok(Some(packet))
+    }
+
+    /// Scales motion from the resolution of the trackpad to the one asked for.
+    fn scale(&mut self, x: i16, y: i16) -> (i16, i16) {
+        let native = Self::NATIVE_CPI.max(1);
+        self.remainder.0 += x as i32 * self.cpi as i32;
+        self.remainder.1 += y as i32 * self.cpi as i32;
+        let scaled = (self.remainder.0 / native, self.remainder.1 / native);
+        self.remainder.0 -= scaled.0 * native;
+        self.remainder.1 -= scaled.1 * native;
+        (scaled.0 as i16, scaled.1 as i16)
+    }
+
+    fn read_relative(&mut self) -> Result<PointingReport, PointingError> {
+        let Some(packet) = self.read_packet::<3>()? else {
+            return Ok(PointingReport {
+                buttons: self.buttons,
+                ..PointingReport::default()
+            });
+        };
+        // 9 bits deltas, their sign bits being in the first byte
+        let delta = |low: u8, negative: bool| low as i16 - if negative { 256 } else { 0 };
+        let x = delta(packet[1], packet[0] & 0x10 != 0);
+        let y = delta(packet[2], packet[0] & 0x20 != 0);
+        self.buttons = packet[0] & 0x07;
+        let (x, y) = self.scale(x, y);
+        Ok(PointingReport {
+            x,
+            y,
+            buttons: self.buttons,
+            wheel: 0,
+        })
+    }
+
+    fn read_absolute(&mut self) -> Result<PointingReport, PointingError> {
+        let mut report = PointingReport::default();
+        if let Some(tap) = self.tapped {
+            if timer_elapsed(tap) < TAP_CLICK_TIME {
+                report.buttons = 1;
+                return Ok(report);
+            }
+            self.tapped = None;
+        }
+        let Some(packet) = self.read_packet::<6>()? else {
+            return Ok(report);
+        };
+        let x = packet[2] as i16 | ((packet[4] as i16 & 0x0F) << 8);
+        let y = packet[3] as i16 | ((packet[4] as i16 & 0xF0) << 4);
+
+        // The trackpad reports zeros once lifted
+        if x == 0 && y == 0 {
+            if let Some(touch) = self.touch.take()
+                && Config::TAP_TO_CLICK
+                && !touch.scrolling
+                && touch.travel <= TAP_TRAVEL
+                && timer_elapsed(touch.start) <= TAP_TIME
+            {
+                self.tapped = Some(timer_read());
+                report.buttons = 1;
+            }
+            self.angle = 0;
+            return Ok(report);
+        }
+
+        // Relative to the center, the vertical counts scaled to the horizontal ones
+        let x = x.clamp(X_MIN, X_MAX) - (X_MIN + X_MAX) / 2;
+        let y = ((y.clamp(Y_MIN, Y_MAX) - (Y_MIN + Y_MAX) / 2) as i32 * X_RANGE
+            / (Y_MAX - Y_MIN) as i32) as i16;
+        let Some(touch) = &mut self.touch else {
+            let radius = X_RANGE / 2;
+            let inner = radius * (100 - Config::SCROLL_RING.min(100) as i32) / 100;
+            let distance = x as i32 * x as i32 + y as i32 * y as i32;
+            self.touch = Some(Touch {
+                position: (x, y),
+                start: timer_read(),
+                travel: 0,
+                scrolling: Config::CIRCULAR_SCROLL && distance > inner * inner,
+            });
+            return Ok(report);
+        };
+
+        let (previous_x, previous_y) = touch.position;
+        touch.position = (x, y);
+        let (dx, dy) = (x - previous_x, y - previous_y);
+        touch.travel = touch
+            .travel
+            .saturating_add(dx.unsigned_abs() + dy.unsigned_abs());
+        if touch.scrolling {
+            // Angle between the two positions, from their cross product
+            let cross = previous_x as i32 * y as i32 - previous_y as i32 * x as i32;
+            let distance =
+                previous_x as i32 * previous_x as i32 + previous_y as i32 * previous_y as i32;
+            self.angle += cross * 256 / distance.max(1);
+            let pulses = self.angle / SCROLL_PULSE_ANGLE;
+            self.angle -= pulses * SCROLL_PULSE_ANGLE;
+            // Clockwise, with y downward, scrolls down
+            report.wheel = (-pulses).clamp(-127, 127) as i8;
+        } else {
+            (report.x, report.y) = self.scale(dx, dy);
+        }
+        Ok(report)
+    }
+}
+
+impl<Bus: I2CBus, Config: PinnacleConfig> PointingDevice for CirquePinnacle<Bus, Config> {
+    const WHEEL_PULSES_PER_DETENT: i8 = SCROLL_PULSES_PER_DETENT;
+    const I2C_DEVICE: bool = true;
+
+    fn init(&mut self, cpi: u16) -> Result<(), PointingError> {
+        self.bus.init();
+        let mut firmware_id = [0];
+        self.read_registers(REG_FIRMWARE_ID, &mut firmware_id)?;
+        if firmware_id[0] != PINNACLE_FIRMWARE_ID {
+            return Err(PointingError());
+        }
+        self.write_register(REG_STATUS1, 0x00)?;
+        // Active mode, no sleep
+        self.write_register(REG_SYS_CONFIG1, 0x00)?;
+        let mut feed_config2 = FEED2_SCROLL_DISABLE | FEED2_GLIDE_EXTEND_DISABLE;
+        if !Config::TAP_TO_CLICK {
+            feed_config2 |= FEED2_ALL_TAPS_DISABLE;
+        }
+        self.write_register(REG_FEED_CONFIG2, feed_config2)?;
+        if Config::CIRCULAR_SCROLL {
+            // Few zero packets once lifted, the first one ending the touch
+            self.write_register(REG_Z_IDLE, 5)?;
+            self.write_register(REG_FEED_CONFIG1, FEED_ENABLE | FEED_ABSOLUTE)?;
+        } else {
+            self.write_register(REG_FEED_CONFIG1, FEED_ENABLE)?;
+        }
+        self.set_cpi(cpi)
+    }
+
+    fn read(&mut self) -> Result<PointingReport, PointingError> {
+        if Config::CIRCULAR_SCROLL {
+            self.read_absolute()
+        } else {
+            self.read_relative()
+        }
+    }
+
+    fn set_cpi(&mut self, cpi: u16) -> Result<(), PointingError> {
+        // The trackpad has a fixed resolution, the motion is scaled instead
+        self.cpi = cpi;
+        self.remainder = (0, 0);
+        Ok(())
+    }
+}
@@ -0,0 +1,152 @@
+//! This module measures the scan rate and the latencies of the keyboard, when [Keyboard::PROFILING] is enabled.
+//! The measures are read with [profiling_stats], or shown on screen with [OmkKeyboard::draw_profiling_page].
+//! With a [crate::console], they are also printed every second.
+
+use crate::{
+    Keyboard, OmkKeyboard, println,
+    timer::{timer_elapsed, timer_elapsed_us16, timer_read, timer_read_us},
+};
+
+/// Measures of the keyboard, durations being in microseconds and saturated to `u16`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ProfilingStats {
+    /// Matrix scans during the last second
+    pub scan_rate: u16,
+    /// Longest `task()`
+    pub max_task_us: u16,
+    /// Longest OLED rendering
+    pub max_render_us: u16,
+    /// Last exchange with the other half, on the master
+    pub last_exchange_us: u16,
+    /// Longest exchange with the other half, on the master
+    pub max_exchange_us: u16,
+    /// Last latency from a key edge to its keyboard report being sent
+    pub last_report_latency_us: u16,
+    /// Longest latency from a key edge to its keyboard report being sent
+    pub max_report_latency_us: u16,
+}
+
+static mut STATS: ProfilingStats = ProfilingStats {
+    scan_rate: 0,
+    max_task_us: 0,
+    max_render_us: 0,
+    last_exchange_us: 0,
+    max_exchange_us: 0,
+    last_report_latency_us: 0,
+    max_report_latency_us: 0,
+};
+
+static mut SCAN_COUNT: u16 = 0;
+static mut SCAN_WINDOW_START: u32 = 0;
+/// Time of the oldest key edge not yet reported to the host
+static mut PENDING_KEY_EDGE: Option<u32> = None;
+
+/// Returns a copy of the current measures.
+pub fn profiling_stats() -> ProfilingStats {
+    unsafe { STATS }
+}
+
+/// Resets the worst-case measures.
+pub fn profiling_reset() {
+    unsafe {
+        STATS.max_task_us = 0;
+        STATS.max_render_us = 0;
+        STATS.max_exchange_us = 0;
+        STATS.max_report_latency_us = 0;
+    }
+}
+
+/// Records a full matrix scan.
+///
+/// Returns `true` once per second, when the scan rate is updated.
+pub(crate) fn scan_done() -> bool {
+    unsafe {
+        SCAN_COUNT += 1;
+        if timer_elapsed(SCAN_WINDOW_START) >= 1000 {
+            STATS.scan_rate = SCAN_COUNT;
+            SCAN_COUNT = 0;
+            SCAN_WINDOW_START = timer_read();
+            return true;
+        }
+    }
+    false
+}
+
+/// Prints the measures on the console, on one line.
+pub fn print_profiling_stats() {
+    let stats = profiling_stats();
+    println!(
+        "scan {}/s task {}us render {}us exchange {}/{}us latency {}/{}us",
+        stats.scan_rate,
+        stats.max_task_us,
+        stats.max_render_us,
+        stats.last_exchange_us,
+        stats.max_exchange_us,
+        stats.last_report_latency_us,
+        stats.max_report_latency_us
+    );
+}
+
+/// Records the end of a `task()` started at `start`.
+pub(crate) fn task_done(start: u32) {
+    let duration = timer_elapsed_us16(start);
+    unsafe { STATS.max_task_us = STATS.max_task_us.max(duration) };
+}
+
+/// Records the end of a rendering started at `start`.
+pub(crate) fn render_done(start: u32) {
+    let duration = timer_elapsed_us16(start);
+    unsafe { STATS.max_render_us = STATS.max_render_us.max(duration) };
+}
+
+/// Records the end of an exchange with the other half started at `start`.
+pub(crate) fn exchange_done(start: u32) {
+    let duration = timer_elapsed_us16(start);
+    unsafe {
+        STATS.last_exchange_us = duration;
+        STATS.max_exchange_us = STATS.max_exchange_us.max(duration);
+    }
+}
+
+/// Records a key press or release, starting the report latency measure if none is running.
+pub(crate) fn key_edge() {
+    unsafe {
+        if PENDING_KEY_EDGE.is_none() {
+            PENDING_KEY_EDGE = Some(timer_read_us());
+        }
+    }
+}
+
+/// Records a keyboard report being sent to the host.
+pub(crate) fn report_sent() {
+    if let Some(start) = unsafe { PENDING_KEY_EDGE.take() } {
+        let latency = timer_elapsed_us16(start);
+        unsafe {
+            STATS.last_report_latency_us = latency;
+            STATS.max_report_latency_us = STATS.max_report_latency_us.max(latency);
+        }
+    }
+}
+
+impl<User: Keyboard> OmkKeyboard<User> {
+    /// Draws the measures, one per line: scan rate, then longest task, rendering, exchange
+    /// and report latency, in microseconds. Values are capped to 9999.
+    pub fn draw_profiling_page(offset_x: u8, offset_y: u8) {
+        let stats = profiling_stats();
+        let lines = [
+            ('S', stats.scan_rate),
+            ('T', stats.max_task_us),
+            ('R', stats.max_render_us),
+            ('X', stats.max_exchange_us),
+            ('L', stats.max_report_latency_us),
+        ];
+        for (i, (label, value)) in lines.into_iter().enumerate() {
+            let y = offset_y + i as u8 * User::CHAR_HEIGHT;
+            for c in 0..5 {
+                Self::clear_char(offset_x + c * User::CHAR_WIDTH, y);
+            }
+            Self::draw_char(label, offset_x, y);
+            Self::draw_u16(value.min(9999), offset_x + User::CHAR_WIDTH, y);
+        }
+    }
+}
@@ -1,11 +1,65 @@
+//! This module reads the rotary encoders, [Keyboard::ENCODERS_PER_HAND] on each half, from the
+//! timer interrupt. Each half sends the pulses of its encoders to the other one.
+//!
+//! Steps of the encoders are passed to [Keyboard::rotary_encoder_handler], and tap the keys of
+//! [Keyboard::ENCODER_MAP] on the master, for the current layer. With [Keyboard::ENCODER_ACCELERATION],
+//! steps closer than [Keyboard::ENCODER_ACCELERATION_INTERVAL] ms are multiplied, the more the faster
+//! the encoder spins, for fine moves when turned slowly and coarse ones when spun. Pulses aren't.
+//!
+//! The switches of the encoders, [Keyboard::LEFT_ENCODER_SWITCHES] and [Keyboard::RIGHT_ENCODER_SWITCHES],
+//! are read with the matrix, as the keys at [Keyboard::ENCODER_SWITCH_POSITIONS].
+//!
+//!
+//! ```ignore
+//! #[progmem]
+//! static ENCODER_MAP: EncoderMap<UserKeyboard> = { use omk::keys::*;
+//! [
+//!     // Layer 0: volume on the left encoder, nothing on the right one
+//!     [[VOL_DO, VOL_UP], [NO_OP, NO_OP]],
+//!     // Layer 1: pages
+//!     [[PAGE_UP, PAGE_DW], [NO_OP, NO_OP]],
+//! ]};
+//! ```
+
 use core::{marker::PhantomData, num::Wrapping};
 
+use avr_base::pins::NO_PIN;
+
 use crate::InterruptsHandler;
-use crate::{Keyboard, OmkKeyboard, atomic::atomic_access, is_master};
+use crate::{
+    Keyboard, OmkKeyboard,
+    atomic::atomic_access,
+    is_master,
+    keymap::CustomKey,
+    timer::{timer_elapsed, timer_read},
+};
+
+/// Holding time of the keys of [Keyboard::ENCODER_MAP], in milliseconds.
+const ENCODER_TAP_TIME: u16 = 10;
+
+/// Represents the taps of the keys of [Keyboard::ENCODER_MAP] not done yet, one key being held at a time.
+pub struct EncoderMapState<User: Keyboard> {
+    /// Steps of each encoder not tapped yet, the encoders of the left half first
+    pending: [i8; User::ENCODER_COUNT],
+    /// Key held, released [ENCODER_TAP_TIME] ms after its press
+    key: Option<&'static dyn CustomKey<User>>,
+    /// Time of the last press or release
+    timer: u32,
+}
 
-/// Represents a rotary encoder with user-defined constraints.
+impl<User: Keyboard> const Default for EncoderMapState<User> {
+    fn default() -> Self {
+        Self {
+            pending: [0; _],
+            key: None,
+            timer: 0,
+        }
+    }
+}
+
+/// Represents the rotary encoders of a half, with user-defined constraints.
 pub struct RotaryEncoder<User: Keyboard> {
-    encoder: RotaryState,
+    encoder: RotaryState<User>,
     _phantom: PhantomData<User>,
 }
 
@@ -16,13 +70,24 @@ impl<User: Keyboard> Clone for RotaryEncoder<User> {
 }
 impl<User: Keyboard> Copy for RotaryEncoder<User> {}
 
-/// Represents the state of the rotary encoder.
-#[derive(Clone, Copy)]
-pub(crate) struct RotaryState {
-    state: u8,
-    pulses: Wrapping<i8>,
-    prev_other_pulses: Wrapping<i8>,
+/// Represents the state of the rotary encoders.
+pub(crate) struct RotaryState<User: Keyboard> {
+    /// Last two readings of the pins of each encoder of this half
+    state: [u8; User::ENCODERS_PER_HAND],
+    pulses: [Wrapping<i8>; User::ENCODERS_PER_HAND],
+    prev_other_pulses: [Wrapping<i8>; User::ENCODERS_PER_HAND],
+    /// Pulses not making a whole step yet, the encoders of the left half first
+    remainders: [i8; User::ENCODER_COUNT],
+    /// Time of the last step of each encoder, for the acceleration
+    last_step: [u32; User::ENCODER_COUNT],
+}
+
+impl<User: Keyboard> Clone for RotaryState<User> {
+    fn clone(&self) -> Self {
+        *self
+    }
 }
+impl<User: Keyboard> Copy for RotaryState<User> {}
 
 impl<User: Keyboard> Default for RotaryEncoder<User> {
     fn default() -> Self {
@@ -38,21 +103,22 @@ pub(crate) unsafe fn fast_encoder_task<User: Keyboard + InterruptsHandler<User>>
     encoder: *mut RotaryEncoder<User>,
 ) {
     const LUT: [i8; 16] = [0, -1, 1, 0, 1, 0, 0, -1, -1, 0, 0, 1, 0, 1, -1, 0];
-    let (pad_a, pad_b) = (
-        User::ROTARY_ENCODER_PIN1.gpio_read_pin(),
-        User::ROTARY_ENCODER_PIN2.gpio_read_pin(),
-    );
-    let new_state = pad_a as u8 | ((pad_b as u8) << 1);
-    unsafe {
-        (*encoder).encoder.state = (*encoder).encoder.state << 2 | new_state;
-        let add = LUT[(*encoder).encoder.state as usize % 16];
-        (*encoder).encoder.pulses += add;
-        if is_master() {
-            (*<User as InterruptsHandler<User>>::SHARED_MEMORY_MASTER)
-                .master_rotary_encoder_pulses += add;
-        } else {
-            (*<User as InterruptsHandler<User>>::SHARED_MEMORY_SLAVE)
-                .slave_rotary_encoder_pulses += add;
+    let pins = OmkKeyboard::<User>::rotary_encoder_pins();
+    for (i, (pin1, pin2)) in pins.iter().enumerate() {
+        let (pad_a, pad_b) = (pin1.gpio_read_pin(), pin2.gpio_read_pin());
+        let new_state = pad_a as u8 | ((pad_b as u8) << 1);
+        unsafe {
+            let state = &mut (*encoder).encoder;
+            state.state[i] = state.state[i] << 2 | new_state;
+            let add = LUT[state.state[i] as usize % 16];
+            state.pulses[i] += add;
+            if is_master() {
+                (*<User as InterruptsHandler<User>>::SHARED_MEMORY_MASTER)
+                    .master_rotary_encoder_pulses[i] += add;
+            } else {
+                (*<User as InterruptsHandler<User>>::SHARED_MEMORY_SLAVE)
+                    .slave_rotary_encoder_pulses[i] += add;
+            }
         }
     }
 }
@@ -62,53 +128,137 @@ impl<User: Keyboard> RotaryEncoder<User> {
     pub const fn new() -> Self {
         Self {
             encoder: RotaryState {
-                state: 0,
-                pulses: Wrapping(0),
-                prev_other_pulses: Wrapping(0),
+                state: [0; _],
+                pulses: [Wrapping(0); _],
+                prev_other_pulses: [Wrapping(0); _],
+                remainders: [0; _],
+                last_step: [0; _],
             },
             _phantom: PhantomData,
         }
     }
 
-    /// Initializes the rotary encoder pins as input with pull-up resistors.
+    /// Initializes the rotary encoder pins, and the ones of their switches, as input with pull-up resistors.
     pub fn init() {
-        User::ROTARY_ENCODER_PIN1.gpio_set_pin_input_high();
-        User::ROTARY_ENCODER_PIN2.gpio_set_pin_input_high();
+        const {
+            assert!(
+                User::ENCODER_COUNT
+                    == if User::SPLIT {
+                        User::ENCODERS_PER_HAND * 2
+                    } else {
+                        User::ENCODERS_PER_HAND
+                    },
+                "ENCODER_COUNT must be ENCODERS_PER_HAND * 2 on split keyboards, ENCODERS_PER_HAND on unibody ones"
+            )
+        };
+        for (pin1, pin2) in OmkKeyboard::<User>::rotary_encoder_pins() {
+            pin1.gpio_set_pin_input_high();
+            pin2.gpio_set_pin_input_high();
+        }
+        for pin in OmkKeyboard::<User>::encoder_switch_pins() {
+            if pin != NO_PIN {
+                pin.gpio_set_pin_input_high();
+            }
+        }
     }
 }
 
 impl<User: Keyboard> RotaryEncoder<User> {
-    /// Processes the rotary encoder task and returns the number of rotations since the last call.
-    pub fn task(kb: &mut OmkKeyboard<User>) -> (i8, i8) {
+    /// Processes the rotary encoder task.
+    ///
+    /// Returns the number of steps of each encoder since the last call, then the number of pulses,
+    /// both with the encoders of the left half first.
+    pub fn task(
+        kb: &mut OmkKeyboard<User>,
+    ) -> ([i8; User::ENCODER_COUNT], [i8; User::ENCODER_COUNT]) {
         unsafe {
             atomic_access(kb, |_, shared| {
                 let encoder = &mut shared.rotary_encoder.encoder;
-                if is_master() {
-                    let this = encoder.pulses.0 / User::ROTARY_ENCODER_RESOLUTION;
-                    encoder.pulses %= User::ROTARY_ENCODER_RESOLUTION;
-                    let other_new = shared.slave_memory.slave_rotary_encoder_pulses;
-                    let other =
-                        (other_new - encoder.prev_other_pulses).0 / User::ROTARY_ENCODER_RESOLUTION;
-                    encoder.prev_other_pulses = other_new
-                        - Wrapping(
-                            (other_new - encoder.prev_other_pulses).0
-                                % User::ROTARY_ENCODER_RESOLUTION,
-                        );
-                    (this, other)
-                } else {
-                    let this = encoder.pulses.0 / User::ROTARY_ENCODER_RESOLUTION;
-                    encoder.pulses %= User::ROTARY_ENCODER_RESOLUTION;
-                    let other_new = shared.master_memory.master_rotary_encoder_pulses;
-                    let other =
-                        (other_new - encoder.prev_other_pulses).0 / User::ROTARY_ENCODER_RESOLUTION;
-                    encoder.prev_other_pulses = other_new
-                        - Wrapping(
-                            (other_new - encoder.prev_other_pulses).0
-                                % User::ROTARY_ENCODER_RESOLUTION,
-                        );
-                    (other, this)
+                let this_offset = OmkKeyboard::<User>::this_hand_encoder_offset();
+                let other_offset = OmkKeyboard::<User>::other_hand_encoder_offset();
+                let mut pulses = [0; User::ENCODER_COUNT];
+                for i in 0..User::ENCODERS_PER_HAND {
+                    pulses[this_offset + i] = encoder.pulses[i].0;
+                    encoder.pulses[i] = Wrapping(0);
+                    if User::SPLIT {
+                        let other_new = if is_master() {
+                            shared.slave_memory.slave_rotary_encoder_pulses[i]
+                        } else {
+                            shared.master_memory.master_rotary_encoder_pulses[i]
+                        };
+                        pulses[other_offset + i] = (other_new - encoder.prev_other_pulses[i]).0;
+                        encoder.prev_other_pulses[i] = other_new;
+                    }
+                }
+
+                // Pulses not making a whole step are kept for the next call
+                let resolution = User::ROTARY_ENCODER_RESOLUTION;
+                let mut steps = [0; User::ENCODER_COUNT];
+                for i in 0..User::ENCODER_COUNT {
+                    let total = encoder.remainders[i].wrapping_add(pulses[i]);
+                    encoder.remainders[i] = total % resolution;
+                    steps[i] = total / resolution;
+                }
+
+                if User::ENCODER_ACCELERATION > 1 {
+                    let threshold = User::ENCODER_ACCELERATION_INTERVAL.max(1) as u32;
+                    let now = timer_read();
+                    for i in 0..User::ENCODER_COUNT {
+                        if steps[i] == 0 {
+                            continue;
+                        }
+                        // Mean time between the steps since the last task
+                        let interval =
+                            timer_elapsed(encoder.last_step[i]) / steps[i].unsigned_abs() as u32;
+                        encoder.last_step[i] = now;
+                        if interval < threshold {
+                            // From 1 at the threshold, to ENCODER_ACCELERATION for instant steps
+                            let factor = 1
+                                + (User::ENCODER_ACCELERATION as u32 - 1) * (threshold - interval)
+                                    / threshold;
+                            steps[i] = (steps[i] as i16 * factor as i16).clamp(-127, 127) as i8;
+                        }
+                    }
                 }
+                (steps, pulses)
             })
         }
     }
 }
+
+impl<User: Keyboard> OmkKeyboard<User> {
+    /// Taps the keys of [Keyboard::ENCODER_MAP] for the steps of the encoders, on the master.
+    ///
+    /// Steps are queued, and tapped one at a time from the next tasks on, each key being held
+    /// for [ENCODER_TAP_TIME] ms, so that spinning an encoder doesn't stall the keyboard.
+    pub fn encoder_map_task(&mut self, steps: &[i8; User::ENCODER_COUNT]) {
+        let Some(map) = User::ENCODER_MAP else {
+            return;
+        };
+        if !is_master() {
+            return;
+        }
+        let layer = self.layer as usize;
+        let state = &mut self.encoder_map_state;
+        for (pending, steps) in state.pending.iter_mut().zip(steps) {
+            *pending = pending.saturating_add(*steps);
+        }
+        if timer_elapsed(state.timer) < ENCODER_TAP_TIME as u32 {
+            return;
+        }
+        if let Some(key) = state.key.take() {
+            state.timer = timer_read();
+            key.on_released(self);
+            return;
+        }
+        let Some(encoder) = state.pending.iter().position(|steps| *steps != 0) else {
+            return;
+        };
+        let clockwise = state.pending[encoder] > 0;
+        state.pending[encoder] -= state.pending[encoder].signum();
+        let key = map.at(layer).at(encoder).at(clockwise as usize).read();
+        state.key = Some(key);
+        state.timer = timer_read();
+        key.on_pressed(self);
+    }
+}
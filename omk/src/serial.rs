@@ -1,23 +1,26 @@
 //! This module provides serial communication functionality for the keyboard firmware.
 //! It includes utilities for data transmission, synchronization, and error handling.
 
+pub mod i2c_transport;
 pub mod shared_memory;
 
 use core::{mem::transmute, ptr::null_mut, sync::atomic::AtomicBool};
 
 use avr_base::{
     F_CPU,
+    pins::NO_PIN,
     register::{EICRA, EIMSK},
 };
 use avr_delay::{delay_cycles, delay_us};
 
 use crate::{
-    Keyboard, OmkKeyboard,
+    Keyboard, OmkKeyboard, OmkShared,
     atomic::atomic_access,
+    i2c::{i2c_init, i2c_target_init},
     interrupts::InterruptsHandler,
-    is_master,
+    is_master, profiling,
     serial::shared_memory::{MasterSharedMemory, SlaveSharedMemory},
-    timer::cycles_read,
+    timer::{cycles_read, timer_read_us},
 };
 
 const SERIAL_DELAY: u64 = 3; // in microseconds
@@ -31,6 +34,15 @@ const SERIAL_DELAY_HALF_CYCLES: u64 = SERIAL_DELAY_CYCLES / 2;
 
 type const SLAVE_INT_WIDTH_US: u64 = 1;
 
+/// Link used to exchange the shared memories between both halves.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SplitTransport {
+    /// Half-duplex soft serial on [Keyboard::SOFT_SERIAL_PIN].
+    SoftSerial,
+    /// I2C, the slave half being a target at [Keyboard::SPLIT_I2C_ADDRESS].
+    I2C,
+}
+
 /// Represents an error in serial communication.
 #[derive(Debug)]
 pub struct SerialError;
@@ -145,10 +157,29 @@ impl<User: Keyboard + InterruptsHandler<User>> OmkKeyboard<User> {
     ///
     /// This function sets up shared memory and configures the serial pins based on the device role (master or slave).
     pub(crate) fn serial_init(&mut self) {
-        if is_master() {
-            Self::soft_serial_initiator_init();
-        } else {
-            Self::soft_serial_target_init();
+        const {
+            assert!(
+                !User::SPLIT
+                    || !matches!(User::SPLIT_TRANSPORT, SplitTransport::SoftSerial)
+                    || User::SOFT_SERIAL_PIN.0 != NO_PIN.0,
+                "SOFT_SERIAL_PIN must be set on split keyboards linked by soft serial"
+            )
+        };
+        match User::SPLIT_TRANSPORT {
+            SplitTransport::SoftSerial => {
+                if is_master() {
+                    Self::soft_serial_initiator_init();
+                } else {
+                    Self::soft_serial_target_init();
+                }
+            }
+            SplitTransport::I2C => {
+                if is_master() {
+                    i2c_init();
+                } else {
+                    i2c_target_init(User::SPLIT_I2C_ADDRESS);
+                }
+            }
         }
     }
 
@@ -363,33 +394,62 @@ impl<User: Keyboard + InterruptsHandler<User>> OmkKeyboard<User> {
 
     /// Executes the serial task for data synchronization between master and slave devices.
     pub fn serial_task(&mut self) {
-        unsafe {
-            atomic_access(self, |kb, shared| {
-                if is_master() {
-                    // Copy the matrix in the shared memory
-                    shared.master_memory.master_matrix = *kb.current_matrix
-                        [User::THIS_HAND_OFFSET..User::THIS_HAND_OFFSET + User::ROWS_PER_HAND]
-                        .as_mut_array()
-                        .unwrap_unchecked();
-                    Self::master_exec_transactions();
-                    // Copy the matrix from the shared memory
-                    *kb.current_matrix
-                        [User::OTHER_HAND_OFFSET..User::OTHER_HAND_OFFSET + User::ROWS_PER_HAND]
-                        .as_mut_array()
-                        .unwrap_unchecked() = shared.slave_memory.slave_matrix;
-                } else {
+        if is_master() {
+            let exchange_start = if User::PROFILING { timer_read_us() } else { 0 };
+            match User::SPLIT_TRANSPORT {
+                SplitTransport::SoftSerial => unsafe {
+                    atomic_access(self, |kb, shared| {
+                        Self::master_copy_to_shared(kb, shared);
+                        Self::master_exec_transactions();
+                        Self::master_copy_from_shared(kb, shared);
+                    })
+                },
+                SplitTransport::I2C => {
+                    unsafe { atomic_access(self, Self::master_copy_to_shared) };
+                    Self::i2c_master_exec_transactions();
+                    unsafe { atomic_access(self, Self::master_copy_from_shared) };
+                }
+            }
+            if User::PROFILING {
+                profiling::exchange_done(exchange_start);
+            }
+        } else {
+            unsafe {
+                atomic_access(self, |kb, shared| {
+                    let (this, other) = (Self::this_hand_offset(), Self::other_hand_offset());
                     // Copy the matrix in the shared memory
                     shared.slave_memory.slave_matrix = *kb.current_matrix
-                        [User::THIS_HAND_OFFSET..User::THIS_HAND_OFFSET + User::ROWS_PER_HAND]
+                        [this..this + User::ROWS_PER_HAND]
                         .as_mut_array()
                         .unwrap_unchecked();
                     // Copy the matrix from the shared memory
-                    *kb.current_matrix
-                        [User::OTHER_HAND_OFFSET..User::OTHER_HAND_OFFSET + User::ROWS_PER_HAND]
+                    *kb.current_matrix[other..other + User::ROWS_PER_HAND]
                         .as_mut_array()
                         .unwrap_unchecked() = shared.master_memory.master_matrix;
-                }
-            })
+                    kb.suspended = shared.master_memory.suspended;
+                })
+            }
+        }
+    }
+
+    /// Copies the master matrix in the shared memory, before a transaction.
+    fn master_copy_to_shared(kb: &mut OmkKeyboard<User>, shared: &mut OmkShared<User>) {
+        shared.master_memory.suspended = kb.suspended;
+        let this = Self::this_hand_offset();
+        shared.master_memory.master_matrix = unsafe {
+            *kb.current_matrix[this..this + User::ROWS_PER_HAND]
+                .as_mut_array()
+                .unwrap_unchecked()
+        };
+    }
+
+    /// Copies the slave matrix from the shared memory, after a transaction.
+    fn master_copy_from_shared(kb: &mut OmkKeyboard<User>, shared: &mut OmkShared<User>) {
+        let other = Self::other_hand_offset();
+        unsafe {
+            *kb.current_matrix[other..other + User::ROWS_PER_HAND]
+                .as_mut_array()
+                .unwrap_unchecked() = shared.slave_memory.slave_matrix;
         }
     }
 
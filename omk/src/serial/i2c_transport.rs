@@ -0,0 +1,123 @@
+//! This module implements the split transport over I2C, as an alternative to the soft serial link.
+//!
+//! The slave half is an I2C target exposing both shared memories as a register map: the
+//! [MasterSharedMemory] starts at register 0 and is followed by the [SlaveSharedMemory].
+//! The master writes its memory, then reads back the slave one.
+
+use core::{iter::once, marker::PhantomData, slice::from_raw_parts_mut};
+
+use crate::{
+    Keyboard, OmkKeyboard,
+    i2c::{I2CError, I2CTargetRegisters, i2c_receive, i2c_target_interrupt, i2c_transmit},
+    interrupts::InterruptsHandler,
+    serial::{
+        ERROR_COUNT, SERIAL_INTERRUPT_EXECUTED, Transaction,
+        shared_memory::{MasterSharedMemory, SlaveSharedMemory},
+    },
+};
+
+/// Timeout of a single I2C operation with the slave half, in milliseconds.
+const SPLIT_I2C_TIMEOUT: u16 = 5;
+
+/// Register map of the slave half, backed by the shared memories.
+struct SplitRegisters<User>(PhantomData<User>);
+
+impl<User: Keyboard + InterruptsHandler<User>> SplitRegisters<User> {
+    /// Register at which the master writes its [MasterSharedMemory].
+    const MASTER_MEMORY_REGISTER: u8 = 0;
+    /// Register from which the master reads the [SlaveSharedMemory].
+    ///
+    /// Used by both halves, so it also checks that the register map fits in the `u8` registers.
+    const SLAVE_MEMORY_REGISTER: u8 = {
+        assert!(
+            size_of::<MasterSharedMemory<User>>() + size_of::<SlaveSharedMemory<User>>() <= 255,
+            "The shared memories don't fit in the 255 registers of the I2C split link"
+        );
+        size_of::<MasterSharedMemory<User>>() as u8
+    };
+    /// Size of the whole register map.
+    const REGISTER_COUNT: u8 =
+        Self::SLAVE_MEMORY_REGISTER + size_of::<SlaveSharedMemory<User>>() as u8;
+}
+
+impl<User: Keyboard + InterruptsHandler<User>> I2CTargetRegisters for SplitRegisters<User> {
+    fn read(register: u8) -> u8 {
+        if register < Self::SLAVE_MEMORY_REGISTER {
+            unsafe {
+                User::SHARED_MEMORY_MASTER
+                    .cast::<u8>()
+                    .add(register as usize)
+                    .read_volatile()
+            }
+        } else if register < Self::REGISTER_COUNT {
+            unsafe {
+                User::SHARED_MEMORY_SLAVE
+                    .cast::<u8>()
+                    .add((register - Self::SLAVE_MEMORY_REGISTER) as usize)
+                    .read_volatile()
+            }
+        } else {
+            0
+        }
+    }
+
+    fn write(register: u8, data: u8) {
+        // Only the master memory can be written by the master
+        if register < Self::SLAVE_MEMORY_REGISTER {
+            unsafe {
+                User::SHARED_MEMORY_MASTER
+                    .cast::<u8>()
+                    .add(register as usize)
+                    .write_volatile(data)
+            }
+        }
+    }
+
+    fn stop() {
+        SERIAL_INTERRUPT_EXECUTED.store(true, core::sync::atomic::Ordering::Relaxed);
+    }
+}
+
+impl<User: Keyboard + InterruptsHandler<User>> OmkKeyboard<User> {
+    /// Handles the TWI interrupt on the slave half, when the split link uses I2C.
+    #[inline(always)]
+    pub fn split_i2c_interrupt() {
+        unsafe { i2c_target_interrupt::<SplitRegisters<User>>() }
+    }
+
+    /// Exchanges the shared memories with the slave half over I2C.
+    ///
+    /// Unlike [Self::master_exec_transactions], this must not be called from an atomic context,
+    /// since I2C timeouts rely on the timer interrupt.
+    pub fn i2c_master_exec_transactions() {
+        if Self::i2c_exchange_shared_memory().is_err() {
+            unsafe { ERROR_COUNT += 1 };
+        }
+    }
+
+    fn i2c_exchange_shared_memory() -> Result<(), I2CError> {
+        let address = User::SPLIT_I2C_ADDRESS << 1;
+
+        // Write the master memory
+        let (data, len) = Transaction::SyncMaster.get_send_address::<User>();
+        i2c_transmit(
+            address,
+            once(SplitRegisters::<User>::MASTER_MEMORY_REGISTER)
+                .chain((0..len as usize).map(|i| unsafe { data.add(i).read_volatile() })),
+            SPLIT_I2C_TIMEOUT,
+        )?;
+
+        // Select then read the slave memory
+        i2c_transmit(
+            address,
+            once(SplitRegisters::<User>::SLAVE_MEMORY_REGISTER),
+            SPLIT_I2C_TIMEOUT,
+        )?;
+        let (data, len) = Transaction::SyncSlave.get_receive_address::<User>();
+        i2c_receive(
+            address,
+            unsafe { from_raw_parts_mut(data, len as usize) },
+            SPLIT_I2C_TIMEOUT,
+        )
+    }
+}
@@ -7,7 +7,11 @@ use crate::Keyboard;
 #[derive(Debug, Clone, Copy)]
 pub struct MasterSharedMemory<User: Keyboard> {
     pub(crate) master_matrix: [User::MatrixRowType; User::ROWS_PER_HAND],
-    pub(crate) master_rotary_encoder_pulses: Wrapping<i8>,
+    pub(crate) master_rotary_encoder_pulses: [Wrapping<i8>; User::ENCODERS_PER_HAND],
+    /// Whether the host has suspended the USB bus
+    pub(crate) suspended: bool,
+    /// Resolution of the pointing device, for the one of the slave
+    pub(crate) master_pointing_cpi: u16,
 }
 
 impl<User: Keyboard> MasterSharedMemory<User> {
@@ -15,7 +19,9 @@ impl<User: Keyboard> MasterSharedMemory<User> {
     pub const fn new() -> Self {
         Self {
             master_matrix: [0.into(); _],
-            master_rotary_encoder_pulses: Wrapping(0),
+            master_rotary_encoder_pulses: [Wrapping(0); _],
+            suspended: false,
+            master_pointing_cpi: 0,
         }
     }
 }
@@ -30,7 +36,13 @@ impl<User: Keyboard> Default for MasterSharedMemory<User> {
 #[derive(Debug, Clone, Copy)]
 pub struct SlaveSharedMemory<User: Keyboard> {
     pub(crate) slave_matrix: [User::MatrixRowType; User::ROWS_PER_HAND],
-    pub(crate) slave_rotary_encoder_pulses: Wrapping<i8>,
+    pub(crate) slave_rotary_encoder_pulses: [Wrapping<i8>; User::ENCODERS_PER_HAND],
+    /// Motion of the pointing device summed since boot, wrapping, see [crate::pointing]
+    pub(crate) slave_pointing_x: Wrapping<i16>,
+    pub(crate) slave_pointing_y: Wrapping<i16>,
+    pub(crate) slave_pointing_wheel: Wrapping<i8>,
+    /// Buttons of the pointing device
+    pub(crate) slave_pointing_buttons: u8,
 }
 
 impl<User: Keyboard> SlaveSharedMemory<User> {
@@ -38,7 +50,11 @@ impl<User: Keyboard> SlaveSharedMemory<User> {
     pub const fn new() -> Self {
         Self {
             slave_matrix: [0.into(); _],
-            slave_rotary_encoder_pulses: Wrapping(0),
+            slave_rotary_encoder_pulses: [Wrapping(0); _],
+            slave_pointing_x: Wrapping(0),
+            slave_pointing_y: Wrapping(0),
+            slave_pointing_wheel: Wrapping(0),
+            slave_pointing_buttons: 0,
         }
     }
 }
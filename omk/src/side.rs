@@ -0,0 +1,187 @@
+//! This module decides at boot which half is the left one, and which half is the master.
+//! The master is the half connected to the computer, the other one becoming the serial target,
+//! so that a single firmware image can work on both halves.
+
+use core::arch::asm;
+
+use avr_base::{
+    pins::Pin,
+    register::{OTGPADE, USBCON, USBE, USBSTA, VBUS},
+};
+use avr_delay::delay_us;
+use lufa_rs::{USB_DEVICE_STATE, USB_Disable, USB_Init, USB_USBTask, UsbDeviceStates};
+
+use crate::{
+    Keyboard,
+    eeprom::EepromRefMut,
+    timer::{timer_elapsed16, timer_read},
+};
+
+/// Source of the handedness of each half.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Handedness {
+    /// `IS_KEYBOARD_RIGHT` env variable at build time, which needs one firmware per half.
+    BuildVariable,
+    /// Level read on a pin at boot, pulled high by default.
+    Pin {
+        /// Pin tied to a different level on each half.
+        pin: Pin,
+        /// `true` if the pin is tied low on the left half, `false` if it is on the right one.
+        low_is_left: bool,
+    },
+    /// Byte stored in EEPROM by [store_handedness], or by the `left_hand` / `right_hand` make targets.
+    /// Falls back on the build variable while it is unset.
+    Eeprom,
+}
+
+/// How the master half is chosen at boot.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MasterElection {
+    /// The left half is always the master, and must be the one plugged into the computer.
+    Left,
+    /// The half powered by USB is the master, detected on the VBUS pad.
+    Vbus,
+    /// The half enumerated by a USB host is the master.
+    /// Needed by boards where VBUS is tied to VCC, and thus also powered on the other half.
+    Enumeration {
+        /// Maximum time to wait for the enumeration at boot, in milliseconds.
+        timeout: u16,
+    },
+}
+
+/// Value of the handedness byte on the left half.
+pub const HANDEDNESS_LEFT: u8 = 1;
+/// Value of the handedness byte on the right half.
+pub const HANDEDNESS_RIGHT: u8 = 0;
+
+/// Address of the handedness byte, the last one of the EEPROM.
+pub(crate) const HANDEDNESS_ADDRESS: u16 = 0x3FF;
+
+/// Handedness byte, see the [EEPROM layout](crate::dynamic_keymap#eeprom-layout).
+const HANDEDNESS_EEPROM: EepromRefMut<'static, u8> =
+    unsafe { EepromRefMut::new(HANDEDNESS_ADDRESS as *mut u8) };
+
+static mut IS_LEFT: bool = !side();
+static mut IS_MASTER: bool = !side();
+
+/// Decides the side and the role of this half, and initializes or disables USB accordingly.
+///
+/// This must be called once at boot, after the timer initialization and before any code relying
+/// on [is_left] or [is_master].
+pub(crate) fn side_init<User: Keyboard>() {
+    if !User::SPLIT {
+        // A unibody keyboard is a single left master half
+        unsafe {
+            IS_LEFT = true;
+            IS_MASTER = true;
+            USB_Init();
+        }
+        return;
+    }
+
+    let left = match User::HANDEDNESS {
+        Handedness::BuildVariable => !side(),
+        Handedness::Pin { pin, low_is_left } => {
+            pin.gpio_set_pin_input_high();
+            delay_us::<10>();
+            pin.gpio_read_pin() != low_is_left
+        }
+        Handedness::Eeprom => match HANDEDNESS_EEPROM.read() {
+            HANDEDNESS_LEFT => true,
+            HANDEDNESS_RIGHT => false,
+            _ => !side(),
+        },
+    };
+    unsafe { IS_LEFT = left };
+
+    let (master, usb_initialized) = match User::MASTER_ELECTION {
+        MasterElection::Left => (left, false),
+        MasterElection::Vbus => (vbus_detected(), false),
+        MasterElection::Enumeration { timeout } => {
+            let enumerated = usb_enumerated(timeout);
+            (enumerated, enumerated)
+        }
+    };
+    unsafe { IS_MASTER = master };
+
+    if master {
+        if !usb_initialized {
+            unsafe { USB_Init() };
+        }
+    } else {
+        // Needed for the code to works directly after flash, but seems to crash LUFA, which already resolve the problem on flash
+        USBCON.write(USBCON & !USBE);
+    }
+}
+
+/// Stores the handedness of this half in EEPROM.
+///
+/// It is used from the next boot on, if [Keyboard::HANDEDNESS] is [Handedness::Eeprom].
+pub fn store_handedness(left: bool) {
+    let mut eeprom = HANDEDNESS_EEPROM;
+    let value = if left {
+        HANDEDNESS_LEFT
+    } else {
+        HANDEDNESS_RIGHT
+    };
+    // Spare an EEPROM write cycle if the value is already stored
+    if eeprom.read() != value {
+        eeprom.write(&value);
+    }
+}
+
+/// Returns true if the VBUS line is powered, i.e. if this half is plugged to a computer.
+fn vbus_detected() -> bool {
+    // Enable the VBUS pad
+    USBCON.write(USBCON | OTGPADE);
+    delay_us::<5>();
+    USBSTA & VBUS != 0
+}
+
+/// Starts USB and waits for a host to configure the device, for at most `timeout` milliseconds.
+///
+/// Returns true if the device was configured, USB being left initialized, false otherwise.
+fn usb_enumerated(timeout: u16) -> bool {
+    unsafe {
+        USB_Init();
+        // USB and timer interrupts are needed for the enumeration
+        asm!("sei");
+    }
+    let start = timer_read();
+    let mut configured = false;
+    while !configured && timer_elapsed16(start) < timeout {
+        unsafe { USB_USBTask() };
+        configured = unsafe { USB_DEVICE_STATE } == UsbDeviceStates::DeviceStateConfigured as u8;
+    }
+    unsafe {
+        asm!("cli");
+        if !configured {
+            USB_Disable();
+        }
+    }
+    configured
+}
+
+/// Return true if `IS_KEYBOARD_RIGHT` env variable is set, false otherwise
+#[inline(always)]
+pub const fn side() -> bool {
+    match option_env!("IS_KEYBOARD_RIGHT") {
+        Some(x) => !x.is_empty(),
+        None => false,
+    }
+}
+/// Return true if this half is the left one
+#[inline(always)]
+pub fn is_left() -> bool {
+    unsafe { IS_LEFT }
+}
+/// Return true if this half is the right one
+#[inline(always)]
+pub fn is_right() -> bool {
+    !is_left()
+}
+/// Return true if this half is the master, i.e. the one connected to the computer
+#[inline(always)]
+pub fn is_master() -> bool {
+    unsafe { IS_MASTER }
+}
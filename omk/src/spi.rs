@@ -0,0 +1,94 @@
+//! This module provides functions for SPI communication, the microcontroller being the controller.
+//! Each device has its own chip select pin, low during its transactions.
+//!
+//! Device drivers talk to an [SpiBus], defined in [omk_drivers] so that they are tested on the host.
+
+use avr_base::{
+    pins::{B0, B1, B2, B3, Pin},
+    register::{CPHA, CPOL, DORD, MSTR, SPCR, SPDR, SPE, SPI2X, SPIF, SPSR},
+};
+use avr_delay::delay_us;
+pub use omk_drivers::spi::{SpiBus, SpiConfig, SpiError};
+
+use crate::timer::{timer_elapsed16, timer_read};
+
+/// Timeout of a byte transfer, in milliseconds.
+const SPI_TIMEOUT: u16 = 100;
+
+/// Slave select pin, kept as an output for the SPI to stay the controller.
+const SS_PIN: Pin = B0;
+const SCK_PIN: Pin = B1;
+const MOSI_PIN: Pin = B2;
+const MISO_PIN: Pin = B3;
+
+/// Initializes the SPI pins. Chip selects are set up by [spi_chip_select_init].
+pub fn spi_init() {
+    SS_PIN.gpio_set_pin_output();
+    SS_PIN.gpio_write_pin_high();
+    SCK_PIN.gpio_set_pin_output();
+    MOSI_PIN.gpio_set_pin_output();
+    MISO_PIN.gpio_set_pin_input();
+    SPCR.write(0);
+}
+
+/// Sets a chip select pin up, the device being deselected.
+pub fn spi_chip_select_init(chip_select: Pin) {
+    chip_select.gpio_set_pin_output();
+    chip_select.gpio_write_pin_high();
+}
+
+/// Selects the device on `chip_select`, configuring the SPI for it.
+///
+/// Returns an `SpiError` if the clock divisor isn't supported.
+pub fn spi_start(chip_select: Pin, config: SpiConfig) -> Result<(), SpiError> {
+    // The double speed bit halves the divisor set by SPR1 and SPR0
+    let (spr, double_speed) = match config.divisor {
+        2 => (0, true),
+        4 => (0, false),
+        8 => (1, true),
+        16 => (1, false),
+        32 => (2, true),
+        64 => (2, false),
+        128 => (3, false),
+        _ => return Err(SpiError()),
+    };
+    let mut control = SPE | MSTR | spr;
+    if config.mode & 0b10 != 0 {
+        control |= CPOL;
+    }
+    if config.mode & 0b01 != 0 {
+        control |= CPHA;
+    }
+    if config.lsb_first {
+        control |= DORD;
+    }
+    SPCR.write(control);
+    SPSR.write(if double_speed { SPI2X } else { 0 });
+    chip_select.gpio_write_pin_low();
+    Ok(())
+}
+
+/// Sends a byte, and returns the byte received meanwhile.
+pub fn spi_transfer(data: u8) -> Result<u8, SpiError> {
+    SPDR.write(data);
+    let timeout_timer = timer_read();
+    while SPSR & SPIF == 0 {
+        if timer_elapsed16(timeout_timer) > SPI_TIMEOUT {
+            return Err(SpiError());
+        }
+    }
+    Ok(SPDR.read())
+}
+
+/// Deselects the device on `chip_select`, ending the transaction.
+pub fn spi_stop(chip_select: Pin) {
+    chip_select.gpio_write_pin_high();
+    SPCR.write(0);
+}
+
+/// Waits at least `us` microseconds, interrupts enabled or not.
+pub fn spi_delay_us(us: u16) {
+    for _ in 0..us {
+        delay_us::<1>();
+    }
+}
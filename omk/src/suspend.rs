@@ -0,0 +1,47 @@
+//! This module follows the USB suspend state of the host.
+//! While suspended, the screen is turned off, rendering stops, and the scan is optionally slowed down
+//! with [Keyboard::SUSPEND_SCAN_INTERVAL]. A key press then asks the host to resume, if it allows remote wakeups.
+//! The master learns the state from USB, and forwards it to the slave over the split link.
+
+use crate::{
+    Keyboard, OmkKeyboard,
+    idle::cpu_sleep,
+    is_master,
+    timer::{timer_elapsed, timer_read},
+    usb::is_suspended,
+};
+
+/// Suspend state the keyboard last reacted to.
+static mut HANDLED_SUSPENDED: bool = false;
+/// Time of the last scan while suspended.
+static mut LAST_SUSPENDED_SCAN: u32 = 0;
+
+impl<User: Keyboard> OmkKeyboard<User> {
+    /// Updates the suspend state, and reacts to its changes.
+    ///
+    /// While suspended, it also waits for [Keyboard::SUSPEND_SCAN_INTERVAL] ms since the previous scan.
+    pub(crate) fn suspend_task(&mut self) {
+        if is_master() {
+            self.suspended = is_suspended();
+        }
+
+        if self.suspended != unsafe { HANDLED_SUSPENDED } {
+            unsafe { HANDLED_SUSPENDED = self.suspended };
+            if self.suspended {
+                let _ = Self::oled_off();
+            } else {
+                // Turns the screen back on
+                let _ = Self::render(true);
+            }
+            User::suspend_handler(self, self.suspended);
+        }
+
+        if self.suspended && User::SUSPEND_SCAN_INTERVAL != 0 {
+            while unsafe { timer_elapsed(LAST_SUSPENDED_SCAN) } < User::SUSPEND_SCAN_INTERVAL as u32
+            {
+                cpu_sleep();
+            }
+            unsafe { LAST_SUSPENDED_SCAN = timer_read() };
+        }
+    }
+}
@@ -4,7 +4,8 @@ use crate::atomic::atomic;
 use avr_base::{
     F_CPU,
     register::{
-        CS00, CS01, CS10, OCIE0A, OCR0A, TCCR0A, TCCR0B, TCCR1A, TCCR1B, TCNT1L, TIMSK0, WGM01,
+        CS00, CS01, CS10, OCF0A, OCIE0A, OCR0A, TCCR0A, TCCR0B, TCCR1A, TCCR1B, TCNT0, TCNT1L,
+        TIFR0, TIMSK0, WGM01,
     },
 };
 
@@ -56,6 +57,28 @@ pub fn timer_read() -> u32 {
     atomic(|| unsafe { core::ptr::read_volatile(TIMER.get()) })
 }
 
+/// Reads the current timer value in microseconds, with the resolution of a timer 0 tick (4 us).
+///
+/// It wraps around every 71 minutes, so only differences between two values are meaningful.
+pub fn timer_read_us() -> u32 {
+    atomic(|| {
+        let mut ms = unsafe { core::ptr::read_volatile(TIMER.get()) };
+        let ticks = TCNT0.read();
+        // The counter wrapped, but the interrupt is still pending
+        if TIFR0 & OCF0A != 0 && ticks < TIMER_RAW_TOP / 2 {
+            ms = ms.wrapping_add(1);
+        }
+        ms.wrapping_mul(1000)
+            .wrapping_add(ticks as u32 * 1000 / TIMER_RAW_TOP as u32)
+    })
+}
+
+/// Calculates the time elapsed in microseconds since the last recorded value, saturated to 16 bits.
+#[inline(always)]
+pub fn timer_elapsed_us16(last: u32) -> u16 {
+    timer_read_us().wrapping_sub(last).min(u16::MAX as u32) as u16
+}
+
 /// Calculates the time elapsed in milliseconds since the last recorded value.
 ///
 /// ```rust
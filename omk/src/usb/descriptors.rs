@@ -7,16 +7,22 @@ use crate::progmem::{self, ProgmemPtr};
 use keyboard_macros::progmem;
 
 use lufa_rs::{
-    ENDPOINT_ATTR_NO_SYNC, ENDPOINT_DIR_IN, ENDPOINT_USAGE_DATA, EP_TYPE_INTERRUPT,
-    HidDescriptorClassSubclassProtocol, HidDescriptorTypes, LANGUAGE_ID_ENG, NO_DESCRIPTOR,
-    PackedConcreteType, USB_CONFIG_ATTR_REMOTEWAKEUP, USB_CONFIG_ATTR_RESERVED,
+    ENDPOINT_ATTR_NO_SYNC, ENDPOINT_DIR_IN, ENDPOINT_DIR_OUT, ENDPOINT_USAGE_DATA, EP_TYPE_BULK,
+    EP_TYPE_INTERRUPT, HidDescriptorClassSubclassProtocol, HidDescriptorTypes, LANGUAGE_ID_ENG,
+    NO_DESCRIPTOR, PackedConcreteType, USB_CONFIG_ATTR_REMOTEWAKEUP, USB_CONFIG_ATTR_RESERVED,
     UsbDescriptorClassSubclassProtocol, UsbDescriptorConfigurationHeader, UsbDescriptorDevice,
     UsbDescriptorEndpoint, UsbDescriptorHeader, UsbDescriptorInterface, UsbDescriptorString,
-    UsbDescriptorTypes, UsbHidDescriptorHid, hid_descriptor_keyboard, hid_descriptor_mouse,
-    usb_string_descriptor, usb_string_descriptor_array, version_bcd,
+    UsbDescriptorTypes, UsbHidDescriptorHid, hid_descriptor_keyboard, usb_string_descriptor_array,
+    version_bcd,
 };
 
-use crate::usb::MAX_KEYS;
+pub use lufa_rs::UsbDescriptorDevice;
+
+use crate::{
+    Keyboard, OmkKeyboard,
+    console::{Console, console},
+    usb::MAX_KEYS,
+};
 
 const FIXED_CONTROL_ENDPOINT_SIZE: u8 = 8;
 const FIXED_NUM_CONFIGURATIONS: u8 = 1;
@@ -24,7 +30,11 @@ const FIXED_NUM_CONFIGURATIONS: u8 = 1;
 /// Type define for the device configuration descriptor structure. This must be defined in the
 /// application code, as the configuration descriptor contains several sub-descriptors which
 /// vary between devices, and which describe the device's usage to the host.
-pub struct UsbDescriptorConfiguration {
+///
+/// `Console` holds the interfaces of the debug console, `()` when it is disabled.
+/// `RawHidOut` is the Raw HID OUT endpoint, `()` with the CDC console, which takes its number.
+#[repr(C)]
+pub struct UsbDescriptorConfiguration<Console = (), RawHidOut = UsbDescriptorEndpoint> {
     pub config: UsbDescriptorConfigurationHeader,
 
     /// Keyboard HID Interface
@@ -36,6 +46,79 @@ pub struct UsbDescriptorConfiguration {
     pub hid_mouse_interface: UsbDescriptorInterface,
     pub hid_mouse_hid: UsbHidDescriptorHid,
     pub hid_mouse_report_in_endpoint: UsbDescriptorEndpoint,
+
+    /// Raw HID Interface
+    pub hid_raw_interface: UsbDescriptorInterface,
+    pub hid_raw_hid: UsbHidDescriptorHid,
+    pub hid_raw_report_in_endpoint: UsbDescriptorEndpoint,
+    pub hid_raw_report_out_endpoint: RawHidOut,
+
+    /// Debug console interfaces
+    pub console: Console,
+}
+
+/// Interface Association Descriptor, grouping the interfaces of a function.
+#[repr(C, packed)]
+pub struct UsbDescriptorInterfaceAssociation {
+    pub header: UsbDescriptorHeader,
+    pub first_interface_index: u8,
+    pub total_interfaces: u8,
+    pub class: u8,
+    pub sub_class: u8,
+    pub protocol: u8,
+    pub iad_str_index: u8,
+}
+
+/// CDC class-specific Functional Header Descriptor.
+#[repr(C, packed)]
+pub struct UsbCdcDescriptorFunctionalHeader {
+    pub header: UsbDescriptorHeader,
+    pub subtype: u8,
+    pub cdc_specification: u16,
+}
+
+/// CDC class-specific Functional ACM Descriptor.
+#[repr(C, packed)]
+pub struct UsbCdcDescriptorFunctionalAcm {
+    pub header: UsbDescriptorHeader,
+    pub subtype: u8,
+    pub capabilities: u8,
+}
+
+/// CDC class-specific Functional Union Descriptor.
+#[repr(C, packed)]
+pub struct UsbCdcDescriptorFunctionalUnion {
+    pub header: UsbDescriptorHeader,
+    pub subtype: u8,
+    pub master_interface_number: u8,
+    pub slave_interface_number: u8,
+}
+
+/// Interfaces of the CDC-ACM debug console: a control interface with its notification endpoint,
+/// and a data interface with its bulk endpoints.
+#[repr(C)]
+pub struct CdcConsoleDescriptors {
+    pub association: UsbDescriptorInterfaceAssociation,
+
+    /// CDC Control Interface
+    pub cci_interface: UsbDescriptorInterface,
+    pub cdc_functional_header: UsbCdcDescriptorFunctionalHeader,
+    pub cdc_functional_acm: UsbCdcDescriptorFunctionalAcm,
+    pub cdc_functional_union: UsbCdcDescriptorFunctionalUnion,
+    pub cdc_notification_endpoint: UsbDescriptorEndpoint,
+
+    /// CDC Data Interface
+    pub dci_interface: UsbDescriptorInterface,
+    pub cdc_data_out_endpoint: UsbDescriptorEndpoint,
+    pub cdc_data_in_endpoint: UsbDescriptorEndpoint,
+}
+
+/// Interface of the HID debug console, read by `hid_listen` or `qmk console`.
+#[repr(C)]
+pub struct HidConsoleDescriptors {
+    pub interface: UsbDescriptorInterface,
+    pub hid: UsbHidDescriptorHid,
+    pub report_in_endpoint: UsbDescriptorEndpoint,
 }
 
 /// Enum for the device interface descriptor IDs within the device. Each interface descriptor
@@ -47,6 +130,12 @@ pub(crate) enum InterfaceDescriptors {
     Keyboard = 0,
     /// Mouse interface descriptor ID
     Mouse = 1,
+    /// Raw HID interface descriptor ID
+    RawHid = 2,
+    /// First interface descriptor ID of the debug console: the HID console, or the CDC control interface
+    Console = 3,
+    /// CDC data interface descriptor ID, of the debug console
+    CdcData = 4,
 }
 
 /// Enum for the device string descriptor IDs within the device. Each string descriptor should
@@ -62,6 +151,129 @@ pub enum StringDescriptors {
     KeyboardProduct = 2,
 }
 
+/// String descriptor ID of the serial number, answered by LUFA itself from the signature row of
+/// the microcontroller (`USE_INTERNAL_SERIAL`).
+const INTERNAL_SERIAL_STR_INDEX: u8 = 0xDC;
+
+/// String descriptor, holding `N` UTF-16 code units.
+/// Built at compile time with [usb_string_descriptor!](crate::usb_string_descriptor).
+#[repr(C, packed)]
+pub struct UsbStringDescriptor<const N: usize> {
+    pub header: UsbDescriptorHeader,
+    pub string: [u16; N],
+}
+
+impl<const N: usize> UsbStringDescriptor<N> {
+    /// Encodes `string` in UTF-16, `N` being its [utf16_len].
+    pub const fn new(string: &str) -> Self {
+        assert!(
+            N == utf16_len(string),
+            "N must be the UTF-16 length of the string"
+        );
+        assert!(
+            size_of::<Self>() <= u8::MAX as usize,
+            "USB strings are limited to 126 characters"
+        );
+        let bytes = string.as_bytes();
+        let mut units = [0; N];
+        let (mut i, mut unit) = (0, 0);
+        while i < bytes.len() {
+            // Decode the UTF-8 sequence starting at `i`
+            let (mut code, size) = match bytes[i] {
+                byte if byte < 0x80 => (byte as u32, 1),
+                byte if byte < 0xE0 => ((byte & 0x1F) as u32, 2),
+                byte if byte < 0xF0 => ((byte & 0x0F) as u32, 3),
+                byte => ((byte & 0x07) as u32, 4),
+            };
+            let mut j = 1;
+            while j < size {
+                code = (code << 6) | (bytes[i + j] & 0x3F) as u32;
+                j += 1;
+            }
+            // Characters out of the BMP take a surrogate pair
+            if code >= 0x10000 {
+                code -= 0x10000;
+                units[unit] = 0xD800 | (code >> 10) as u16;
+                units[unit + 1] = 0xDC00 | (code & 0x3FF) as u16;
+                unit += 2;
+            } else {
+                units[unit] = code as u16;
+                unit += 1;
+            }
+            i += size;
+        }
+        Self {
+            header: UsbDescriptorHeader {
+                r#type: UsbDescriptorTypes::String as u8,
+                size: size_of::<Self>() as u8,
+            },
+            string: units,
+        }
+    }
+}
+
+/// Returns the count of UTF-16 code units of `string`, the `N` of its [UsbStringDescriptor].
+pub const fn utf16_len(string: &str) -> usize {
+    let bytes = string.as_bytes();
+    let (mut i, mut len) = (0, 0);
+    while i < bytes.len() {
+        match bytes[i] {
+            // Continuation bytes don't start a character
+            byte if byte & 0xC0 == 0x80 => {}
+            // 4 bytes characters take a surrogate pair
+            byte if byte >= 0xF0 => len += 2,
+            _ => len += 1,
+        }
+        i += 1;
+    }
+    len
+}
+
+/// Builds the [UsbStringDescriptor] of a const string, at compile time.
+///
+/// ```ignore
+/// static PRODUCT: UsbStringDescriptor<{ utf16_len("Keyboard") }> = usb_string_descriptor!("Keyboard");
+/// ```
+#[macro_export]
+macro_rules! usb_string_descriptor {
+    ($string:expr) => {
+        $crate::usb::UsbStringDescriptor::<{ $crate::usb::utf16_len($string) }>::new($string)
+    };
+}
+
+/// Descriptor stored in program memory, with its size.
+#[derive(Clone, Copy)]
+pub struct DescriptorRef {
+    address: ProgmemPtr<()>,
+    size: u16,
+}
+
+impl DescriptorRef {
+    /// `descriptor` must point to program memory.
+    pub const fn new<T>(descriptor: *const T) -> Self {
+        Self {
+            address: ProgmemPtr::new(descriptor.cast()),
+            size: size_of::<T>() as u16,
+        }
+    }
+}
+
+/// Descriptors built from the [Keyboard] consts by `#[entry]`: the identity of the keyboard, and
+/// the configuration holding only the interfaces and reports it enables.
+#[derive(Clone, Copy)]
+pub struct UsbDescriptors {
+    pub device: DescriptorRef,
+    pub configuration: DescriptorRef,
+    pub manufacturer: DescriptorRef,
+    pub product: DescriptorRef,
+    /// HID descriptors of the interfaces, within the configuration
+    pub keyboard_hid: DescriptorRef,
+    pub mouse_hid: DescriptorRef,
+    pub raw_hid_hid: DescriptorRef,
+    /// Report descriptor of the mouse interface, see [mouse_descriptor]
+    pub mouse_report: DescriptorRef,
+}
+
 #[doc = " \\brief Standard HID Boot Protocol Mouse Report.\n\n  Type define for a standard Boot Protocol Mouse report"]
 #[repr(C, packed)]
 #[derive_const(Default)]
@@ -79,6 +291,42 @@ pub struct UsbMouseReportData {
     pub h: i8,
 }
 
+/// HID Absolute Pointer Report, sent through the mouse interface.
+///
+/// Positions go from 0 to [ABSOLUTE_POINTER_MAX] on each axis, spanning the whole screen.
+#[repr(C, packed)]
+#[derive_const(Default)]
+#[derive(Debug, Copy, Clone, PartialOrd, Ord, PartialEq, Eq)]
+pub struct UsbAbsolutePointerReportData {
+    /// Always released, the clicks go through the mouse report
+    pub button: u8,
+    pub x: u16,
+    pub y: u16,
+}
+
+/// HID Gamepad Report, sent through the mouse interface.
+///
+/// Axes go from -127 to 127, in the order of [GamepadAxis].
+#[repr(C, packed)]
+#[derive_const(Default)]
+#[derive(Debug, Copy, Clone, PartialOrd, Ord, PartialEq, Eq)]
+pub struct UsbGamepadReportData {
+    /// Pressed buttons, a bit per button, the first one in the lowest bit
+    pub buttons: u8,
+    pub axes: [i8; 6],
+}
+
+/// Axes of the gamepad report.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GamepadAxis {
+    X,
+    Y,
+    Z,
+    Rx,
+    Ry,
+    Rz,
+}
+
 /// Endpoint address of the Keyboard HID reporting IN endpoint.
 pub const KEYBOARD_IN_ENDPOINT_ADDR: u8 = (ENDPOINT_DIR_IN | 1) as u8;
 
@@ -88,95 +336,465 @@ pub const MOUSE_IN_ENDPOINT_ADDR: u8 = (ENDPOINT_DIR_IN | 3) as u8;
 /// Size in bytes of the Keyboard HID reporting IN endpoint.
 pub const HID_ENDPOINT_SIZE: u8 = 8;
 
-/// Descripteur de périphérique
-#[progmem]
-static DEVICE_DESCRIPTOR: UsbDescriptorDevice = UsbDescriptorDevice {
+/// Wheel units per detent, once the host enabled the high resolution scrolling.
+/// A mouse report holds at most 127 units, nearly 8 detents.
+pub const WHEEL_RESOLUTION_MULTIPLIER: u8 = 16;
+
+/// Report ID of the mouse report, once the absolute pointer or the gamepad shares the mouse interface.
+pub const MOUSE_REPORT_ID: u8 = 1;
+
+/// Report ID of the absolute pointer report.
+pub const ABSOLUTE_POINTER_REPORT_ID: u8 = 2;
+
+/// Report ID of the gamepad report.
+pub const GAMEPAD_REPORT_ID: u8 = 3;
+
+/// Maximum position of the absolute pointer, on both axes, as declared by its report descriptor.
+pub const ABSOLUTE_POINTER_MAX: u16 = 0x7FFF;
+
+/// Endpoint address of the Raw HID reporting IN endpoint.
+pub const RAW_HID_IN_ENDPOINT_ADDR: u8 = (ENDPOINT_DIR_IN | 4) as u8;
+
+/// Endpoint address of the Raw HID reporting OUT endpoint.
+///
+/// The CDC console needs its number, all the endpoints of the microcontroller being used then:
+/// the reports from the host come through the control endpoint instead.
+pub const RAW_HID_OUT_ENDPOINT_ADDR: u8 = (ENDPOINT_DIR_OUT | 5) as u8;
+
+/// Size in bytes of the Raw HID reports, and of their endpoints.
+pub const RAW_HID_REPORT_SIZE: u8 = 32;
+
+/// Endpoint address of the CDC notification IN endpoint.
+pub const CDC_NOTIFICATION_ENDPOINT_ADDR: u8 = (ENDPOINT_DIR_IN | 2) as u8;
+
+/// Endpoint address of the CDC data OUT endpoint, the one of the Raw HID OUT endpoint.
+pub const CDC_RX_ENDPOINT_ADDR: u8 = RAW_HID_OUT_ENDPOINT_ADDR;
+
+/// Endpoint address of the CDC data IN endpoint.
+pub const CDC_TX_ENDPOINT_ADDR: u8 = (ENDPOINT_DIR_IN | 6) as u8;
+
+/// Size in bytes of the CDC notification IN endpoint.
+pub const CDC_NOTIFICATION_ENDPOINT_SIZE: u8 = 8;
+
+/// Size in bytes of the CDC data endpoints.
+pub const CDC_TXRX_ENDPOINT_SIZE: u8 = 32;
+
+/// Endpoint address of the HID console IN endpoint.
+/// It is the one of the CDC data IN endpoint, as both consoles can't be enabled together.
+pub const HID_CONSOLE_IN_ENDPOINT_ADDR: u8 = CDC_TX_ENDPOINT_ADDR;
+
+/// Size in bytes of the HID console reports, and of their endpoint.
+pub const HID_CONSOLE_REPORT_SIZE: u8 = 32;
+
+/// Communication Device Class, of the control interface.
+const CDC_CSCP_CDC_CLASS: u8 = 0x02;
+/// Abstract Control Model subclass, of the control interface.
+const CDC_CSCP_ACM_SUBCLASS: u8 = 0x02;
+/// AT commands protocol, of the control interface.
+const CDC_CSCP_AT_COMMAND_PROTOCOL: u8 = 0x01;
+/// Data class, of the data interface.
+const CDC_CSCP_CDC_DATA_CLASS: u8 = 0x0A;
+/// CDC class-specific interface descriptor type.
+const CDC_DTYPE_CS_INTERFACE: u8 = 0x24;
+/// Functional descriptor subtypes.
+const CDC_DSUBTYPE_CS_INTERFACE_HEADER: u8 = 0x00;
+const CDC_DSUBTYPE_CS_INTERFACE_ACM: u8 = 0x02;
+const CDC_DSUBTYPE_CS_INTERFACE_UNION: u8 = 0x06;
+/// Interface Association Descriptor type.
+const DTYPE_INTERFACE_ASSOCIATION: u8 = 0x0B;
+/// Device class, subclass and protocol announcing Interface Association Descriptors.
+const USB_CSCP_IAD_DEVICE_CLASS: u8 = 0xEF;
+const USB_CSCP_IAD_DEVICE_SUBCLASS: u8 = 0x02;
+const USB_CSCP_IAD_DEVICE_PROTOCOL: u8 = 0x01;
+
+/// Builds the device descriptor, from the identity set in the [Keyboard] consts.
+pub const fn device_descriptor<User: Keyboard>() -> UsbDescriptorDevice {
+    let cdc = matches!(User::CONSOLE, Console::Cdc);
+    UsbDescriptorDevice {
+        header: UsbDescriptorHeader {
+            r#type: UsbDescriptorTypes::Device as u8,
+            size: size_of::<UsbDescriptorDevice>() as u8,
+        },
+        usb_specification: version_bcd(2, 0, 0),
+        // Needed by some hosts to group the interfaces of the CDC console, the class being
+        // given by the interfaces otherwise
+        class: if cdc {
+            USB_CSCP_IAD_DEVICE_CLASS
+        } else {
+            UsbDescriptorClassSubclassProtocol::UsbCscpNoDeviceClass as u8
+        },
+        sub_class: if cdc {
+            USB_CSCP_IAD_DEVICE_SUBCLASS
+        } else {
+            UsbDescriptorClassSubclassProtocol::UsbCscpNoDeviceSubclass as u8
+        },
+        protocol: if cdc {
+            USB_CSCP_IAD_DEVICE_PROTOCOL
+        } else {
+            UsbDescriptorClassSubclassProtocol::UsbCscpNoDeviceProtocol as u8
+        },
+        endpoint0_size: FIXED_CONTROL_ENDPOINT_SIZE,
+        vendor_id: User::USB_VENDOR_ID,
+        product_id: User::USB_PRODUCT_ID,
+        release_number: User::USB_DEVICE_VERSION,
+        manufacturer_str_index: StringDescriptors::Manufacturer as u8,
+        product_str_index: StringDescriptors::KeyboardProduct as u8,
+        serial_num_str_index: if User::USB_SERIAL_NUMBER {
+            INTERNAL_SERIAL_STR_INDEX
+        } else {
+            NO_DESCRIPTOR as u8
+        },
+        number_of_configurations: FIXED_NUM_CONFIGURATIONS,
+    }
+}
+
+/// Interfaces of the debug console in the configuration descriptor, given by [ConsoleLayout].
+pub trait ConfigurationLayout {
+    /// Interfaces of the console, `()` when it is disabled
+    type Console;
+    /// Raw HID OUT endpoint, `()` with the CDC console, which takes its number
+    type RawHidOut;
+    const CONSOLE: Self::Console;
+    const RAW_HID_OUT_ENDPOINT: Self::RawHidOut;
+    const TOTAL_INTERFACES: u8;
+}
+
+/// Layout of the configuration descriptor for a [Console], given as `Console as u8`.
+pub struct ConsoleLayout<const CONSOLE: u8>;
+
+impl ConfigurationLayout for ConsoleLayout<{ Console::Disabled as u8 }> {
+    type Console = ();
+    type RawHidOut = UsbDescriptorEndpoint;
+    const CONSOLE: () = ();
+    const RAW_HID_OUT_ENDPOINT: UsbDescriptorEndpoint = RAW_HID_OUT_ENDPOINT;
+    const TOTAL_INTERFACES: u8 = 3;
+}
+
+impl ConfigurationLayout for ConsoleLayout<{ Console::Cdc as u8 }> {
+    type Console = CdcConsoleDescriptors;
+    type RawHidOut = ();
+    const CONSOLE: CdcConsoleDescriptors = CDC_CONSOLE_DESCRIPTORS;
+    const RAW_HID_OUT_ENDPOINT: () = ();
+    const TOTAL_INTERFACES: u8 = 5;
+}
+
+impl ConfigurationLayout for ConsoleLayout<{ Console::Hid as u8 }> {
+    type Console = HidConsoleDescriptors;
+    type RawHidOut = UsbDescriptorEndpoint;
+    const CONSOLE: HidConsoleDescriptors = HID_CONSOLE_DESCRIPTORS;
+    const RAW_HID_OUT_ENDPOINT: UsbDescriptorEndpoint = RAW_HID_OUT_ENDPOINT;
+    const TOTAL_INTERFACES: u8 = 4;
+}
+
+/// Configuration descriptor of a [ConfigurationLayout].
+pub type ConfigurationDescriptor<Layout> = UsbDescriptorConfiguration<
+    <Layout as ConfigurationLayout>::Console,
+    <Layout as ConfigurationLayout>::RawHidOut,
+>;
+
+/// Builds the configuration descriptor, followed by the interfaces of the console of `Layout`,
+/// which must be `ConsoleLayout<{ User::CONSOLE as u8 }>`.
+/// The mouse report descriptor depends on the absolute pointer and the gamepad, hence its length.
+pub const fn configuration_descriptor<User: Keyboard, Layout: ConfigurationLayout>()
+-> ConfigurationDescriptor<Layout> {
+    UsbDescriptorConfiguration {
+        config: UsbDescriptorConfigurationHeader {
+            header: UsbDescriptorHeader {
+                r#type: UsbDescriptorTypes::Configuration as u8,
+                size: size_of::<UsbDescriptorConfigurationHeader>() as u8,
+            },
+            total_configuration_size: size_of::<ConfigurationDescriptor<Layout>>() as u16,
+            total_interfaces: Layout::TOTAL_INTERFACES,
+            configuration_number: 1,
+            configuration_str_index: NO_DESCRIPTOR as u8,
+            config_attributes: (USB_CONFIG_ATTR_RESERVED | USB_CONFIG_ATTR_REMOTEWAKEUP) as u8,
+            max_power_consumption: 50, // 100 mA (2mA units)
+        },
+        hid_keyboard_interface: UsbDescriptorInterface {
+            header: UsbDescriptorHeader {
+                r#type: UsbDescriptorTypes::Interface as u8,
+                size: size_of::<UsbDescriptorInterface>() as u8,
+            },
+            interface_number: InterfaceDescriptors::Keyboard as u8,
+            alternate_setting: 0x00,
+            total_endpoints: 1,
+            class: HidDescriptorClassSubclassProtocol::HidCscpHidClass as u8,
+            sub_class: HidDescriptorClassSubclassProtocol::HidCscpBootSubclass as u8,
+            protocol: HidDescriptorClassSubclassProtocol::HidCscpKeyboardBootProtocol as u8,
+            interface_str_index: NO_DESCRIPTOR as u8,
+        },
+        hid_keyboard_hid: UsbHidDescriptorHid {
+            header: UsbDescriptorHeader {
+                r#type: HidDescriptorTypes::HidHid as u8,
+                size: size_of::<UsbHidDescriptorHid>() as u8,
+            },
+            hid_spec: version_bcd(1, 1, 1),
+            country_code: 0x00,
+            total_report_descriptors: 1,
+            hid_report_type: HidDescriptorTypes::HidReport as u8,
+            hid_report_length: KEYBOARD_DESCRIPTOR.len() as u16,
+        },
+        hid_report_in_endpoint: UsbDescriptorEndpoint {
+            header: UsbDescriptorHeader {
+                r#type: UsbDescriptorTypes::Endpoint as u8,
+                size: size_of::<UsbDescriptorEndpoint>() as u8,
+            },
+            endpoint_address: KEYBOARD_IN_ENDPOINT_ADDR,
+            attributes: (EP_TYPE_INTERRUPT | ENDPOINT_ATTR_NO_SYNC | ENDPOINT_USAGE_DATA) as u8,
+            endpoint_size: HID_ENDPOINT_SIZE as u16,
+            polling_interval_ms: 0x05,
+        },
+
+        hid_mouse_interface: UsbDescriptorInterface {
+            header: UsbDescriptorHeader {
+                size: size_of::<UsbDescriptorInterface>() as u8,
+                r#type: UsbDescriptorTypes::Interface as u8,
+            },
+
+            interface_number: InterfaceDescriptors::Mouse as u8,
+            alternate_setting: 0x00,
+
+            total_endpoints: 1,
+
+            class: HidDescriptorClassSubclassProtocol::HidCscpHidClass as u8,
+            sub_class: HidDescriptorClassSubclassProtocol::HidCscpBootSubclass as u8,
+            protocol: HidDescriptorClassSubclassProtocol::HidCscpMouseBootProtocol as u8,
+
+            interface_str_index: NO_DESCRIPTOR as u8,
+        },
+
+        hid_mouse_hid: UsbHidDescriptorHid {
+            header: UsbDescriptorHeader {
+                size: size_of::<UsbHidDescriptorHid>() as u8,
+                r#type: HidDescriptorTypes::HidHid as u8,
+            },
+
+            hid_spec: version_bcd(1, 1, 1),
+            country_code: 0x00,
+            total_report_descriptors: 1,
+            hid_report_type: HidDescriptorTypes::HidReport as u8,
+            hid_report_length: mouse_descriptor_size::<User>() as u16,
+        },
+
+        hid_mouse_report_in_endpoint: UsbDescriptorEndpoint {
+            header: UsbDescriptorHeader {
+                size: size_of::<UsbDescriptorEndpoint>() as u8,
+                r#type: UsbDescriptorTypes::Endpoint as u8,
+            },
+
+            endpoint_address: MOUSE_IN_ENDPOINT_ADDR,
+            attributes: (EP_TYPE_INTERRUPT | ENDPOINT_ATTR_NO_SYNC | ENDPOINT_USAGE_DATA) as u8,
+            endpoint_size: HID_ENDPOINT_SIZE as u16,
+            polling_interval_ms: 0x05,
+        },
+
+        hid_raw_interface: UsbDescriptorInterface {
+            header: UsbDescriptorHeader {
+                size: size_of::<UsbDescriptorInterface>() as u8,
+                r#type: UsbDescriptorTypes::Interface as u8,
+            },
+
+            interface_number: InterfaceDescriptors::RawHid as u8,
+            alternate_setting: 0x00,
+
+            // The OUT endpoint is `()` when left out
+            total_endpoints: if size_of::<Layout::RawHidOut>() == 0 {
+                1
+            } else {
+                2
+            },
+
+            class: HidDescriptorClassSubclassProtocol::HidCscpHidClass as u8,
+            sub_class: HidDescriptorClassSubclassProtocol::HidCscpNonBootSubclass as u8,
+            protocol: HidDescriptorClassSubclassProtocol::HidCscpNonBootProtocol as u8,
+
+            interface_str_index: NO_DESCRIPTOR as u8,
+        },
+
+        hid_raw_hid: UsbHidDescriptorHid {
+            header: UsbDescriptorHeader {
+                size: size_of::<UsbHidDescriptorHid>() as u8,
+                r#type: HidDescriptorTypes::HidHid as u8,
+            },
+
+            hid_spec: version_bcd(1, 1, 1),
+            country_code: 0x00,
+            total_report_descriptors: 1,
+            hid_report_type: HidDescriptorTypes::HidReport as u8,
+            hid_report_length: RAW_HID_DESCRIPTOR.len() as u16,
+        },
+
+        hid_raw_report_in_endpoint: UsbDescriptorEndpoint {
+            header: UsbDescriptorHeader {
+                size: size_of::<UsbDescriptorEndpoint>() as u8,
+                r#type: UsbDescriptorTypes::Endpoint as u8,
+            },
+
+            endpoint_address: RAW_HID_IN_ENDPOINT_ADDR,
+            attributes: (EP_TYPE_INTERRUPT | ENDPOINT_ATTR_NO_SYNC | ENDPOINT_USAGE_DATA) as u8,
+            endpoint_size: RAW_HID_REPORT_SIZE as u16,
+            polling_interval_ms: 0x01,
+        },
+        hid_raw_report_out_endpoint: Layout::RAW_HID_OUT_ENDPOINT,
+
+        console: Layout::CONSOLE,
+    }
+}
+
+/// Raw HID OUT endpoint, left out with the CDC console.
+const RAW_HID_OUT_ENDPOINT: UsbDescriptorEndpoint = UsbDescriptorEndpoint {
     header: UsbDescriptorHeader {
-        r#type: UsbDescriptorTypes::Device as u8,
-        size: size_of::<UsbDescriptorDevice>() as u8,
+        size: size_of::<UsbDescriptorEndpoint>() as u8,
+        r#type: UsbDescriptorTypes::Endpoint as u8,
     },
-    usb_specification: version_bcd(2, 0, 0),
-    class: UsbDescriptorClassSubclassProtocol::UsbCscpNoDeviceClass as u8,
-    sub_class: UsbDescriptorClassSubclassProtocol::UsbCscpNoDeviceSubclass as u8,
-    protocol: UsbDescriptorClassSubclassProtocol::UsbCscpNoDeviceProtocol as u8,
-    endpoint0_size: FIXED_CONTROL_ENDPOINT_SIZE,
-    vendor_id: 0xfc32,
-    product_id: 0x0287,
-    release_number: version_bcd(0, 0, 1),
-    manufacturer_str_index: StringDescriptors::Manufacturer as u8,
-    product_str_index: StringDescriptors::KeyboardProduct as u8,
-    serial_num_str_index: NO_DESCRIPTOR as u8,
-    number_of_configurations: FIXED_NUM_CONFIGURATIONS,
+
+    endpoint_address: RAW_HID_OUT_ENDPOINT_ADDR,
+    attributes: (EP_TYPE_INTERRUPT | ENDPOINT_ATTR_NO_SYNC | ENDPOINT_USAGE_DATA) as u8,
+    endpoint_size: RAW_HID_REPORT_SIZE as u16,
+    polling_interval_ms: 0x01,
 };
-// Descripteur de configuration
-#[progmem]
-static CONFIGURATION_DESCRIPTOR: UsbDescriptorConfiguration = UsbDescriptorConfiguration {
-    config: UsbDescriptorConfigurationHeader {
+
+/// Interfaces of the CDC-ACM debug console.
+const CDC_CONSOLE_DESCRIPTORS: CdcConsoleDescriptors = CdcConsoleDescriptors {
+    association: UsbDescriptorInterfaceAssociation {
         header: UsbDescriptorHeader {
-            r#type: UsbDescriptorTypes::Configuration as u8,
-            size: size_of::<UsbDescriptorConfigurationHeader>() as u8,
+            size: size_of::<UsbDescriptorInterfaceAssociation>() as u8,
+            r#type: DTYPE_INTERFACE_ASSOCIATION,
         },
-        total_configuration_size: size_of::<UsbDescriptorConfiguration>() as u16,
+
+        first_interface_index: InterfaceDescriptors::Console as u8,
         total_interfaces: 2,
-        configuration_number: 1,
-        configuration_str_index: NO_DESCRIPTOR as u8,
-        config_attributes: (USB_CONFIG_ATTR_RESERVED | USB_CONFIG_ATTR_REMOTEWAKEUP) as u8,
-        max_power_consumption: 50, // 100 mA (2mA units)
+
+        class: CDC_CSCP_CDC_CLASS,
+        sub_class: CDC_CSCP_ACM_SUBCLASS,
+        protocol: CDC_CSCP_AT_COMMAND_PROTOCOL,
+
+        iad_str_index: NO_DESCRIPTOR as u8,
     },
-    hid_keyboard_interface: UsbDescriptorInterface {
+
+    cci_interface: UsbDescriptorInterface {
         header: UsbDescriptorHeader {
-            r#type: UsbDescriptorTypes::Interface as u8,
             size: size_of::<UsbDescriptorInterface>() as u8,
+            r#type: UsbDescriptorTypes::Interface as u8,
         },
-        interface_number: InterfaceDescriptors::Keyboard as u8,
+
+        interface_number: InterfaceDescriptors::Console as u8,
         alternate_setting: 0x00,
+
         total_endpoints: 1,
-        class: HidDescriptorClassSubclassProtocol::HidCscpHidClass as u8,
-        sub_class: HidDescriptorClassSubclassProtocol::HidCscpBootSubclass as u8,
-        protocol: HidDescriptorClassSubclassProtocol::HidCscpKeyboardBootProtocol as u8,
+
+        class: CDC_CSCP_CDC_CLASS,
+        sub_class: CDC_CSCP_ACM_SUBCLASS,
+        protocol: CDC_CSCP_AT_COMMAND_PROTOCOL,
+
         interface_str_index: NO_DESCRIPTOR as u8,
     },
-    hid_keyboard_hid: UsbHidDescriptorHid {
+
+    cdc_functional_header: UsbCdcDescriptorFunctionalHeader {
         header: UsbDescriptorHeader {
-            r#type: HidDescriptorTypes::HidHid as u8,
-            size: size_of::<UsbHidDescriptorHid>() as u8,
+            size: size_of::<UsbCdcDescriptorFunctionalHeader>() as u8,
+            r#type: CDC_DTYPE_CS_INTERFACE,
         },
-        hid_spec: version_bcd(1, 1, 1),
-        country_code: 0x00,
-        total_report_descriptors: 1,
-        hid_report_type: HidDescriptorTypes::HidReport as u8,
-        hid_report_length: KEYBOARD_DESCRIPTOR.len() as u16,
+        subtype: CDC_DSUBTYPE_CS_INTERFACE_HEADER,
+        cdc_specification: version_bcd(1, 1, 0),
     },
-    hid_report_in_endpoint: UsbDescriptorEndpoint {
+
+    cdc_functional_acm: UsbCdcDescriptorFunctionalAcm {
+        header: UsbDescriptorHeader {
+            size: size_of::<UsbCdcDescriptorFunctionalAcm>() as u8,
+            r#type: CDC_DTYPE_CS_INTERFACE,
+        },
+        subtype: CDC_DSUBTYPE_CS_INTERFACE_ACM,
+        // Supports the line coding and line state requests
+        capabilities: 0x02,
+    },
+
+    cdc_functional_union: UsbCdcDescriptorFunctionalUnion {
+        header: UsbDescriptorHeader {
+            size: size_of::<UsbCdcDescriptorFunctionalUnion>() as u8,
+            r#type: CDC_DTYPE_CS_INTERFACE,
+        },
+        subtype: CDC_DSUBTYPE_CS_INTERFACE_UNION,
+        master_interface_number: InterfaceDescriptors::Console as u8,
+        slave_interface_number: InterfaceDescriptors::CdcData as u8,
+    },
+
+    cdc_notification_endpoint: UsbDescriptorEndpoint {
         header: UsbDescriptorHeader {
-            r#type: UsbDescriptorTypes::Endpoint as u8,
             size: size_of::<UsbDescriptorEndpoint>() as u8,
+            r#type: UsbDescriptorTypes::Endpoint as u8,
         },
-        endpoint_address: KEYBOARD_IN_ENDPOINT_ADDR,
+
+        endpoint_address: CDC_NOTIFICATION_ENDPOINT_ADDR,
         attributes: (EP_TYPE_INTERRUPT | ENDPOINT_ATTR_NO_SYNC | ENDPOINT_USAGE_DATA) as u8,
-        endpoint_size: HID_ENDPOINT_SIZE as u16,
+        endpoint_size: CDC_NOTIFICATION_ENDPOINT_SIZE as u16,
+        polling_interval_ms: 0xFF,
+    },
+
+    dci_interface: UsbDescriptorInterface {
+        header: UsbDescriptorHeader {
+            size: size_of::<UsbDescriptorInterface>() as u8,
+            r#type: UsbDescriptorTypes::Interface as u8,
+        },
+
+        interface_number: InterfaceDescriptors::CdcData as u8,
+        alternate_setting: 0x00,
+
+        total_endpoints: 2,
+
+        class: CDC_CSCP_CDC_DATA_CLASS,
+        sub_class: 0x00,
+        protocol: 0x00,
+
+        interface_str_index: NO_DESCRIPTOR as u8,
+    },
+
+    cdc_data_out_endpoint: UsbDescriptorEndpoint {
+        header: UsbDescriptorHeader {
+            size: size_of::<UsbDescriptorEndpoint>() as u8,
+            r#type: UsbDescriptorTypes::Endpoint as u8,
+        },
+
+        endpoint_address: CDC_RX_ENDPOINT_ADDR,
+        attributes: (EP_TYPE_BULK | ENDPOINT_ATTR_NO_SYNC | ENDPOINT_USAGE_DATA) as u8,
+        endpoint_size: CDC_TXRX_ENDPOINT_SIZE as u16,
         polling_interval_ms: 0x05,
     },
 
-    hid_mouse_interface: UsbDescriptorInterface {
+    cdc_data_in_endpoint: UsbDescriptorEndpoint {
+        header: UsbDescriptorHeader {
+            size: size_of::<UsbDescriptorEndpoint>() as u8,
+            r#type: UsbDescriptorTypes::Endpoint as u8,
+        },
+
+        endpoint_address: CDC_TX_ENDPOINT_ADDR,
+        attributes: (EP_TYPE_BULK | ENDPOINT_ATTR_NO_SYNC | ENDPOINT_USAGE_DATA) as u8,
+        endpoint_size: CDC_TXRX_ENDPOINT_SIZE as u16,
+        polling_interval_ms: 0x05,
+    },
+};
+
+/// Interface of the HID debug console.
+const HID_CONSOLE_DESCRIPTORS: HidConsoleDescriptors = HidConsoleDescriptors {
+    interface: UsbDescriptorInterface {
         header: UsbDescriptorHeader {
             size: size_of::<UsbDescriptorInterface>() as u8,
             r#type: UsbDescriptorTypes::Interface as u8,
         },
 
-        interface_number: InterfaceDescriptors::Mouse as u8,
+        interface_number: InterfaceDescriptors::Console as u8,
         alternate_setting: 0x00,
 
         total_endpoints: 1,
 
         class: HidDescriptorClassSubclassProtocol::HidCscpHidClass as u8,
-        sub_class: HidDescriptorClassSubclassProtocol::HidCscpBootSubclass as u8,
-        protocol: HidDescriptorClassSubclassProtocol::HidCscpMouseBootProtocol as u8,
+        sub_class: HidDescriptorClassSubclassProtocol::HidCscpNonBootSubclass as u8,
+        protocol: HidDescriptorClassSubclassProtocol::HidCscpNonBootProtocol as u8,
 
         interface_str_index: NO_DESCRIPTOR as u8,
     },
 
-    hid_mouse_hid: UsbHidDescriptorHid {
+    hid: UsbHidDescriptorHid {
         header: UsbDescriptorHeader {
             size: size_of::<UsbHidDescriptorHid>() as u8,
             r#type: HidDescriptorTypes::HidHid as u8,
@@ -186,40 +804,32 @@ static CONFIGURATION_DESCRIPTOR: UsbDescriptorConfiguration = UsbDescriptorConfi
         country_code: 0x00,
         total_report_descriptors: 1,
         hid_report_type: HidDescriptorTypes::HidReport as u8,
-        hid_report_length: MOUSE_DESCRIPTOR.len() as u16,
+        hid_report_length: HID_CONSOLE_DESCRIPTOR.len() as u16,
     },
 
-    hid_mouse_report_in_endpoint: UsbDescriptorEndpoint {
+    report_in_endpoint: UsbDescriptorEndpoint {
         header: UsbDescriptorHeader {
             size: size_of::<UsbDescriptorEndpoint>() as u8,
             r#type: UsbDescriptorTypes::Endpoint as u8,
         },
 
-        endpoint_address: MOUSE_IN_ENDPOINT_ADDR,
+        endpoint_address: HID_CONSOLE_IN_ENDPOINT_ADDR,
         attributes: (EP_TYPE_INTERRUPT | ENDPOINT_ATTR_NO_SYNC | ENDPOINT_USAGE_DATA) as u8,
-        endpoint_size: HID_ENDPOINT_SIZE as u16,
-        polling_interval_ms: 0x05,
+        endpoint_size: HID_CONSOLE_REPORT_SIZE as u16,
+        polling_interval_ms: 0x01,
     },
 };
 
-const KEYBOARD_HID: ProgmemPtr<UsbHidDescriptorHid> = unsafe {
-    ProgmemPtr::new(
-        const { &raw const (*CONFIGURATION_DESCRIPTOR.as_ptr().address()).hid_keyboard_hid },
-    )
-};
-
-const MOUSE_HID: ProgmemPtr<UsbHidDescriptorHid> = unsafe {
-    ProgmemPtr::new(
-        const { &raw const (*CONFIGURATION_DESCRIPTOR.as_ptr().address()).hid_mouse_hid },
-    )
-};
+/// HID descriptor of the HID console, within the configuration with [Console::Hid] only.
+#[progmem]
+static HID_CONSOLE_HID: UsbHidDescriptorHid = HID_CONSOLE_DESCRIPTORS.hid;
 
-#[unsafe(no_mangle)]
-/// Callback for retrieving USB descriptors.
+/// Retrieves the USB descriptors, for the `CALLBACK_USB_GetDescriptor` of LUFA generated by `#[entry]`.
 ///
 /// # Safety
 /// `descriptor_address` parameter came from LUFA, which call this function with a pointer which can always be dereferenced
-pub unsafe extern "C" fn CALLBACK_USB_GetDescriptor(
+pub unsafe fn get_descriptor(
+    descriptors: &UsbDescriptors,
     w_value: u16,
     w_index: u16,
     descriptor_address: *mut *const c_void,
@@ -234,12 +844,12 @@ pub unsafe extern "C" fn CALLBACK_USB_GetDescriptor(
 
     match descriptor_type {
         c if c == UsbDescriptorTypes::Device as u8 => {
-            address = DEVICE_DESCRIPTOR.as_ptr().cast();
-            size = DEVICE_DESCRIPTOR.len();
+            address = descriptors.device.address;
+            size = descriptors.device.size as usize;
         }
         c if c == UsbDescriptorTypes::Configuration as u8 => {
-            address = CONFIGURATION_DESCRIPTOR.as_ptr().cast();
-            size = CONFIGURATION_DESCRIPTOR.len();
+            address = descriptors.configuration.address;
+            size = descriptors.configuration.size as usize;
         }
         c if c == UsbDescriptorTypes::String as u8 => match descriptor_number {
             code if code == StringDescriptors::Language as u8 => {
@@ -247,25 +857,35 @@ pub unsafe extern "C" fn CALLBACK_USB_GetDescriptor(
                 size = LANGUAGE_STRING.len();
             }
             code if code == StringDescriptors::Manufacturer as u8 => {
-                address = MANUFACTURER_STRING.as_ptr().cast();
-                size = MANUFACTURER_STRING.len();
+                address = descriptors.manufacturer.address;
+                size = descriptors.manufacturer.size as usize;
             }
             code if code == StringDescriptors::KeyboardProduct as u8 => {
-                address = KEYBOARD_PRODUCT_STRING.as_ptr().cast();
-                size = KEYBOARD_PRODUCT_STRING.len();
+                address = descriptors.product.address;
+                size = descriptors.product.size as usize;
             }
-            _ => panic!(),
+            // Unknown descriptors are answered with a STALL
+            _ => return NO_DESCRIPTOR as u16,
         },
         c if c == HidDescriptorTypes::HidHid as u8 => match interface_number {
             c if c == InterfaceDescriptors::Keyboard as u8 => {
-                address = KEYBOARD_HID.cast();
-                size = KEYBOARD_HID.len();
+                address = descriptors.keyboard_hid.address;
+                size = descriptors.keyboard_hid.size as usize;
             }
             c if c == InterfaceDescriptors::Mouse as u8 => {
-                address = MOUSE_HID.cast();
-                size = MOUSE_HID.len();
+                address = descriptors.mouse_hid.address;
+                size = descriptors.mouse_hid.size as usize;
+            }
+            c if c == InterfaceDescriptors::RawHid as u8 => {
+                address = descriptors.raw_hid_hid.address;
+                size = descriptors.raw_hid_hid.size as usize;
             }
-            _ => panic!(),
+            c if c == InterfaceDescriptors::Console as u8 && console() == Console::Hid => {
+                address = HID_CONSOLE_HID.as_ptr().cast();
+                size = HID_CONSOLE_HID.len();
+            }
+            // Unknown descriptors are answered with a STALL
+            _ => return NO_DESCRIPTOR as u16,
         },
         c if c == HidDescriptorTypes::HidReport as u8 => match interface_number {
             c if c == InterfaceDescriptors::Keyboard as u8 => {
@@ -273,10 +893,19 @@ pub unsafe extern "C" fn CALLBACK_USB_GetDescriptor(
                 size = KEYBOARD_DESCRIPTOR.len();
             }
             c if c == InterfaceDescriptors::Mouse as u8 => {
-                address = MOUSE_DESCRIPTOR.as_ptr().cast();
-                size = MOUSE_DESCRIPTOR.len();
+                address = descriptors.mouse_report.address;
+                size = descriptors.mouse_report.size as usize;
+            }
+            c if c == InterfaceDescriptors::RawHid as u8 => {
+                address = RAW_HID_DESCRIPTOR.as_ptr().cast();
+                size = RAW_HID_DESCRIPTOR.len();
             }
-            _ => panic!(),
+            c if c == InterfaceDescriptors::Console as u8 && console() == Console::Hid => {
+                address = HID_CONSOLE_DESCRIPTOR.as_ptr().cast();
+                size = HID_CONSOLE_DESCRIPTOR.len();
+            }
+            // Unknown descriptors are answered with a STALL
+            _ => return NO_DESCRIPTOR as u16,
         },
         _ => {
             address = ProgmemPtr::new(null());
@@ -295,24 +924,245 @@ pub unsafe extern "C" fn CALLBACK_USB_GetDescriptor(
 static LANGUAGE_STRING: PackedConcreteType<UsbDescriptorString, i16, 1> =
     usb_string_descriptor_array!([LANGUAGE_ID_ENG as i16]);
 
-/// Manufacturer descriptor string.
-///
-/// This string contains the manufacturer's details in human-readable form.
+/// HID report descriptor for the keyboard.
 #[progmem]
-static MANUFACTURER_STRING: PackedConcreteType<UsbDescriptorString, i16, 14> =
-    usb_string_descriptor!("Surv&madcodder");
+pub static KEYBOARD_DESCRIPTOR: [u8; 64] = hid_descriptor_keyboard!(MAX_KEYS);
 
-/// Product descriptor string.
+/// Report descriptor of the mouse report.
 ///
-/// This string contains the product's details in human-readable form.
-#[progmem]
-static KEYBOARD_PRODUCT_STRING: PackedConcreteType<UsbDescriptorString, i16, 21> =
-    usb_string_descriptor!("Rust Keyboard & Mouse");
+/// Boot protocol compatible report of [UsbMouseReportData]: 8 buttons, X, Y, then the vertical
+/// and horizontal wheels. A feature report holds the Resolution Multiplier of each wheel, that
+/// hosts supporting high resolution scrolling set to get [WHEEL_RESOLUTION_MULTIPLIER] units per detent.
+#[rustfmt::skip]
+const MOUSE_REPORT_DESCRIPTOR: [u8; 107] = [
+    0x05, 0x01,                        // Usage Page (Generic Desktop)
+    0x09, 0x02,                        // Usage (Mouse)
+    0xA1, 0x01,                        // Collection (Application)
+    0x09, 0x01,                        //   Usage (Pointer)
+    0xA1, 0x00,                        //   Collection (Physical)
+    0x05, 0x09,                        //     Usage Page (Button)
+    0x19, 0x01,                        //     Usage Minimum (1)
+    0x29, 0x08,                        //     Usage Maximum (8)
+    0x15, 0x00,                        //     Logical Minimum (0)
+    0x25, 0x01,                        //     Logical Maximum (1)
+    0x95, 0x08,                        //     Report Count (8)
+    0x75, 0x01,                        //     Report Size (1)
+    0x81, 0x02,                        //     Input (Data, Variable, Absolute)
+    0x05, 0x01,                        //     Usage Page (Generic Desktop)
+    0x09, 0x30,                        //     Usage (X)
+    0x09, 0x31,                        //     Usage (Y)
+    0x15, 0x81,                        //     Logical Minimum (-127)
+    0x25, 0x7F,                        //     Logical Maximum (127)
+    0x95, 0x02,                        //     Report Count (2)
+    0x75, 0x08,                        //     Report Size (8)
+    0x81, 0x06,                        //     Input (Data, Variable, Relative)
+    0xA1, 0x02,                        //     Collection (Logical)
+    0x09, 0x48,                        //       Usage (Resolution Multiplier)
+    0x15, 0x00,                        //       Logical Minimum (0)
+    0x25, 0x01,                        //       Logical Maximum (1)
+    0x35, 0x01,                        //       Physical Minimum (1)
+    0x45, WHEEL_RESOLUTION_MULTIPLIER, //       Physical Maximum
+    0x75, 0x02,                        //       Report Size (2)
+    0x95, 0x01,                        //       Report Count (1)
+    0xA4,                              //       Push
+    0xB1, 0x02,                        //       Feature (Data, Variable, Absolute)
+    0x09, 0x38,                        //       Usage (Wheel)
+    0x15, 0x81,                        //       Logical Minimum (-127)
+    0x25, 0x7F,                        //       Logical Maximum (127)
+    0x35, 0x00,                        //       Physical Minimum (0)
+    0x45, 0x00,                        //       Physical Maximum (0)
+    0x75, 0x08,                        //       Report Size (8)
+    0x81, 0x06,                        //       Input (Data, Variable, Relative)
+    0xC0,                              //     End Collection
+    0xA1, 0x02,                        //     Collection (Logical)
+    0x09, 0x48,                        //       Usage (Resolution Multiplier)
+    0xB4,                              //       Pop
+    0xB1, 0x02,                        //       Feature (Data, Variable, Absolute)
+    0x35, 0x00,                        //       Physical Minimum (0)
+    0x45, 0x00,                        //       Physical Maximum (0)
+    0x75, 0x04,                        //       Report Size (4)
+    0xB1, 0x03,                        //       Feature (Constant), padding
+    0x05, 0x0C,                        //       Usage Page (Consumer)
+    0x0A, 0x38, 0x02,                  //       Usage (AC Pan)
+    0x15, 0x81,                        //       Logical Minimum (-127)
+    0x25, 0x7F,                        //       Logical Maximum (127)
+    0x75, 0x08,                        //       Report Size (8)
+    0x81, 0x06,                        //       Input (Data, Variable, Relative)
+    0xC0,                              //     End Collection
+    0xC0,                              //   End Collection
+    0xC0,                              // End Collection
+];
 
-/// HID report descriptor for the keyboard.
+/// Absolute mouse collection of the [mouse_descriptor], with [Keyboard::ABSOLUTE_POINTER].
+///
+/// The buttons are there for the hosts to take it for a mouse.
+#[rustfmt::skip]
+const ABSOLUTE_POINTER_REPORT_DESCRIPTOR: [u8; 47] = [
+    0x05, 0x01,                       // Usage Page (Generic Desktop)
+    0x09, 0x02,                       // Usage (Mouse)
+    0xA1, 0x01,                       // Collection (Application)
+    0x85, ABSOLUTE_POINTER_REPORT_ID, //   Report ID
+    0x09, 0x01,                       //   Usage (Pointer)
+    0xA1, 0x00,                       //   Collection (Physical)
+    0x05, 0x09,                       //     Usage Page (Button)
+    0x19, 0x01,                       //     Usage Minimum (1)
+    0x29, 0x08,                       //     Usage Maximum (8)
+    0x15, 0x00,                       //     Logical Minimum (0)
+    0x25, 0x01,                       //     Logical Maximum (1)
+    0x95, 0x08,                       //     Report Count (8)
+    0x75, 0x01,                       //     Report Size (1)
+    0x81, 0x02,                       //     Input (Data, Variable, Absolute)
+    0x05, 0x01,                       //     Usage Page (Generic Desktop)
+    0x09, 0x30,                       //     Usage (X)
+    0x09, 0x31,                       //     Usage (Y)
+    0x15, 0x00,                       //     Logical Minimum (0)
+    0x26, 0xFF, 0x7F,                 //     Logical Maximum (32767)
+    0x95, 0x02,                       //     Report Count (2)
+    0x75, 0x10,                       //     Report Size (16)
+    0x81, 0x02,                       //     Input (Data, Variable, Absolute)
+    0xC0,                             //   End Collection
+    0xC0,                             // End Collection
+];
+
+/// Gamepad collection of the [mouse_descriptor], with the gamepad.
+#[rustfmt::skip]
+const GAMEPAD_REPORT_DESCRIPTOR: [u8; 49] = [
+    0x05, 0x01,              // Usage Page (Generic Desktop)
+    0x09, 0x05,              // Usage (Game Pad)
+    0xA1, 0x01,              // Collection (Application)
+    0x85, GAMEPAD_REPORT_ID, //   Report ID
+    0x05, 0x09,              //   Usage Page (Button)
+    0x19, 0x01,              //   Usage Minimum (1)
+    0x29, 0x08,              //   Usage Maximum (8)
+    0x15, 0x00,              //   Logical Minimum (0)
+    0x25, 0x01,              //   Logical Maximum (1)
+    0x95, 0x08,              //   Report Count (8)
+    0x75, 0x01,              //   Report Size (1)
+    0x81, 0x02,              //   Input (Data, Variable, Absolute)
+    0x05, 0x01,              //   Usage Page (Generic Desktop)
+    0x09, 0x30,              //   Usage (X)
+    0x09, 0x31,              //   Usage (Y)
+    0x09, 0x32,              //   Usage (Z)
+    0x09, 0x33,              //   Usage (Rx)
+    0x09, 0x34,              //   Usage (Ry)
+    0x09, 0x35,              //   Usage (Rz)
+    0x15, 0x81,              //   Logical Minimum (-127)
+    0x25, 0x7F,              //   Logical Maximum (127)
+    0x95, 0x06,              //   Report Count (6)
+    0x75, 0x08,              //   Report Size (8)
+    0x81, 0x02,              //   Input (Data, Variable, Absolute)
+    0xC0,                    // End Collection
+];
+
+/// Size of the report descriptor of the mouse interface, see [mouse_descriptor].
+pub const fn mouse_descriptor_size<User: Keyboard>() -> usize {
+    let absolute = User::ABSOLUTE_POINTER;
+    let gamepad = OmkKeyboard::<User>::gamepad_present();
+    let mut size = MOUSE_REPORT_DESCRIPTOR.len();
+    if absolute || gamepad {
+        // Report ID item of the mouse report
+        size += 2;
+    }
+    if absolute {
+        size += ABSOLUTE_POINTER_REPORT_DESCRIPTOR.len();
+    }
+    if gamepad {
+        size += GAMEPAD_REPORT_DESCRIPTOR.len();
+    }
+    size
+}
+
+/// Builds the report descriptor of the mouse interface, `SIZE` being its [mouse_descriptor_size].
+///
+/// It holds the mouse report, then with [Keyboard::ABSOLUTE_POINTER] an absolute mouse report of
+/// [UsbAbsolutePointerReportData], and with [OmkKeyboard::gamepad_present] a report of
+/// [UsbGamepadReportData].
+/// The reports are then numbered [MOUSE_REPORT_ID], [ABSOLUTE_POINTER_REPORT_ID] and [GAMEPAD_REPORT_ID].
+pub const fn mouse_descriptor<User: Keyboard, const SIZE: usize>() -> [u8; SIZE] {
+    // The Report ID item goes right after the Collection (Application) item
+    const REPORT_ID_START: usize = 6;
+    assert!(
+        SIZE == mouse_descriptor_size::<User>(),
+        "SIZE must be the mouse_descriptor_size"
+    );
+    let absolute = User::ABSOLUTE_POINTER;
+    let gamepad = OmkKeyboard::<User>::gamepad_present();
+    let numbered = absolute || gamepad;
+    let mut descriptor = [0; SIZE];
+    let mut i = 0;
+    while i < MOUSE_REPORT_DESCRIPTOR.len() {
+        let shift = if numbered && i >= REPORT_ID_START {
+            2
+        } else {
+            0
+        };
+        descriptor[i + shift] = MOUSE_REPORT_DESCRIPTOR[i];
+        i += 1;
+    }
+    if !numbered {
+        return descriptor;
+    }
+    descriptor[REPORT_ID_START] = 0x85;
+    descriptor[REPORT_ID_START + 1] = MOUSE_REPORT_ID;
+    let mut end = MOUSE_REPORT_DESCRIPTOR.len() + 2;
+    if absolute {
+        let mut i = 0;
+        while i < ABSOLUTE_POINTER_REPORT_DESCRIPTOR.len() {
+            descriptor[end + i] = ABSOLUTE_POINTER_REPORT_DESCRIPTOR[i];
+            i += 1;
+        }
+        end += ABSOLUTE_POINTER_REPORT_DESCRIPTOR.len();
+    }
+    if gamepad {
+        let mut i = 0;
+        while i < GAMEPAD_REPORT_DESCRIPTOR.len() {
+            descriptor[end + i] = GAMEPAD_REPORT_DESCRIPTOR[i];
+            i += 1;
+        }
+    }
+    descriptor
+}
+
+/// HID report descriptor for the Raw HID interface.
+///
+/// Vendor defined usage page 0xFF60 and usage 0x61, with one input and one output report of
+/// `RAW_HID_REPORT_SIZE` bytes, as expected by the host tools.
 #[progmem]
-pub static KEYBOARD_DESCRIPTOR: [u8; 64] = hid_descriptor_keyboard!(MAX_KEYS);
+#[rustfmt::skip]
+pub static RAW_HID_DESCRIPTOR: [u8; 34] = [
+    0x06, 0x60, 0xFF,          // Usage Page (Vendor Defined 0xFF60)
+    0x09, 0x61,                // Usage (0x61)
+    0xA1, 0x01,                // Collection (Application)
+    0x09, 0x62,                //   Usage (0x62)
+    0x15, 0x00,                //   Logical Minimum (0)
+    0x26, 0xFF, 0x00,          //   Logical Maximum (255)
+    0x95, RAW_HID_REPORT_SIZE, //   Report Count
+    0x75, 0x08,                //   Report Size (8)
+    0x81, 0x02,                //   Input (Data, Variable, Absolute)
+    0x09, 0x63,                //   Usage (0x63)
+    0x15, 0x00,                //   Logical Minimum (0)
+    0x26, 0xFF, 0x00,          //   Logical Maximum (255)
+    0x95, RAW_HID_REPORT_SIZE, //   Report Count
+    0x75, 0x08,                //   Report Size (8)
+    0x91, 0x02,                //   Output (Data, Variable, Absolute)
+    0xC0,                      // End Collection
+];
 
-/// HID report descriptor for the mouse.
+/// HID report descriptor for the HID console interface.
+///
+/// Usage page 0xFF31 and usage 0x74, with one input report of `HID_CONSOLE_REPORT_SIZE` bytes,
+/// as expected by `hid_listen` and `qmk console`.
 #[progmem]
-pub static MOUSE_DESCRIPTOR: [u8; 118] = hid_descriptor_mouse!();
+#[rustfmt::skip]
+pub static HID_CONSOLE_DESCRIPTOR: [u8; 21] = [
+    0x06, 0x31, 0xFF,              // Usage Page (Vendor Defined 0xFF31)
+    0x09, 0x74,                    // Usage (0x74)
+    0xA1, 0x01,                    // Collection (Application)
+    0x09, 0x75,                    //   Usage (0x75)
+    0x15, 0x00,                    //   Logical Minimum (0)
+    0x26, 0xFF, 0x00,              //   Logical Maximum (255)
+    0x95, HID_CONSOLE_REPORT_SIZE, //   Report Count
+    0x75, 0x08,                    //   Report Size (8)
+    0x81, 0x02,                    //   Input (Data, Variable, Absolute)
+    0xC0,                          // End Collection
+];
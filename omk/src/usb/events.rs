@@ -1,47 +1,172 @@
 //! This module handles USB events and HID report management.
 //! It includes event handlers for USB state changes and functions for managing keyboard and mouse reports.
 
-use core::{ffi::c_void, ptr::null_mut};
+use core::{
+    ffi::c_void,
+    ptr::{copy_nonoverlapping, null_mut},
+};
 
 use lufa_rs::{
-    EP_TYPE_INTERRUPT, Endpoint_ClearIN, Endpoint_ClearOUT, Endpoint_ClearSETUP,
+    EP_TYPE_BULK, EP_TYPE_INTERRUPT, Endpoint_ClearIN, Endpoint_ClearOUT, Endpoint_ClearSETUP,
     Endpoint_ClearStatusStage, Endpoint_ConfigureEndpoint, Endpoint_IsOUTReceived,
-    Endpoint_IsReadWriteAllowed, Endpoint_SelectEndpoint, Endpoint_Write_8,
+    Endpoint_IsReadWriteAllowed, Endpoint_Read_8, Endpoint_Read_Control_Stream_LE,
+    Endpoint_Read_Stream_LE, Endpoint_SelectEndpoint, Endpoint_Write_8,
     Endpoint_Write_Control_Stream_LE, Endpoint_Write_Stream_LE, HidClassRequests,
-    REQDIR_DEVICETOHOST, REQREC_INTERFACE, REQTYPE_CLASS, USB_CONTROL_REQUEST, USB_DEVICE_STATE,
-    USB_Device_EnableSOFEvents, UsbDeviceStates, UsbKeyboardReportData,
+    REQDIR_DEVICETOHOST, REQDIR_HOSTTODEVICE, REQREC_INTERFACE, REQTYPE_CLASS, USB_CONTROL_REQUEST,
+    USB_DEVICE_REMOTE_WAKEUP_ENABLED, USB_DEVICE_STATE, USB_Device_EnableSOFEvents,
+    USB_Device_SendRemoteWakeup, UsbDeviceStates, UsbKeyboardReportData,
 };
 
-use crate::usb::{
-    MAX_KEYS,
-    descriptors::{
-        HID_ENDPOINT_SIZE, InterfaceDescriptors, KEYBOARD_IN_ENDPOINT_ADDR, MOUSE_IN_ENDPOINT_ADDR,
-        UsbMouseReportData,
+use crate::{
+    console::{Console, cdc_control_request, console},
+    profiling,
+    usb::{
+        MAX_KEYS,
+        descriptors::{
+            ABSOLUTE_POINTER_MAX, ABSOLUTE_POINTER_REPORT_ID, CDC_NOTIFICATION_ENDPOINT_ADDR,
+            CDC_NOTIFICATION_ENDPOINT_SIZE, CDC_RX_ENDPOINT_ADDR, CDC_TX_ENDPOINT_ADDR,
+            CDC_TXRX_ENDPOINT_SIZE, GAMEPAD_REPORT_ID, GamepadAxis, HID_CONSOLE_IN_ENDPOINT_ADDR,
+            HID_CONSOLE_REPORT_SIZE, HID_ENDPOINT_SIZE, InterfaceDescriptors,
+            KEYBOARD_IN_ENDPOINT_ADDR, MOUSE_IN_ENDPOINT_ADDR, MOUSE_REPORT_ID,
+            RAW_HID_IN_ENDPOINT_ADDR, RAW_HID_OUT_ENDPOINT_ADDR, RAW_HID_REPORT_SIZE,
+            UsbAbsolutePointerReportData, UsbGamepadReportData, UsbMouseReportData,
+            WHEEL_RESOLUTION_MULTIPLIER,
+        },
     },
 };
 
-/// Indicates what report mode the host has requested, `true` for normal HID
+/// Number of HID interfaces, indexed by [InterfaceDescriptors], the last one being the HID console.
+const HID_INTERFACE_COUNT: usize = 4;
+
+/// HID report type of input reports, in the high byte of the `wValue` of GET_REPORT.
+const HID_REPORT_ITEM_IN: u8 = 1;
+/// HID report type of output reports, in the high byte of the `wValue` of SET_REPORT.
+const HID_REPORT_ITEM_OUT: u8 = 2;
+/// HID report type of feature reports, in the high byte of the `wValue` of GET_REPORT and SET_REPORT.
+const HID_REPORT_ITEM_FEATURE: u8 = 3;
+
+/// Size of the boot protocol mouse report: buttons, then X and Y.
+const BOOT_MOUSE_REPORT_SIZE: u16 = 3;
+
+/// Number of tries waiting for the Raw HID IN endpoint to be ready, before dropping a report.
+const RAW_HID_SEND_TRIES: u16 = 1000;
+
+/// Indicates what report mode the host has requested for each interface, `true` for normal HID
 /// reporting mode, `false` for special boot protocol reporting mode.
-static mut USING_REPORT_PROTOCOL: bool = true;
+static mut USING_REPORT_PROTOCOL: [bool; HID_INTERFACE_COUNT] = [true; HID_INTERFACE_COUNT];
 
-/// Current Idle period. This is set by the host via a Set Idle HID class
-/// request to silence the device's reports for either the entire idle duration,
+/// Current Idle period of each interface, in milliseconds. This is set by the host via a Set Idle
+/// HID class request to silence the device's reports for either the entire idle duration,
 /// or until the report status changes (e.g. the user presses a key).
-static mut IDLE_COUNT: u16 = 0;
+static mut IDLE_COUNT: [u16; HID_INTERFACE_COUNT] = [0; HID_INTERFACE_COUNT];
 
-/// Current Idle period remaining. When the IDLE_COUNT value is set, this tracks
+/// Current Idle period remaining of each interface. When the IDLE_COUNT value is set, this tracks
 /// the remaining number of idle milliseconds. This is separate to the IDLE_COUNT
 /// timer and is incremented and compared as the host may request the current
 /// idle period via a Get Idle HID class request, thus its value must be
 /// preserved.
-static mut IDLE_MS_REMAINING: u16 = 0;
+static mut IDLE_MS_REMAINING: [u16; HID_INTERFACE_COUNT] = [0; HID_INTERFACE_COUNT];
+
+/// Resolution Multiplier feature report of the mouse, set by hosts supporting high resolution
+/// scrolling: 2 bits for the vertical wheel, then 2 bits for the horizontal one.
+static mut MOUSE_RESOLUTION_MULTIPLIER: u8 = 0;
+
+/// Set when the absolute pointer shares the mouse interface, numbering its reports.
+static mut ABSOLUTE_POINTER: bool = false;
+
+/// Set when the gamepad shares the mouse interface, numbering its reports.
+static mut GAMEPAD: bool = false;
+
+/// LEDs state of the host (Num Lock, Caps Lock, ...), from the keyboard output report.
+static mut KEYBOARD_LEDS: u8 = 0;
+
+/// Last Raw HID report received from the host through the control endpoint.
+static mut RAW_HID_RECEIVED: [u8; RAW_HID_REPORT_SIZE as usize] = [0; RAW_HID_REPORT_SIZE as usize];
+/// Set while `RAW_HID_RECEIVED` hasn't been read.
+static mut RAW_HID_PENDING: bool = false;
+
+/// Set while the host has suspended the bus.
+static mut SUSPENDED: bool = false;
 
 /// Event handler for the USB_Connect event.
 ///
 /// This function is called when the USB device is connected and begins enumeration.
 #[unsafe(no_mangle)]
 pub extern "C" fn EVENT_USB_Device_Connect() {
-    unsafe { USING_REPORT_PROTOCOL = true };
+    unsafe { USING_REPORT_PROTOCOL = [true; HID_INTERFACE_COUNT] };
+}
+
+/// Event handler for the USB_Suspend event.
+///
+/// This function is called when the host stops sending frames, e.g. when the computer goes to sleep.
+#[unsafe(no_mangle)]
+pub extern "C" fn EVENT_USB_Device_Suspend() {
+    unsafe { SUSPENDED = true };
+}
+
+/// Event handler for the USB_WakeUp event.
+///
+/// This function is called when the bus activity resumes, from the host or after a remote wakeup.
+#[unsafe(no_mangle)]
+pub extern "C" fn EVENT_USB_Device_WakeUp() {
+    unsafe { SUSPENDED = false };
+}
+
+/// Event handler for the USB_Reset event.
+///
+/// This function is called when the host resets the bus, which also ends a suspend.
+#[unsafe(no_mangle)]
+pub extern "C" fn EVENT_USB_Device_Reset() {
+    unsafe { SUSPENDED = false };
+    // Hosts not supporting high resolution scrolling never set it
+    unsafe { MOUSE_RESOLUTION_MULTIPLIER = 0 };
+}
+
+/// Adds the absolute pointer to the mouse interface. Called at boot, before USB is initialized.
+pub(crate) fn absolute_pointer_init(enabled: bool) {
+    unsafe { ABSOLUTE_POINTER = enabled };
+}
+
+/// Returns true if the mouse interface has the absolute pointer.
+#[inline(always)]
+pub fn absolute_pointer_enabled() -> bool {
+    unsafe { ABSOLUTE_POINTER }
+}
+
+/// Adds the gamepad to the mouse interface. Called at boot, before USB is initialized.
+pub(crate) fn gamepad_init(enabled: bool) {
+    unsafe { GAMEPAD = enabled };
+}
+
+/// Returns true if the mouse interface has the gamepad.
+#[inline(always)]
+pub fn gamepad_enabled() -> bool {
+    unsafe { GAMEPAD }
+}
+
+/// Returns true if other reports share the mouse interface, the reports then starting with their ID.
+#[inline(always)]
+fn mouse_interface_numbered() -> bool {
+    absolute_pointer_enabled() || gamepad_enabled()
+}
+
+/// Returns true if the host has suspended the bus.
+pub fn is_suspended() -> bool {
+    unsafe { SUSPENDED }
+}
+
+/// Asks the host to resume the bus, if it is suspended and the host enabled remote wakeups.
+///
+/// Returns `true` if the wakeup was signaled.
+pub fn send_remote_wakeup() -> bool {
+    unsafe {
+        if SUSPENDED && USB_DEVICE_REMOTE_WAKEUP_ENABLED {
+            USB_Device_SendRemoteWakeup();
+            true
+        } else {
+            false
+        }
+    }
 }
 
 /// Event handler for the USB_ConfigurationChanged event.
@@ -65,6 +190,48 @@ pub extern "C" fn EVENT_USB_Device_ConfigurationChanged() {
             HID_ENDPOINT_SIZE as u16,
             1,
         );
+        config_success &= Endpoint_ConfigureEndpoint(
+            RAW_HID_IN_ENDPOINT_ADDR,
+            EP_TYPE_INTERRUPT as u8,
+            RAW_HID_REPORT_SIZE as u16,
+            1,
+        );
+        if console() != Console::Cdc {
+            config_success &= Endpoint_ConfigureEndpoint(
+                RAW_HID_OUT_ENDPOINT_ADDR,
+                EP_TYPE_INTERRUPT as u8,
+                RAW_HID_REPORT_SIZE as u16,
+                1,
+            );
+        }
+        if console() == Console::Hid {
+            config_success &= Endpoint_ConfigureEndpoint(
+                HID_CONSOLE_IN_ENDPOINT_ADDR,
+                EP_TYPE_INTERRUPT as u8,
+                HID_CONSOLE_REPORT_SIZE as u16,
+                1,
+            );
+        }
+        if console() == Console::Cdc {
+            config_success &= Endpoint_ConfigureEndpoint(
+                CDC_NOTIFICATION_ENDPOINT_ADDR,
+                EP_TYPE_INTERRUPT as u8,
+                CDC_NOTIFICATION_ENDPOINT_SIZE as u16,
+                1,
+            );
+            config_success &= Endpoint_ConfigureEndpoint(
+                CDC_TX_ENDPOINT_ADDR,
+                EP_TYPE_BULK as u8,
+                CDC_TXRX_ENDPOINT_SIZE as u16,
+                1,
+            );
+            config_success &= Endpoint_ConfigureEndpoint(
+                CDC_RX_ENDPOINT_ADDR,
+                EP_TYPE_BULK as u8,
+                CDC_TXRX_ENDPOINT_SIZE as u16,
+                1,
+            );
+        }
     }
 
     // Turn on Start-of-Frame events for tracking HID report period expiry
@@ -81,88 +248,312 @@ pub extern "C" fn EVENT_USB_Device_ConfigurationChanged() {
 #[unsafe(no_mangle)]
 pub extern "C" fn EVENT_USB_Device_StartOfFrame() {
     unsafe {
-        // One millisecond has elapsed, decrement the idle time remaining counter if
-        // it has not already elapsed
-        IDLE_MS_REMAINING = IDLE_MS_REMAINING.saturating_sub(1);
+        // One millisecond has elapsed, decrement the idle time remaining counters if
+        // they have not already elapsed
+        for remaining in IDLE_MS_REMAINING.iter_mut() {
+            *remaining = remaining.saturating_sub(1);
+        }
     }
 }
 
 /// Event handler for USB control requests.
 ///
 /// This function processes HID class requests from the USB host.
+/// Unsupported requests are left with their SETUP packet pending, which LUFA answers with a STALL.
 #[unsafe(no_mangle)]
 pub extern "C" fn EVENT_USB_Device_ControlRequest() {
-    unsafe {
-        if USB_CONTROL_REQUEST.bm_request_type
-            == (REQDIR_DEVICETOHOST | REQTYPE_CLASS | REQREC_INTERFACE) as u8
-        {
-            match USB_CONTROL_REQUEST.b_request {
-                code if code == HidClassRequests::HidReqGetReport as u8 => {
-                    Endpoint_ClearSETUP();
+    const DEVICE_TO_HOST: u8 = (REQDIR_DEVICETOHOST | REQTYPE_CLASS | REQREC_INTERFACE) as u8;
+    const HOST_TO_DEVICE: u8 = (REQDIR_HOSTTODEVICE | REQTYPE_CLASS | REQREC_INTERFACE) as u8;
 
-                    match USB_CONTROL_REQUEST.w_index {
-                        c if c == InterfaceDescriptors::Keyboard as u16 => {
-                            let keyboard_report = UsbKeyboardReportData::default();
-
-                            Endpoint_Write_Control_Stream_LE(
-                                &keyboard_report as *const _ as *const c_void,
-                                size_of::<UsbKeyboardReportData>() as u16,
-                            );
-                        }
-                        c if c == InterfaceDescriptors::Mouse as u16 => {
-                            let mouse_report = UsbMouseReportData::default();
-
-                            Endpoint_Write_Control_Stream_LE(
-                                &mouse_report as *const _ as *const c_void,
-                                size_of::<UsbMouseReportData>() as u16,
-                            );
-                        }
-                        _ => panic!(),
-                    }
-                    Endpoint_ClearOUT();
+    unsafe {
+        let request_type = USB_CONTROL_REQUEST.bm_request_type;
+        let interface = USB_CONTROL_REQUEST.w_index as usize;
+        if interface == InterfaceDescriptors::Console as usize {
+            match console() {
+                Console::Disabled => return,
+                Console::Cdc => {
+                    cdc_control_request();
+                    return;
                 }
-                code if code == HidClassRequests::HidReqSetReport as u8 => {
-                    Endpoint_ClearSETUP();
-
-                    let mut timeout = 10000;
-                    while !Endpoint_IsOUTReceived() && timeout > 0 {
-                        if USB_DEVICE_STATE == UsbDeviceStates::DeviceStateUnattached as u8 {
-                            return;
-                        }
-                        timeout -= 1;
+                // The HID console answers the HID class requests as the other HID interfaces
+                Console::Hid => {}
+            }
+        }
+        if interface >= HID_INTERFACE_COUNT {
+            return;
+        }
+        let [report_id, report_type] = USB_CONTROL_REQUEST.w_value.to_le_bytes();
+
+        match USB_CONTROL_REQUEST.b_request {
+            code if code == HidClassRequests::HidReqGetReport as u8
+                && request_type == DEVICE_TO_HOST =>
+            {
+                // Raw HID and console input reports only go through their interrupt endpoint
+                if interface > InterfaceDescriptors::Mouse as usize {
+                    return;
+                }
+                if interface == InterfaceDescriptors::Mouse as usize {
+                    let mut report = [0; HID_ENDPOINT_SIZE as usize];
+                    if let Some(size) = mouse_get_report(report_type, report_id, &mut report) {
+                        Endpoint_ClearSETUP();
+                        Endpoint_Write_Control_Stream_LE(report.as_ptr() as *const c_void, size);
+                        Endpoint_ClearOUT();
                     }
-                    if !Endpoint_IsOUTReceived() {
+                    return;
+                }
+                if report_type != HID_REPORT_ITEM_IN || report_id != 0 {
+                    return;
+                }
+                Endpoint_ClearSETUP();
+                Endpoint_Write_Control_Stream_LE(
+                    &raw const KEYBOARD_REPORT_DATA as *const c_void,
+                    size_of::<UsbKeyboardReportData>() as u16,
+                );
+                Endpoint_ClearOUT();
+            }
+            code if code == HidClassRequests::HidReqSetReport as u8
+                && request_type == HOST_TO_DEVICE =>
+            {
+                if report_type == HID_REPORT_ITEM_FEATURE
+                    && interface == InterfaceDescriptors::Mouse as usize
+                {
+                    if report_id != mouse_report_id() {
                         return;
                     }
-
-                    Endpoint_ClearOUT();
-                    Endpoint_ClearStatusStage();
-                }
-                code if code == HidClassRequests::HidReqGetProtocol as u8 => {
+                    // Numbered reports start with their ID
+                    let start = mouse_interface_numbered() as usize;
+                    let mut report = [0; 2];
                     Endpoint_ClearSETUP();
-                    Endpoint_Write_8(USING_REPORT_PROTOCOL as u8);
+                    Endpoint_Read_Control_Stream_LE(
+                        report.as_mut_ptr() as *mut c_void,
+                        1 + start as u16,
+                    );
                     Endpoint_ClearIN();
-                    Endpoint_ClearStatusStage();
+                    MOUSE_RESOLUTION_MULTIPLIER = report[start];
+                    return;
+                }
+                if report_type != HID_REPORT_ITEM_OUT {
+                    return;
                 }
-                code if code == HidClassRequests::HidReqSetProtocol as u8 => {
+                if interface == InterfaceDescriptors::RawHid as usize {
                     Endpoint_ClearSETUP();
-                    Endpoint_ClearStatusStage();
-                    USING_REPORT_PROTOCOL = USB_CONTROL_REQUEST.w_value != 0;
+                    Endpoint_Read_Control_Stream_LE(
+                        &raw mut RAW_HID_RECEIVED as *mut c_void,
+                        RAW_HID_REPORT_SIZE as u16,
+                    );
+                    Endpoint_ClearIN();
+                    RAW_HID_PENDING = true;
+                    return;
                 }
+                // Only the keyboard and the Raw HID interface have an output report
+                if interface != InterfaceDescriptors::Keyboard as usize {
+                    return;
+                }
+                Endpoint_ClearSETUP();
 
-                code if code == HidClassRequests::HidReqSetIdle as u8 => {
-                    Endpoint_ClearSETUP();
-                    Endpoint_ClearStatusStage();
-                    IDLE_COUNT = (USB_CONTROL_REQUEST.w_value & 0xFF00) >> 6;
+                let mut timeout = 10000;
+                while !Endpoint_IsOUTReceived() && timeout > 0 {
+                    if USB_DEVICE_STATE == UsbDeviceStates::DeviceStateUnattached as u8 {
+                        return;
+                    }
+                    timeout -= 1;
                 }
-                code if code == HidClassRequests::HidReqGetIdle as u8 => {
-                    Endpoint_ClearSETUP();
-                    Endpoint_Write_8((IDLE_COUNT >> 2) as u8);
-                    Endpoint_ClearIN();
-                    Endpoint_ClearStatusStage();
+                if !Endpoint_IsOUTReceived() {
+                    return;
                 }
-                _ => {}
+
+                KEYBOARD_LEDS = Endpoint_Read_8();
+                Endpoint_ClearOUT();
+                Endpoint_ClearStatusStage();
+            }
+            code if code == HidClassRequests::HidReqGetProtocol as u8
+                && request_type == DEVICE_TO_HOST =>
+            {
+                Endpoint_ClearSETUP();
+                Endpoint_Write_8(USING_REPORT_PROTOCOL[interface] as u8);
+                Endpoint_ClearIN();
+                Endpoint_ClearStatusStage();
+            }
+            code if code == HidClassRequests::HidReqSetProtocol as u8
+                && request_type == HOST_TO_DEVICE =>
+            {
+                Endpoint_ClearSETUP();
+                Endpoint_ClearStatusStage();
+                USING_REPORT_PROTOCOL[interface] = USB_CONTROL_REQUEST.w_value != 0;
+            }
+            code if code == HidClassRequests::HidReqSetIdle as u8
+                && request_type == HOST_TO_DEVICE =>
+            {
+                Endpoint_ClearSETUP();
+                Endpoint_ClearStatusStage();
+                // The duration is in the high byte, in units of 4 ms
+                IDLE_COUNT[interface] = (USB_CONTROL_REQUEST.w_value >> 8) * 4;
+                IDLE_MS_REMAINING[interface] = IDLE_COUNT[interface];
+            }
+            code if code == HidClassRequests::HidReqGetIdle as u8
+                && request_type == DEVICE_TO_HOST =>
+            {
+                Endpoint_ClearSETUP();
+                Endpoint_Write_8((IDLE_COUNT[interface] / 4) as u8);
+                Endpoint_ClearIN();
+                Endpoint_ClearStatusStage();
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Returns the LEDs state of the host, as a bit field: Num Lock, Caps Lock, Scroll Lock, Compose, Kana.
+pub fn keyboard_leds() -> u8 {
+    unsafe { KEYBOARD_LEDS }
+}
+
+/// Reads the next Raw HID report sent by the host, if one was received.
+///
+/// Reports from the host come through the Raw HID OUT endpoint, or through SET_REPORT requests
+/// on the control endpoint, the only way with the CDC console.
+pub fn raw_hid_read() -> Option<[u8; RAW_HID_REPORT_SIZE as usize]> {
+    if console() != Console::Cdc
+        && let Some(report) = raw_hid_read_endpoint()
+    {
+        return Some(report);
+    }
+    unsafe {
+        if !RAW_HID_PENDING {
+            return None;
+        }
+        RAW_HID_PENDING = false;
+        Some(RAW_HID_RECEIVED)
+    }
+}
+
+/// Reads the next Raw HID report of the OUT endpoint, if one was received.
+fn raw_hid_read_endpoint() -> Option<[u8; RAW_HID_REPORT_SIZE as usize]> {
+    if unsafe { USB_DEVICE_STATE } != UsbDeviceStates::DeviceStateConfigured as u8 {
+        return None;
+    }
+    unsafe {
+        Endpoint_SelectEndpoint(RAW_HID_OUT_ENDPOINT_ADDR);
+        if !Endpoint_IsOUTReceived() {
+            return None;
+        }
+        let mut data = [0; RAW_HID_REPORT_SIZE as usize];
+        let received = Endpoint_IsReadWriteAllowed();
+        if received {
+            Endpoint_Read_Stream_LE(
+                data.as_mut_ptr() as *mut c_void,
+                RAW_HID_REPORT_SIZE as u16,
+                null_mut(),
+            );
+        }
+        // Acknowledge the packet, even an empty one
+        Endpoint_ClearOUT();
+        received.then_some(data)
+    }
+}
+
+/// Sends a Raw HID report to the host.
+///
+/// Returns `false` if the device isn't configured, or if the host didn't read the previous report in time.
+pub fn raw_hid_send(data: &[u8; RAW_HID_REPORT_SIZE as usize]) -> bool {
+    if unsafe { USB_DEVICE_STATE } != UsbDeviceStates::DeviceStateConfigured as u8 {
+        return false;
+    }
+    unsafe {
+        Endpoint_SelectEndpoint(RAW_HID_IN_ENDPOINT_ADDR);
+        let mut tries = RAW_HID_SEND_TRIES;
+        while !Endpoint_IsReadWriteAllowed() {
+            if tries == 0 {
+                return false;
             }
+            tries -= 1;
+        }
+        Endpoint_Write_Stream_LE(
+            data.as_ptr() as *const c_void,
+            RAW_HID_REPORT_SIZE as u16,
+            null_mut(),
+        );
+        Endpoint_ClearIN();
+    }
+    true
+}
+
+/// Size of the mouse report, reduced to the boot report in boot protocol.
+fn mouse_report_size() -> u16 {
+    if unsafe { USING_REPORT_PROTOCOL[InterfaceDescriptors::Mouse as usize] } {
+        size_of::<UsbMouseReportData>() as u16
+    } else {
+        BOOT_MOUSE_REPORT_SIZE
+    }
+}
+
+/// ID of the mouse report in the control requests, 0 when the reports aren't numbered.
+fn mouse_report_id() -> u8 {
+    if mouse_interface_numbered() {
+        MOUSE_REPORT_ID
+    } else {
+        0
+    }
+}
+
+/// Returns true if the reports sent through the mouse endpoint start with their ID.
+/// The boot protocol report never does.
+fn mouse_reports_numbered() -> bool {
+    mouse_interface_numbered()
+        && unsafe { USING_REPORT_PROTOCOL[InterfaceDescriptors::Mouse as usize] }
+}
+
+/// Copies a report of the mouse interface in `report`, after its ID when the reports are numbered.
+///
+/// Returns the size of the report, `None` if the interface has no such report.
+fn mouse_get_report(
+    report_type: u8,
+    report_id: u8,
+    report: &mut [u8; HID_ENDPOINT_SIZE as usize],
+) -> Option<u16> {
+    const {
+        // Each report must fit in the endpoint after its ID
+        assert!(1 + size_of::<UsbMouseReportData>() <= HID_ENDPOINT_SIZE as usize);
+        assert!(1 + size_of::<UsbAbsolutePointerReportData>() <= HID_ENDPOINT_SIZE as usize);
+        assert!(1 + size_of::<UsbGamepadReportData>() <= HID_ENDPOINT_SIZE as usize);
+    };
+    let (data, size) = match report_type {
+        HID_REPORT_ITEM_IN if report_id == mouse_report_id() => (
+            (&raw const MOUSE_REPORT_DATA).cast::<u8>(),
+            mouse_report_size(),
+        ),
+        HID_REPORT_ITEM_IN
+            if absolute_pointer_enabled() && report_id == ABSOLUTE_POINTER_REPORT_ID =>
+        {
+            (
+                (&raw const ABSOLUTE_POINTER_REPORT_DATA).cast(),
+                size_of::<UsbAbsolutePointerReportData>() as u16,
+            )
+        }
+        HID_REPORT_ITEM_IN if gamepad_enabled() && report_id == GAMEPAD_REPORT_ID => (
+            (&raw const GAMEPAD_REPORT_DATA).cast(),
+            size_of::<UsbGamepadReportData>() as u16,
+        ),
+        HID_REPORT_ITEM_FEATURE if report_id == mouse_report_id() => {
+            (&raw const MOUSE_RESOLUTION_MULTIPLIER, 1)
+        }
+        _ => return None,
+    };
+    let start = mouse_interface_numbered() as usize;
+    report[0] = report_id;
+    unsafe { copy_nonoverlapping(data, report[start..].as_mut_ptr(), size as usize) };
+    Some(start as u16 + size)
+}
+
+/// Returns true if the idle period of an interface expired, restarting it.
+fn idle_period_expired(interface: InterfaceDescriptors) -> bool {
+    let interface = interface as usize;
+    unsafe {
+        if IDLE_COUNT[interface] != 0 && IDLE_MS_REMAINING[interface] == 0 {
+            IDLE_MS_REMAINING[interface] = IDLE_COUNT[interface];
+            true
+        } else {
+            false
         }
     }
 }
@@ -173,9 +564,16 @@ static mut KEYBOARD_REPORT_DATA: UsbKeyboardReportData = UsbKeyboardReportData {
     reserved: 0,
 };
 static mut MOUSE_REPORT_DATA: UsbMouseReportData = UsbMouseReportData::default();
+static mut ABSOLUTE_POINTER_REPORT_DATA: UsbAbsolutePointerReportData =
+    UsbAbsolutePointerReportData::default();
+static mut GAMEPAD_REPORT_DATA: UsbGamepadReportData = UsbGamepadReportData::default();
 
 static mut KEYBOARD_REPORT_DATA_UPDATED: bool = false;
 static mut MOUSE_REPORT_DATA_UPDATED: bool = false;
+static mut ABSOLUTE_POINTER_REPORT_DATA_UPDATED: bool = false;
+static mut GAMEPAD_REPORT_DATA_UPDATED: bool = false;
+/// ID of the last report sent through the mouse endpoint, the pending reports taking turns.
+static mut LAST_MOUSE_REPORT_ID: u8 = MOUSE_REPORT_ID;
 
 /// Adds a keycode to the keyboard report.
 ///
@@ -237,14 +635,46 @@ pub fn toggle_code(code: u8) {
     }
 }
 
-/// Set the current delta value of the vertical wheel.
-pub fn set_vertical_wheel_delta(value: i8) {
+/// Returns the wheel units of a detent, vertical then horizontal: [WHEEL_RESOLUTION_MULTIPLIER]
+/// once the host enabled the high resolution scrolling, 1 otherwise.
+pub fn wheel_resolution() -> (i8, i8) {
+    let multiplier = unsafe { MOUSE_RESOLUTION_MULTIPLIER };
+    let resolution = |bits: u8| {
+        if bits != 0 {
+            WHEEL_RESOLUTION_MULTIPLIER as i8
+        } else {
+            1
+        }
+    };
+    (
+        resolution(multiplier & 0b11),
+        resolution((multiplier >> 2) & 0b11),
+    )
+}
+
+/// Set the current delta value of the vertical wheel, in detents.
+pub fn set_vertical_wheel_delta(detents: i8) {
+    let value = detents.saturating_mul(wheel_resolution().0);
     if unsafe { MOUSE_REPORT_DATA.v } != value {
         unsafe { MOUSE_REPORT_DATA.v = value };
         unsafe { MOUSE_REPORT_DATA_UPDATED = true };
     }
 }
 
+/// Set the current delta values of the vertical and horizontal wheels, in units of [wheel_resolution].
+pub fn set_wheel_delta(v: i8, h: i8) {
+    if unsafe { MOUSE_REPORT_DATA.v } != v || unsafe { MOUSE_REPORT_DATA.h } != h {
+        unsafe { MOUSE_REPORT_DATA.v = v };
+        unsafe { MOUSE_REPORT_DATA.h = h };
+        unsafe { MOUSE_REPORT_DATA_UPDATED = true };
+    }
+}
+
+/// Get the current delta values of the vertical and horizontal wheels, in units of [wheel_resolution].
+pub fn get_wheel_delta() -> (i8, i8) {
+    unsafe { (MOUSE_REPORT_DATA.v, MOUSE_REPORT_DATA.h) }
+}
+
 /// Set the current delta value of the movement of the mouse.
 pub fn set_mouse_delta(x: i8, y: i8) {
     if unsafe { MOUSE_REPORT_DATA.x } != x || unsafe { MOUSE_REPORT_DATA.y } != y {
@@ -259,6 +689,47 @@ pub fn get_mouse_delta() -> (i8, i8) {
     unsafe { (MOUSE_REPORT_DATA.x, MOUSE_REPORT_DATA.y) }
 }
 
+/// Moves the pointer to an absolute position, from 0 to [ABSOLUTE_POINTER_MAX] on each axis,
+/// (0, 0) being the top left corner of the screen.
+///
+/// Needs [Keyboard::ABSOLUTE_POINTER](crate::Keyboard::ABSOLUTE_POINTER), the position is dropped otherwise.
+pub fn set_pointer_position(x: u16, y: u16) {
+    unsafe {
+        ABSOLUTE_POINTER_REPORT_DATA.x = x.min(ABSOLUTE_POINTER_MAX);
+        ABSOLUTE_POINTER_REPORT_DATA.y = y.min(ABSOLUTE_POINTER_MAX);
+        ABSOLUTE_POINTER_REPORT_DATA_UPDATED = true;
+    }
+}
+
+/// Sets an axis of the gamepad, from -127 to 127.
+///
+/// Needs [Keyboard::GAMEPAD](crate::Keyboard::GAMEPAD), the axis is dropped otherwise.
+pub fn set_gamepad_axis(axis: GamepadAxis, value: i8) {
+    unsafe {
+        let value = value.max(-127);
+        if GAMEPAD_REPORT_DATA.axes[axis as usize] != value {
+            GAMEPAD_REPORT_DATA.axes[axis as usize] = value;
+            GAMEPAD_REPORT_DATA_UPDATED = true;
+        }
+    }
+}
+
+/// Presses a button of the gamepad, from 0 to 7.
+pub fn gamepad_button_press(button: u8) {
+    unsafe {
+        GAMEPAD_REPORT_DATA.buttons |= 1 << button;
+        GAMEPAD_REPORT_DATA_UPDATED = true;
+    }
+}
+
+/// Releases a button of the gamepad, from 0 to 7.
+pub fn gamepad_button_release(button: u8) {
+    unsafe {
+        GAMEPAD_REPORT_DATA.buttons &= !(1 << button);
+        GAMEPAD_REPORT_DATA_UPDATED = true;
+    }
+}
+
 // Mouse clicks
 pub fn mouse_left_click_press() {
     unsafe { MOUSE_REPORT_DATA.button |= 0b1 };
@@ -290,42 +761,98 @@ pub fn mouse_wheel_click_release() {
     unsafe { MOUSE_REPORT_DATA_UPDATED = true };
 }
 
+/// Presses a mouse button, from 0 for the left one to 7.
+pub fn mouse_button_press(button: u8) {
+    unsafe { MOUSE_REPORT_DATA.button |= 1 << button };
+    unsafe { MOUSE_REPORT_DATA_UPDATED = true };
+}
+
+/// Releases a mouse button, from 0 for the left one to 7.
+pub fn mouse_button_release(button: u8) {
+    unsafe { MOUSE_REPORT_DATA.button &= !(1 << button) };
+    unsafe { MOUSE_REPORT_DATA_UPDATED = true };
+}
+
 /// Sends the next mouse HID report if needed.
 pub fn send_next_mouse_report() {
     if unsafe { USB_DEVICE_STATE } != UsbDeviceStates::DeviceStateConfigured as u8 {
         return;
     }
     unsafe {
-        let send_report = if IDLE_COUNT != 0 && IDLE_MS_REMAINING == 0 {
-            IDLE_MS_REMAINING = IDLE_COUNT;
-            true
-        } else {
-            (MOUSE_REPORT_DATA.x != 0
-                || MOUSE_REPORT_DATA.y != 0
-                || MOUSE_REPORT_DATA.v != 0
-                || MOUSE_REPORT_DATA.h != 0)
-                || MOUSE_REPORT_DATA_UPDATED
-        };
+        let send_report = idle_period_expired(InterfaceDescriptors::Mouse)
+            || MOUSE_REPORT_DATA.x != 0
+            || MOUSE_REPORT_DATA.y != 0
+            || MOUSE_REPORT_DATA.v != 0
+            || MOUSE_REPORT_DATA.h != 0
+            || MOUSE_REPORT_DATA_UPDATED;
+
+        let send_absolute_report = ABSOLUTE_POINTER_REPORT_DATA_UPDATED
+            && absolute_pointer_enabled()
+            && mouse_reports_numbered();
+        let send_gamepad_report =
+            GAMEPAD_REPORT_DATA_UPDATED && gamepad_enabled() && mouse_reports_numbered();
 
         // Select the mouse endpoint
         Endpoint_SelectEndpoint(MOUSE_IN_ENDPOINT_ADDR);
 
-        if Endpoint_IsReadWriteAllowed() && send_report {
-            Endpoint_Write_Stream_LE(
-                &MOUSE_REPORT_DATA as *const _ as *const c_void,
-                size_of::<UsbMouseReportData>() as u16,
-                null_mut(),
-            );
-
-            Endpoint_ClearIN();
-
-            MOUSE_REPORT_DATA_UPDATED = false;
-            // Reset scroll and movement values
-            MOUSE_REPORT_DATA.v = 0;
-            MOUSE_REPORT_DATA.h = 0;
-            MOUSE_REPORT_DATA.x = 0;
-            MOUSE_REPORT_DATA.y = 0;
+        if !Endpoint_IsReadWriteAllowed() {
+            return;
+        }
+        // Start after the last report sent, so that a continuous motion doesn't starve the others
+        let Some(report_id) = (1..=3)
+            .map(|turn| (LAST_MOUSE_REPORT_ID + turn - 1) % 3 + 1)
+            .find(|report_id| match *report_id {
+                MOUSE_REPORT_ID => send_report,
+                ABSOLUTE_POINTER_REPORT_ID => send_absolute_report,
+                _ => send_gamepad_report,
+            })
+        else {
+            return;
+        };
+        match report_id {
+            MOUSE_REPORT_ID => {
+                if mouse_reports_numbered() {
+                    Endpoint_Write_8(MOUSE_REPORT_ID);
+                }
+                Endpoint_Write_Stream_LE(
+                    &MOUSE_REPORT_DATA as *const _ as *const c_void,
+                    mouse_report_size(),
+                    null_mut(),
+                );
+
+                Endpoint_ClearIN();
+
+                MOUSE_REPORT_DATA_UPDATED = false;
+                // Reset scroll and movement values
+                MOUSE_REPORT_DATA.v = 0;
+                MOUSE_REPORT_DATA.h = 0;
+                MOUSE_REPORT_DATA.x = 0;
+                MOUSE_REPORT_DATA.y = 0;
+            }
+            ABSOLUTE_POINTER_REPORT_ID => {
+                Endpoint_Write_8(ABSOLUTE_POINTER_REPORT_ID);
+                Endpoint_Write_Stream_LE(
+                    &raw const ABSOLUTE_POINTER_REPORT_DATA as *const c_void,
+                    size_of::<UsbAbsolutePointerReportData>() as u16,
+                    null_mut(),
+                );
+                Endpoint_ClearIN();
+
+                ABSOLUTE_POINTER_REPORT_DATA_UPDATED = false;
+            }
+            _ => {
+                Endpoint_Write_8(GAMEPAD_REPORT_ID);
+                Endpoint_Write_Stream_LE(
+                    &raw const GAMEPAD_REPORT_DATA as *const c_void,
+                    size_of::<UsbGamepadReportData>() as u16,
+                    null_mut(),
+                );
+                Endpoint_ClearIN();
+
+                GAMEPAD_REPORT_DATA_UPDATED = false;
+            }
         }
+        LAST_MOUSE_REPORT_ID = report_id;
     }
 }
 
@@ -335,12 +862,8 @@ pub fn send_next_keyboard_report() {
         return;
     }
     unsafe {
-        let send_report = if IDLE_COUNT != 0 && IDLE_MS_REMAINING == 0 {
-            IDLE_MS_REMAINING = IDLE_COUNT;
-            true
-        } else {
-            KEYBOARD_REPORT_DATA_UPDATED
-        };
+        let send_report =
+            idle_period_expired(InterfaceDescriptors::Keyboard) || KEYBOARD_REPORT_DATA_UPDATED;
 
         // Select the keyboard endpoint
         Endpoint_SelectEndpoint(KEYBOARD_IN_ENDPOINT_ADDR);
@@ -355,6 +878,7 @@ pub fn send_next_keyboard_report() {
             );
 
             Endpoint_ClearIN();
+            profiling::report_sent();
         }
     }
 }
@@ -0,0 +1,165 @@
+//! This module implements the VIA protocol over Raw HID, used by configurators to edit the dynamic keymap.
+//! It is enabled with [Keyboard::DYNAMIC_KEYMAP]. Commands are answered in place, by sending back
+//! the received report. Commands it doesn't know are passed to [Keyboard::raw_hid_receive].
+//!
+//! The VIA matrix is the layout of [Keyboard::KEYMAP]: on split keyboards, each row holds the row
+//! of the left half followed by the one of the right half.
+
+use crate::{
+    Keyboard, OmkKeyboard,
+    dynamic_keymap::{eeprom_read, eeprom_update, keymap_address},
+    timer::timer_read,
+    usb::RAW_HID_REPORT_SIZE,
+};
+
+/// Version of the VIA protocol implemented.
+const VIA_PROTOCOL_VERSION: u16 = 0x000C;
+
+const ID_GET_PROTOCOL_VERSION: u8 = 0x01;
+const ID_GET_KEYBOARD_VALUE: u8 = 0x02;
+const ID_SET_KEYBOARD_VALUE: u8 = 0x03;
+const ID_DYNAMIC_KEYMAP_GET_KEYCODE: u8 = 0x04;
+const ID_DYNAMIC_KEYMAP_SET_KEYCODE: u8 = 0x05;
+const ID_DYNAMIC_KEYMAP_RESET: u8 = 0x06;
+const ID_EEPROM_RESET: u8 = 0x0A;
+const ID_BOOTLOADER_JUMP: u8 = 0x0B;
+const ID_DYNAMIC_KEYMAP_MACRO_GET_COUNT: u8 = 0x0C;
+const ID_DYNAMIC_KEYMAP_MACRO_GET_BUFFER_SIZE: u8 = 0x0D;
+const ID_DYNAMIC_KEYMAP_MACRO_GET_BUFFER: u8 = 0x0E;
+const ID_DYNAMIC_KEYMAP_MACRO_SET_BUFFER: u8 = 0x0F;
+const ID_DYNAMIC_KEYMAP_MACRO_RESET: u8 = 0x10;
+const ID_DYNAMIC_KEYMAP_GET_LAYER_COUNT: u8 = 0x11;
+const ID_DYNAMIC_KEYMAP_GET_BUFFER: u8 = 0x12;
+const ID_DYNAMIC_KEYMAP_SET_BUFFER: u8 = 0x13;
+/// Answer to a command that can't be handled.
+const ID_UNHANDLED: u8 = 0xFF;
+
+const ID_UPTIME: u8 = 0x01;
+const ID_LAYOUT_OPTIONS: u8 = 0x02;
+const ID_SWITCH_MATRIX_STATE: u8 = 0x03;
+const ID_FIRMWARE_VERSION: u8 = 0x04;
+
+/// Index of the data in the buffer commands, after the command, the offset and the size.
+const BUFFER_DATA_START: usize = 4;
+/// Maximum size of the data of a buffer command.
+const BUFFER_DATA_MAX: usize = RAW_HID_REPORT_SIZE as usize - BUFFER_DATA_START;
+
+/// Copies between the EEPROM area `[start, start + size[` and the report, for the buffer commands.
+fn via_buffer(data: &mut [u8; RAW_HID_REPORT_SIZE as usize], start: u16, size: u16, write: bool) {
+    let offset = u16::from_be_bytes([data[1], data[2]]);
+    let length = (data[3] as usize)
+        .min(BUFFER_DATA_MAX)
+        .min(size.saturating_sub(offset) as usize);
+    for i in 0..length {
+        let address = start + offset + i as u16;
+        if write {
+            eeprom_update(address, data[BUFFER_DATA_START + i]);
+        } else {
+            data[BUFFER_DATA_START + i] = eeprom_read(address);
+        }
+    }
+}
+
+impl<User: Keyboard> OmkKeyboard<User> {
+    /// Keys per row of the VIA matrix.
+    const VIA_COLUMNS: usize = User::MATRIX_KEYS_COUNT / User::ROWS_PER_HAND;
+
+    /// Handles a VIA command, the answer replacing the report.
+    ///
+    /// Returns `false` if the command isn't a VIA one, the report being left untouched.
+    pub(crate) fn via_command(&mut self, data: &mut [u8; RAW_HID_REPORT_SIZE as usize]) -> bool {
+        match data[0] {
+            ID_GET_PROTOCOL_VERSION => {
+                [data[1], data[2]] = VIA_PROTOCOL_VERSION.to_be_bytes();
+            }
+            ID_GET_KEYBOARD_VALUE => match data[1] {
+                ID_UPTIME => {
+                    [data[2], data[3], data[4], data[5]] = timer_read().to_be_bytes();
+                }
+                ID_LAYOUT_OPTIONS | ID_FIRMWARE_VERSION => data[2..6].fill(0),
+                ID_SWITCH_MATRIX_STATE => self.via_matrix_state(&mut data[2..]),
+                _ => data[0] = ID_UNHANDLED,
+            },
+            // There are no layout options nor device indication to set
+            ID_SET_KEYBOARD_VALUE => {}
+            ID_DYNAMIC_KEYMAP_GET_KEYCODE => {
+                if let Some(index) = Self::via_key_index(data[1], data[2], data[3]) {
+                    [data[4], data[5]] = Self::get_keycode(data[1], index).to_be_bytes();
+                }
+            }
+            ID_DYNAMIC_KEYMAP_SET_KEYCODE => {
+                if let Some(index) = Self::via_key_index(data[1], data[2], data[3]) {
+                    Self::set_keycode(data[1], index, u16::from_be_bytes([data[4], data[5]]));
+                }
+            }
+            ID_DYNAMIC_KEYMAP_RESET => Self::dynamic_keymap_reset(),
+            ID_EEPROM_RESET => {
+                Self::dynamic_keymap_reset();
+                Self::dynamic_keymap_macro_reset();
+            }
+            ID_BOOTLOADER_JUMP => avr_base::reset_to_bootloader(),
+            ID_DYNAMIC_KEYMAP_MACRO_GET_COUNT => data[1] = User::DYNAMIC_KEYMAP_MACRO_COUNT,
+            ID_DYNAMIC_KEYMAP_MACRO_GET_BUFFER_SIZE => {
+                [data[1], data[2]] = User::DYNAMIC_KEYMAP_MACRO_BUFFER_SIZE.to_be_bytes();
+            }
+            ID_DYNAMIC_KEYMAP_MACRO_GET_BUFFER => via_buffer(
+                data,
+                Self::macros_address(),
+                User::DYNAMIC_KEYMAP_MACRO_BUFFER_SIZE,
+                false,
+            ),
+            ID_DYNAMIC_KEYMAP_MACRO_SET_BUFFER => via_buffer(
+                data,
+                Self::macros_address(),
+                User::DYNAMIC_KEYMAP_MACRO_BUFFER_SIZE,
+                true,
+            ),
+            ID_DYNAMIC_KEYMAP_MACRO_RESET => Self::dynamic_keymap_macro_reset(),
+            ID_DYNAMIC_KEYMAP_GET_LAYER_COUNT => data[1] = User::LAYER_COUNT as u8,
+            ID_DYNAMIC_KEYMAP_GET_BUFFER => {
+                via_buffer(data, keymap_address(), Self::DYNAMIC_KEYMAP_SIZE, false)
+            }
+            ID_DYNAMIC_KEYMAP_SET_BUFFER => {
+                via_buffer(data, keymap_address(), Self::DYNAMIC_KEYMAP_SIZE, true)
+            }
+            _ => return false,
+        }
+        true
+    }
+
+    /// Returns the index in a keymap layer of a key of the VIA matrix, `None` if out of it.
+    fn via_key_index(layer: u8, row: u8, column: u8) -> Option<usize> {
+        let (row, column) = (row as usize, column as usize);
+        (layer < User::LAYER_COUNT as u8 && row < User::ROWS_PER_HAND && column < Self::VIA_COLUMNS)
+            .then_some(row * Self::VIA_COLUMNS + column)
+    }
+
+    /// Writes the pressed keys of the VIA matrix, each row being a big endian bit field.
+    fn via_matrix_state(&self, data: &mut [u8]) {
+        let row_bytes = Self::VIA_COLUMNS.div_ceil(8);
+        for via_row in 0..User::ROWS_PER_HAND {
+            for via_column in 0..Self::VIA_COLUMNS {
+                // Columns past the ones of the left half are the right half ones
+                let (row, column) = if via_column < User::MATRIX_COLUMNS {
+                    (via_row, via_column)
+                } else {
+                    (
+                        via_row + User::ROWS_PER_HAND,
+                        via_column - User::MATRIX_COLUMNS,
+                    )
+                };
+                let byte = via_row * row_bytes + row_bytes - 1 - via_column / 8;
+                if byte >= data.len() {
+                    return;
+                }
+                let mut mask: User::MatrixRowType = 1.into();
+                mask <<= column as u8;
+                if self.current_matrix[row] & mask != 0.into() {
+                    data[byte] |= 1 << (via_column % 8);
+                } else {
+                    data[byte] &= !(1 << (via_column % 8));
+                }
+            }
+        }
+    }
+}